@@ -0,0 +1,150 @@
+use vector_lib::Vector3D;
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// STRUCT DEFINITION //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Axis-aligned bounding box spanned by its minimum and maximum corners
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct AABB<T: DataTypeTraits>
+{
+    pub minimum: Vector3D<T>,
+    pub maximum: Vector3D<T>,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////// INITIALIZATION IMPL  /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Implementing AABB<T> initialization through <T>::new()
+impl<T: DataTypeTraits> AABB<T>
+{
+    pub fn new(minimum: Vector3D<T>, maximum: Vector3D<T>) -> Self {
+        AABB { minimum: minimum, maximum: maximum }
+    }
+}
+
+// Implementing AABB<T> initialization through <T>::empty(). The empty box contains nothing and is
+// the identity of `surrounding_box`.
+impl<T: DataTypeTraits> AABB<T>
+{
+    pub fn empty() -> Self {
+        AABB { minimum: Vector3D { x: T::infinity(), y: T::infinity(), z: T::infinity() },
+               maximum: Vector3D { x: T::neg_infinity(), y: T::neg_infinity(), z: T::neg_infinity() } }
+    }
+}
+
+// Implementing AABB<T> initialization from a set of points through <T>::from_points()
+impl<T: DataTypeTraits> AABB<T>
+{
+    pub fn from_points(points: &[Vector3D<T>]) -> Self {
+        points.iter().fold(AABB::empty(), |bbox, point| bbox.expand(point))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// SPECIAL IMPLS ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> AABB<T> {
+    // Smallest box containing both boxes
+    #[inline(always)]
+    pub fn surrounding_box(&self, other: &AABB<T>) -> AABB<T> {
+        AABB { minimum: Vector3D { x: self.minimum.x.min(other.minimum.x),
+                                   y: self.minimum.y.min(other.minimum.y),
+                                   z: self.minimum.z.min(other.minimum.z) },
+               maximum: Vector3D { x: self.maximum.x.max(other.maximum.x),
+                                   y: self.maximum.y.max(other.maximum.y),
+                                   z: self.maximum.z.max(other.maximum.z) } }
+    }
+
+    // Smallest box containing this box and `point`
+    #[inline(always)]
+    pub fn expand(&self, point: &Vector3D<T>) -> AABB<T> {
+        self.surrounding_box(&AABB { minimum: *point, maximum: *point })
+    }
+
+    // Box grown by `delta` on every side. Used to give flat boxes (e.g. of planar shapes) a volume.
+    #[inline(always)]
+    pub fn pad(&self, delta: T) -> AABB<T> {
+        let padding = Vector3D { x: delta, y: delta, z: delta };
+        AABB { minimum: self.minimum - padding, maximum: self.maximum + padding }
+    }
+
+    // Box grown symmetrically along the axes on which it is thinner than `min_thickness`. Flat
+    // boxes (e.g. of axis-aligned triangles) would otherwise be missed by the slab test.
+    #[inline(always)]
+    pub fn ensure_thickness(&self, min_thickness: T) -> AABB<T> {
+        let half = T::from(0.5).unwrap();
+        let d = self.extent();
+        let grow = |extent: T| if extent < min_thickness { (min_thickness - extent) * half } else { T::zero() };
+        let padding = Vector3D { x: grow(d.x), y: grow(d.y), z: grow(d.z) };
+        AABB { minimum: self.minimum - padding, maximum: self.maximum + padding }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.minimum.x > self.maximum.x || self.minimum.y > self.maximum.y || self.minimum.z > self.maximum.z
+    }
+
+    #[inline(always)]
+    pub fn centroid(&self) -> Vector3D<T> {
+        (self.minimum + self.maximum) * T::from(0.5).unwrap()
+    }
+
+    #[inline(always)]
+    pub fn extent(&self) -> Vector3D<T> {
+        self.maximum - self.minimum
+    }
+
+    #[inline(always)]
+    pub fn surface_area(&self) -> T {
+        if self.is_empty() {
+            return T::zero();
+        }
+        let d = self.extent();
+        T::from(2.0).unwrap() * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    // Index (0 = x, 1 = y, 2 = z) of the axis along which the box is largest
+    #[inline(always)]
+    pub fn longest_axis(&self) -> usize {
+        let d = self.extent();
+        if d.x >= d.y && d.x >= d.z { 0 } else if d.y >= d.z { 1 } else { 2 }
+    }
+
+    // Slab test: true if the ray passes through the box for some t in ]t_min, t_max[
+    #[inline(always)]
+    pub fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T) -> bool {
+        let mut t_min = t_min;
+        let mut t_max = t_max;
+        for axis in 0..3 {
+            let inverse_direction = T::one() / axis_value(&ray.direction, axis);
+            let mut t0 = (axis_value(&self.minimum, axis) - axis_value(&ray.origin, axis)) * inverse_direction;
+            let mut t1 = (axis_value(&self.maximum, axis) - axis_value(&ray.origin, axis)) * inverse_direction;
+            if inverse_direction < T::zero() {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            // Written so that NaNs (ray in the plane of a slab) keep the current interval
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Component of `vector` along `axis` (0 = x, 1 = y, 2 = z)
+#[inline(always)]
+pub fn axis_value<T: DataTypeTraits>(vector: &Vector3D<T>, axis: usize) -> T {
+    match axis {
+        0 => vector.x,
+        1 => vector.y,
+        _ => vector.z,
+    }
+}
@@ -0,0 +1,324 @@
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+use aabb_lib::{axis_value, AABB};
+
+use hittable_material_traits::{HitRecord, Hittable};
+
+// Number of buckets the centroids are binned into when evaluating the surface area heuristic
+const SAH_BUCKETS: usize = 16;
+// Leaves never hold more objects than this (unless all centroids coincide)
+const MAX_LEAF_SIZE: usize = 4;
+// Cost of traversing an interior node relative to intersecting one object
+const TRAVERSAL_COST: f64 = 0.125;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// STRUCT DEFINITIONS //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Node of the flattened tree. Interior nodes have `count == 0`, their left child is the next node
+// in the array and `offset` is the index of their right child. Leaves cover the objects
+// `offset..offset + count`.
+#[derive(Clone, Copy)]
+struct BVHNode<T: DataTypeTraits>
+{
+    bbox: AABB<T>,
+    offset: usize,
+    count: usize,
+    axis: usize,
+}
+
+// Bounding volume hierarchy over a set of objects. Objects without a bounding box are kept aside
+// and tested against every ray.
+pub struct BVH<T: DataTypeTraits>
+{
+    nodes: Vec<BVHNode<T>>,
+    objects: Vec<Box<dyn Hittable<T> + Send + Sync>>,
+    unbounded: Vec<Box<dyn Hittable<T> + Send + Sync>>,
+}
+
+// Bookkeeping for an object while the tree is being built
+struct BuildItem<T: DataTypeTraits>
+{
+    index: usize,
+    bbox: AABB<T>,
+    centroid: [T; 3],
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////// INITIALIZATION IMPL  /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Implementing BVH<T> initialization through <T>::new()
+impl<T: DataTypeTraits> BVH<T>
+{
+    pub fn new(objects: Vec<Box<dyn Hittable<T> + Send + Sync>>) -> Self {
+        let mut bounded: Vec<Option<Box<dyn Hittable<T> + Send + Sync>>> = Vec::new();
+        let mut unbounded = Vec::new();
+        let mut items = Vec::new();
+        for object in objects {
+            match object.bounding_box() {
+                Some(bbox) => {
+                    let centroid = bbox.centroid();
+                    items.push(BuildItem { index: bounded.len(), bbox: bbox, centroid: [centroid.x, centroid.y, centroid.z] });
+                    bounded.push(Some(object));
+                }
+                None => unbounded.push(object),
+            }
+        }
+
+        let mut nodes = Vec::with_capacity(2 * items.len());
+        if !items.is_empty() {
+            let count = items.len();
+            build_recursive(&mut items, 0, count, &mut nodes);
+        }
+        // Reorder the objects so every leaf refers to a contiguous range
+        let objects = items.iter().map(|item| bounded[item.index].take().unwrap()).collect();
+        BVH { nodes: nodes, objects: objects, unbounded: unbounded }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////// BUILDING //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Builds the subtree over items[start..end] and returns the index of its root node
+fn build_recursive<T: DataTypeTraits>(items: &mut [BuildItem<T>], start: usize, end: usize,
+                                      nodes: &mut Vec<BVHNode<T>>) -> usize {
+    let node_index = nodes.len();
+    let bbox = items[start..end].iter().fold(AABB::empty(), |bbox, item| bbox.surrounding_box(&item.bbox));
+    nodes.push(BVHNode { bbox: bbox, offset: start, count: end - start, axis: 0 });
+
+    let count = end - start;
+    if count == 1 {
+        return node_index;
+    }
+
+    let centroid_bounds = items[start..end].iter().fold(AABB::empty(), |bounds, item| {
+        bounds.expand(&vector_lib::Vector3D { x: item.centroid[0], y: item.centroid[1], z: item.centroid[2] })
+    });
+    let axis = centroid_bounds.longest_axis();
+    let axis_min = axis_value(&centroid_bounds.minimum, axis);
+    let axis_extent = axis_value(&centroid_bounds.maximum, axis) - axis_min;
+
+    let mid = if axis_extent <= T::zero() {
+        // All centroids coincide: no split can separate the objects
+        if count <= MAX_LEAF_SIZE {
+            return node_index;
+        }
+        start + count / 2
+    } else {
+        match sah_split(&items[start..end], &bbox, axis, axis_min, axis_extent) {
+            Some(bucket) => {
+                let bucket_of = |item: &BuildItem<T>| bucket_index(item.centroid[axis], axis_min, axis_extent);
+                start + partition(&mut items[start..end], |item| bucket_of(item) <= bucket)
+            }
+            None => return node_index,
+        }
+    };
+
+    build_recursive(items, start, mid, nodes);
+    let right = build_recursive(items, mid, end, nodes);
+    nodes[node_index].offset = right;
+    nodes[node_index].count = 0;
+    nodes[node_index].axis = axis;
+    node_index
+}
+
+// Finds the cheapest bucket boundary along `axis` according to the surface area heuristic. Returns
+// the last bucket of the left child, or None if keeping the objects in a leaf is cheaper.
+fn sah_split<T: DataTypeTraits>(items: &[BuildItem<T>], bbox: &AABB<T>, axis: usize,
+                                axis_min: T, axis_extent: T) -> Option<usize> {
+    let mut bucket_boxes = [AABB::empty(); SAH_BUCKETS];
+    let mut bucket_counts = [0_usize; SAH_BUCKETS];
+    for item in items {
+        let bucket = bucket_index(item.centroid[axis], axis_min, axis_extent);
+        bucket_counts[bucket] += 1;
+        bucket_boxes[bucket] = bucket_boxes[bucket].surrounding_box(&item.bbox);
+    }
+
+    // Sweep from the right to get the area and count of every right-hand side
+    let mut right_areas = [0.0_f64; SAH_BUCKETS];
+    let mut right_counts = [0_usize; SAH_BUCKETS];
+    let mut right_box = AABB::empty();
+    let mut right_count = 0;
+    for bucket in (1..SAH_BUCKETS).rev() {
+        right_box = right_box.surrounding_box(&bucket_boxes[bucket]);
+        right_count += bucket_counts[bucket];
+        right_areas[bucket] = right_box.surface_area().to_f64().unwrap();
+        right_counts[bucket] = right_count;
+    }
+
+    let parent_area = bbox.surface_area().to_f64().unwrap();
+    let mut best_cost = f64::INFINITY;
+    let mut best_bucket = 0;
+    let mut left_box = AABB::empty();
+    let mut left_count = 0;
+    for bucket in 0..SAH_BUCKETS - 1 {
+        left_box = left_box.surrounding_box(&bucket_boxes[bucket]);
+        left_count += bucket_counts[bucket];
+        if left_count == 0 || right_counts[bucket + 1] == 0 {
+            continue;
+        }
+        let cost = left_box.surface_area().to_f64().unwrap() * left_count as f64
+                 + right_areas[bucket + 1] * right_counts[bucket + 1] as f64;
+        if cost < best_cost {
+            best_cost = cost;
+            best_bucket = bucket;
+        }
+    }
+    if best_cost == f64::INFINITY {
+        return None;
+    }
+
+    let split_cost = TRAVERSAL_COST + if parent_area > 0.0 { best_cost / parent_area } else { 0.0 };
+    let leaf_cost = items.len() as f64;
+    if items.len() <= MAX_LEAF_SIZE && leaf_cost <= split_cost {
+        return None;
+    }
+    Some(best_bucket)
+}
+
+#[inline(always)]
+fn bucket_index<T: DataTypeTraits>(value: T, axis_min: T, axis_extent: T) -> usize {
+    let bucket = ((value - axis_min) / axis_extent * T::from(SAH_BUCKETS).unwrap()).to_usize().unwrap_or(0);
+    bucket.min(SAH_BUCKETS - 1)
+}
+
+// In-place partition; returns the number of items for which `predicate` holds (now at the front)
+fn partition<T: DataTypeTraits, F: Fn(&BuildItem<T>) -> bool>(items: &mut [BuildItem<T>], predicate: F) -> usize {
+    let mut first = 0;
+    for i in 0..items.len() {
+        if predicate(&items[i]) {
+            items.swap(first, i);
+            first += 1;
+        }
+    }
+    first
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// SPECIAL IMPLS ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> BVH<T> {
+    pub fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for BVH<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let mut hit_record_temp = HitRecord::default();
+        let mut hit_anything = false;
+        let mut closest_so_far = t_max;
+
+        for object in self.unbounded.iter() {
+            if object.hit(ray, t_min, closest_so_far, &mut hit_record_temp) {
+                hit_anything = true;
+                closest_so_far = hit_record_temp.get_t();
+                *hit_record = hit_record_temp.clone();
+            }
+        }
+        if self.nodes.is_empty() {
+            return hit_anything;
+        }
+
+        let direction_is_negative = [ray.direction.x < T::zero(), ray.direction.y < T::zero(), ray.direction.z < T::zero()];
+        let mut stack: Vec<usize> = Vec::with_capacity(64);
+        stack.push(0);
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bbox.hit(ray, t_min, closest_so_far) {
+                continue;
+            }
+            if node.count > 0 {
+                for object in self.objects[node.offset..node.offset + node.count].iter() {
+                    if object.hit(ray, t_min, closest_so_far, &mut hit_record_temp) {
+                        hit_anything = true;
+                        closest_so_far = hit_record_temp.get_t();
+                        *hit_record = hit_record_temp.clone();
+                    }
+                }
+            } else if direction_is_negative[node.axis] {
+                // Visit the child closer to the ray origin first
+                stack.push(node_index + 1);
+                stack.push(node.offset);
+            } else {
+                stack.push(node.offset);
+                stack.push(node_index + 1);
+            }
+        }
+        hit_anything
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        if !self.unbounded.is_empty() {
+            return None;
+        }
+        self.nodes.first().map(|root| root.bbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use vector_lib::{Vector3D, VectorOperations};
+    use color_lib::RGBColor;
+    use sphere_lib::Sphere;
+    use planar_lib::Plane;
+    use material_lib::Lambertian;
+
+    // Random spheres (some of them sharing a center) and two planes, which have no bounding box
+    fn random_objects(seed: u64) -> Vec<Box<dyn Hittable<f64> + Send + Sync>> {
+        let rng = fastrand::Rng::with_seed(seed);
+        let material = Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 }));
+        let point = || Vector3D { x: rng.f64() * 20.0 - 10.0, y: rng.f64() * 20.0 - 10.0, z: rng.f64() * 20.0 - 10.0 };
+        let mut objects: Vec<Box<dyn Hittable<f64> + Send + Sync>> = Vec::new();
+        for index in 0..300 {
+            let center = if index % 30 == 0 { Vector3D { x: 1.0, y: 1.0, z: 1.0 } } else { point() };
+            objects.push(Box::new(Sphere::new(center, 0.1 + (index % 7) as f64 * 0.15, material.clone())));
+        }
+        objects.push(Box::new(Plane::new(Vector3D { x: 0.0, y: -12.0, z: 0.0 }, Vector3D { x: 0.0, y: 1.0, z: 0.0 }, material.clone())));
+        objects.push(Box::new(Plane::new(Vector3D { x: 12.0, y: 0.0, z: 0.0 }, Vector3D { x: -1.0, y: 0.2, z: 0.1 }, material)));
+        objects
+    }
+
+    #[test]
+    fn closest_hits_match_linear_scan() {
+        let bvh = BVH::new(random_objects(3));
+        let objects = random_objects(3);
+        assert_eq!(bvh.len(), objects.len());
+        let rng = fastrand::Rng::with_seed(11);
+        let value = || rng.f64() * 30.0 - 15.0;
+        for _ in 0..5000 {
+            let ray = Ray3D { origin: Vector3D { x: value(), y: value(), z: value() },
+                              direction: Vector3D { x: value(), y: value(), z: value() },
+                              time: 0.0 };
+            let mut expected = HitRecord::default();
+            let mut closest = f64::INFINITY;
+            let mut temp = HitRecord::default();
+            for object in objects.iter() {
+                if object.hit(&ray, 1e-4, closest, &mut temp) {
+                    closest = temp.get_t();
+                    expected = temp.clone();
+                }
+            }
+            let mut found = HitRecord::default();
+            let hit = bvh.hit(&ray, 1e-4, f64::INFINITY, &mut found);
+            assert_eq!(hit, closest.is_finite());
+            if hit {
+                assert!((found.get_t() - expected.get_t()).abs() < 1e-9, "{} != {}", found.get_t(), expected.get_t());
+                assert!((found.get_point() - expected.get_point()).norm() < 1e-9);
+            }
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use vector_lib::Vector3D;
 use vector_lib::DataTypeTraits;
+use vector_lib::VectorOperations;
 
 use ray_lib::Ray3D;
 
+use sampler_lib::Sampler;
+
 
 //  The following struct implements a simple camera using the axis-aligned camera
 pub struct Camera<T: DataTypeTraits>
@@ -15,7 +18,16 @@ pub struct Camera<T: DataTypeTraits>
     origin: Vector3D<T>,
     horizontal: Vector3D<T>,
     vertical: Vector3D<T>,
-    lower_left_corner: Vector3D<T>
+    lower_left_corner: Vector3D<T>,
+
+    // Right and up directions of the image, spanning the lens, and radius of the lens aperture
+    u: Vector3D<T>,
+    v: Vector3D<T>,
+    lens_radius: T,
+
+    // Rays are sent at uniformly random instants in [shutter_open, shutter_close]
+    shutter_open: T,
+    shutter_close: T,
 }
 
 impl<T: DataTypeTraits> Camera<T>
@@ -32,7 +44,91 @@ impl<T: DataTypeTraits> Camera<T>
               vertical: Vector3D{x: T::zero(), y: viewport_height, z: T::zero()},
               lower_left_corner: origin - Vector3D{x: aspect_ratio * viewport_height, y: T::zero(), z: T::zero()} / T::from(2.0).unwrap()
                      - Vector3D{x: T::zero(), y: viewport_height, z: T::zero()} / T::from(2.0).unwrap()
-                     - Vector3D{x: T::zero(), y: T::zero(), z: focal_length} }
+                     - Vector3D{x: T::zero(), y: T::zero(), z: focal_length},
+
+              u: Vector3D{x: T::one(), y: T::zero(), z: T::zero()},
+              v: Vector3D{x: T::zero(), y: T::one(), z: T::zero()},
+              lens_radius: T::zero(),
+
+              shutter_open: T::zero(),
+              shutter_close: T::zero() }
+    }
+}
+
+/// Creates a camera positioned at `look_from`, pointing towards `look_at`.
+///
+/// # Arguments
+///
+/// * `look_from` - The position of the camera.
+/// * `look_at` - The point the camera is looking at (projected onto the center of the image).
+/// * `view_up` - The "up" direction of the world. Its projection onto the image plane becomes the
+///               vertical direction of the image, so it only needs to not be parallel to the
+///               viewing direction.
+/// * `vertical_fov` - The vertical field of view in degrees.
+/// * `aspect_ratio` - The ratio between the width and height of the image.
+impl<T: DataTypeTraits> Camera<T>
+{
+    pub fn look_at(look_from: Vector3D<T>, look_at: Vector3D<T>, view_up: Vector3D<T>,
+                   vertical_fov: T, aspect_ratio: T) -> Self {
+        Self::thin_lens(look_from, look_at, view_up, vertical_fov, aspect_ratio, T::zero(), T::one())
+    }
+}
+
+/// Creates a positionable camera with a thin lens, giving depth of field (defocus blur).
+///
+/// # Arguments
+///
+/// * `look_from`, `look_at`, `view_up`, `vertical_fov`, `aspect_ratio` - As for `Camera::look_at`.
+/// * `aperture` - The diameter of the lens. Rays originate from random points on the lens disk, so
+///                the larger the aperture, the stronger the blur of everything out of focus. An
+///                aperture of zero gives a pinhole camera with everything in focus.
+/// * `focus_distance` - The distance from `look_from` to the plane that is in perfect focus.
+impl<T: DataTypeTraits> Camera<T>
+{
+    pub fn thin_lens(look_from: Vector3D<T>, look_at: Vector3D<T>, view_up: Vector3D<T>,
+                     vertical_fov: T, aspect_ratio: T, aperture: T, focus_distance: T) -> Self {
+        let two = T::from(2.0).unwrap();
+        let theta = utilities_lib::degrees_to_radians(vertical_fov);
+        let viewport_height = two * (theta / two).tan();
+        let viewport_width = aspect_ratio * viewport_height;
+        let focal_length = T::one();
+
+        // Orthonormal basis of the camera; the camera looks along -w
+        let w = (look_from - look_at).unit_vector();
+        let u = view_up.cross_product(&w).unit_vector();
+        let v = w.cross_product(&u);
+
+        // The image plane is placed in the focus plane, so rays through it from any point on the
+        // lens meet there
+        let horizontal = u * (viewport_width * focus_distance);
+        let vertical = v * (viewport_height * focus_distance);
+        Self{ aspect_ratio: aspect_ratio,
+              viewport_height: viewport_height,
+              viewport_width: viewport_width,
+              focal_length: focal_length,
+
+              origin: look_from,
+              horizontal: horizontal,
+              vertical: vertical,
+              lower_left_corner: look_from - horizontal / two - vertical / two - w * (focal_length * focus_distance),
+
+              u: u,
+              v: v,
+              lens_radius: aperture / two,
+
+              shutter_open: T::zero(),
+              shutter_close: T::zero() }
+    }
+}
+
+/// Opens the shutter of the camera from time `shutter_open` to `shutter_close`, giving motion blur
+/// to the objects that move in that interval. By default the shutter only opens at time zero.
+impl<T: DataTypeTraits> Camera<T>
+{
+    pub fn with_shutter(mut self, shutter_open: T, shutter_close: T) -> Self {
+        self.shutter_open = shutter_open;
+        self.shutter_close = shutter_close.max(shutter_open);
+        self
     }
 }
 
@@ -44,15 +140,29 @@ impl<T: DataTypeTraits> Camera<T>
 ///         left edge of the image plane and 1 corresponds to the right edge.
 /// * `v` - The vertical coordinate of the pixel in the range [0, 1], where 0 corresponds to the
 ///         bottom edge of the image plane and 1 corresponds to the top edge.
+/// * `sampler` - The sampler the lens position and the time are drawn from.
 ///
 /// # Returns
 ///
-/// A `Ray3D` object originating from the camera's position and traveling in the direction of the
-/// specified pixel on the image plane.
+/// A `Ray3D` object originating from a random point on the camera's lens (the camera's position
+/// for a pinhole camera) and traveling through the specified pixel on the image plane, at a random
+/// time while the shutter is open.
 impl<T: DataTypeTraits> Camera<T>
 {
-    pub fn get_ray(&self, u: T, v: T) -> Ray3D<T> {
-        Ray3D {origin: self.origin,
-               direction: self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin}
+    pub fn get_ray(&self, u: T, v: T, sampler: &mut dyn Sampler<T>) -> Ray3D<T> {
+        let offset = if self.lens_radius > T::zero() {
+            let lens_point = sampler.sample_disk() * self.lens_radius;
+            self.u * lens_point.x + self.v * lens_point.y
+        } else {
+            Vector3D::zeros()
+        };
+        let time = if self.shutter_close > self.shutter_open {
+            self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.get_1d()
+        } else {
+            self.shutter_open
+        };
+        Ray3D {origin: self.origin + offset,
+               direction: self.lower_left_corner + self.horizontal * u + self.vertical * v - self.origin - offset,
+               time: time}
     }
 }
\ No newline at end of file
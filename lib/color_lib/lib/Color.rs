@@ -1,5 +1,3 @@
-use rand::distributions::{Distribution, Uniform};
-
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////// DATA TRAIT DEFINITIONS ///////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -97,18 +95,17 @@ impl<T: DataTypeTraits> RGBColor<T>
 }
 
 
-// Implementing RGBColor<T> with random uniform in [low,high[ initialization through <T>::random_uniform()
+// Implementing RGBColor<T> with random uniform in [low,high[ initialization through <T>::random_uniform(), drawn from `rng`
 impl<T: DataTypeTraits> RGBColor<T>
 {
-    pub fn random_uniform(lower_bound: T, upper_bound: T) -> RGBColor<T> {
+    pub fn random_uniform(lower_bound: T, upper_bound: T, rng: &mut fastrand::Rng) -> RGBColor<T> {
         if upper_bound <= lower_bound {
             panic!("Upper bound cannot be less than or equal to lower bound");
         }
-        let distribution = Uniform::try_from(lower_bound..upper_bound).unwrap();
-        let mut rng = rand::thread_rng();
-        RGBColor { R: distribution.sample(&mut rng),
-            G: distribution.sample(&mut rng),
-            B: distribution.sample(&mut rng)}
+        let draw = || lower_bound + T::from(rng.f64()).unwrap() * (upper_bound - lower_bound);
+        RGBColor { R: draw(),
+            G: draw(),
+            B: draw()}
     }
 }
 //++++++++++++++++++++++++++++++++++++++++ Addition ++++++++++++++++++++++++++++++++++++++++++++ //
@@ -0,0 +1,225 @@
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// STRUCT DEFINITION //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// In-memory framebuffer storing, for every pixel, the sum of the radiance samples taken so far and
+// the number of samples. Pixels are stored row-major with (0, 0) being the top left corner.
+#[derive(Clone)]
+pub struct Film<T: DataTypeTraits>
+{
+    width: usize,
+    height: usize,
+    sums: Vec<RGBColor<T>>,
+    counts: Vec<u32>,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////// INITIALIZATION IMPL  /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Implementing Film<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Film<T>
+{
+    pub fn new(width: usize, height: usize) -> Self {
+        Film { width: width,
+               height: height,
+               sums: vec![RGBColor::zeros(); width * height],
+               counts: vec![0; width * height] }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////// GETTER & SETTER IMPLS ////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> Film<T> {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get_sum(&self, x: usize, y: usize) -> RGBColor<T> {
+        self.sums[self.index(x, y)]
+    }
+
+    pub fn get_count(&self, x: usize, y: usize) -> u32 {
+        self.counts[self.index(x, y)]
+    }
+
+    #[inline(always)]
+    fn index(&self, x: usize, y: usize) -> usize {
+        assert!(x < self.width && y < self.height,
+                "Pixel ({}, {}) is outside the {}x{} film", x, y, self.width, self.height);
+        y * self.width + x
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// SPECIAL IMPLS ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> Film<T> {
+    // Accumulates a single radiance sample in pixel (x, y)
+    #[inline(always)]
+    pub fn add_sample(&mut self, x: usize, y: usize, color: RGBColor<T>) {
+        let index = self.index(x, y);
+        self.sums[index] = self.sums[index] + color;
+        self.counts[index] += 1;
+    }
+
+    // Accumulates an already summed set of `count` samples in pixel (x, y)
+    #[inline(always)]
+    pub fn add_samples(&mut self, x: usize, y: usize, color_sum: RGBColor<T>, count: u32) {
+        let index = self.index(x, y);
+        self.sums[index] = self.sums[index] + color_sum;
+        self.counts[index] += count;
+    }
+
+    // Mean radiance of pixel (x, y). Pixels without samples are black.
+    #[inline(always)]
+    pub fn pixel(&self, x: usize, y: usize) -> RGBColor<T> {
+        let index = self.index(x, y);
+        if self.counts[index] == 0 {
+            return RGBColor::zeros();
+        }
+        self.sums[index] / T::from(self.counts[index]).unwrap()
+    }
+
+    // Mean radiance of all pixels (row-major, top row first)
+    pub fn resolve(&self) -> Vec<RGBColor<T>> {
+        self.sums.iter().zip(self.counts.iter())
+            .map(|(sum, count)| if *count == 0 { RGBColor::zeros() } else { *sum / T::from(*count).unwrap() })
+            .collect()
+    }
+
+    // New film holding the pixels in the window [x0, x1[ x [y0, y1[ of this film. Panics if the
+    // window is not inside the film, so callers taking windows from users must check them first.
+    pub fn crop(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> Film<T> {
+        assert!(x0 <= x1 && x1 <= self.width && y0 <= y1 && y1 <= self.height,
+                "Crop window [{}, {}[ x [{}, {}[ is outside the {}x{} film", x0, x1, y0, y1, self.width, self.height);
+        let mut cropped = Film::new(x1 - x0, y1 - y0);
+        for y in y0..y1 {
+            let source = y * self.width;
+            let target = (y - y0) * cropped.width;
+            cropped.sums[target..target + cropped.width].copy_from_slice(&self.sums[source + x0..source + x1]);
+            cropped.counts[target..target + cropped.width].copy_from_slice(&self.counts[source + x0..source + x1]);
+        }
+        cropped
+    }
+
+    // Adds the samples of `other` to this film, placing the top left corner of `other` at pixel
+    // (x0, y0). Used to merge rendered tiles or additional passes into a full film. Panics if
+    // `other` does not fit in this film at (x0, y0).
+    pub fn merge(&mut self, other: &Film<T>, x0: usize, y0: usize) {
+        assert!(x0 + other.width <= self.width && y0 + other.height <= self.height,
+                "Film of {}x{} at ({}, {}) does not fit in the {}x{} film",
+                other.width, other.height, x0, y0, self.width, self.height);
+        for y in 0..other.height {
+            for x in 0..other.width {
+                let source = y * other.width + x;
+                let target = (y0 + y) * self.width + x0 + x;
+                self.sums[target] = self.sums[target] + other.sums[source];
+                self.counts[target] += other.counts[source];
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(value: f64) -> RGBColor<f64> {
+        RGBColor { R: value, G: 2.0 * value, B: 3.0 * value }
+    }
+
+    // Film whose pixel (x, y) holds x + 1 samples summing to color(10 y + x)
+    fn numbered_film(width: usize, height: usize) -> Film<f64> {
+        let mut film = Film::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                film.add_samples(x, y, color((10 * y + x) as f64), x as u32 + 1);
+            }
+        }
+        film
+    }
+
+    #[test]
+    fn resolve_averages_the_samples() {
+        let mut film = Film::new(3, 2);
+        film.add_sample(0, 0, color(1.0));
+        film.add_sample(0, 0, color(2.0));
+        film.add_samples(2, 1, color(6.0), 4);
+        let pixels = film.resolve();
+        assert_eq!(pixels.len(), 6);
+        assert!(pixels[0] == color(1.5) && pixels[5] == color(1.5));
+        // Pixels without samples are black
+        assert!(pixels[1..5].iter().all(|pixel| *pixel == RGBColor::zeros()));
+        for (index, pixel) in pixels.iter().enumerate() {
+            assert!(*pixel == film.pixel(index % 3, index / 3));
+        }
+    }
+
+    #[test]
+    fn crop_keeps_the_window() {
+        let film = numbered_film(5, 4);
+        let cropped = film.crop(1, 2, 4, 4);
+        assert_eq!((cropped.width(), cropped.height()), (3, 2));
+        for y in 0..2 {
+            for x in 0..3 {
+                assert!(cropped.get_sum(x, y) == film.get_sum(x + 1, y + 2));
+                assert_eq!(cropped.get_count(x, y), film.get_count(x + 1, y + 2));
+            }
+        }
+        let whole = film.crop(0, 0, 5, 4);
+        assert!(whole.resolve().iter().zip(film.resolve().iter()).all(|(a, b)| a == b));
+        let empty = film.crop(5, 4, 5, 4);
+        assert_eq!((empty.width(), empty.height()), (0, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the 5x4 film")]
+    fn crop_outside_the_film_panics() {
+        numbered_film(5, 4).crop(2, 0, 6, 4);
+    }
+
+    #[test]
+    fn merge_adds_the_samples_in_place() {
+        let mut film = numbered_film(5, 4);
+        let tile = numbered_film(2, 3);
+        film.merge(&tile, 3, 1);
+        for y in 0..4 {
+            for x in 0..5 {
+                let (sum, count) = (color((10 * y + x) as f64), x as u32 + 1);
+                if (3..5).contains(&x) && (1..4).contains(&y) {
+                    let (tile_x, tile_y) = (x - 3, y - 1);
+                    assert!(film.get_sum(x, y) == sum + tile.get_sum(tile_x, tile_y));
+                    assert_eq!(film.get_count(x, y), count + tile.get_count(tile_x, tile_y));
+                } else {
+                    assert!(film.get_sum(x, y) == sum);
+                    assert_eq!(film.get_count(x, y), count);
+                }
+            }
+        }
+        // Merging the crops of a film back into an empty one rebuilds it
+        let original = numbered_film(5, 4);
+        let mut rebuilt = Film::new(5, 4);
+        for (x0, y0, x1, y1) in [(0, 0, 5, 1), (0, 1, 2, 4), (2, 1, 5, 4)] {
+            rebuilt.merge(&original.crop(x0, y0, x1, y1), x0, y0);
+        }
+        assert!(rebuilt.resolve().iter().zip(original.resolve().iter()).all(|(a, b)| a == b));
+    }
+
+    #[test]
+    #[should_panic(expected = "does not fit in the 5x4 film")]
+    fn merge_outside_the_film_panics() {
+        numbered_film(5, 4).merge(&numbered_film(2, 2), 4, 0);
+    }
+}
@@ -0,0 +1,428 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use gltf::mesh::Mode;
+
+use vector_lib::{Vector2D, Vector3D, VectorOperations};
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+use camera_lib::Camera;
+
+use material_lib::{Dielectric, Lambertian, Metal};
+
+use texture_lib::VertexColor;
+
+use mesh_lib::{MeshData, TriangleMesh};
+
+use hittable_material_traits::Material;
+
+/*
+    Imports the default scene of a glTF 2.0 file (.gltf with external or embedded buffers, or .glb).
+    The node tree is walked and every mesh instance becomes a triangle mesh with the transforms of
+    its node and ancestors baked into the vertices. Perspective cameras are kept with their world
+    placement; orthographic cameras are skipped since the renderer has no such projection.
+
+    Metallic-roughness materials are mapped onto
+      - Dielectric when they transmit light (KHR_materials_transmission factor above 0.5, or a
+        blended base color with alpha below 0.5), using the KHR_materials_ior index (default 1.5),
+      - Metal when the metallic factor is at least 0.5, with the roughness as fuzz,
+      - Lambertian otherwise, with the base color factor times the COLOR_0 vertex colors as albedo.
+    Textures are not read.
+ */
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// ERROR TYPE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum GltfError {
+    Gltf(gltf::Error),
+    Invalid(String),
+}
+
+impl std::fmt::Display for GltfError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            GltfError::Gltf(e) => write!(f, "{}", e),
+            GltfError::Invalid(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Gltf(e) => Some(e),
+            GltfError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<gltf::Error> for GltfError {
+    fn from(e: gltf::Error) -> Self {
+        GltfError::Gltf(e)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// STRUCT DEFINITIONS //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Perspective camera of a glTF scene, placed in world space
+#[derive(Clone, Debug)]
+pub struct GltfCamera<T: DataTypeTraits>
+{
+    pub name: Option<String>,
+    pub look_from: Vector3D<T>,
+    pub look_at: Vector3D<T>,
+    pub view_up: Vector3D<T>,
+    pub vertical_fov: T, // Degrees
+    pub aspect_ratio: Option<T>, // Aspect ratio the camera was authored for, if given
+}
+
+impl<T: DataTypeTraits> GltfCamera<T> {
+    // Camera rendering an image with the given aspect ratio
+    pub fn to_camera(&self, aspect_ratio: T) -> Camera<T> {
+        Camera::look_at(self.look_from, self.look_at, self.view_up, self.vertical_fov, aspect_ratio)
+    }
+}
+
+// Everything imported from a glTF file
+pub struct GltfScene<T: DataTypeTraits>
+{
+    pub meshes: Vec<TriangleMesh<T>>,
+    pub cameras: Vec<GltfCamera<T>>,
+}
+
+// Column-major 4x4 matrix, as stored by glTF
+type Matrix = [[f64; 4]; 4];
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////// TRANSFORMS ////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+fn to_matrix(matrix: [[f32; 4]; 4]) -> Matrix {
+    matrix.map(|column| column.map(|value| value as f64))
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[0.0; 4]; 4];
+    for (column, b_column) in b.iter().enumerate() {
+        for row in 0..4 {
+            product[column][row] = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    product
+}
+
+fn transform_point(matrix: &Matrix, point: [f64; 3]) -> [f64; 3] {
+    let mut result = [matrix[3][0], matrix[3][1], matrix[3][2]];
+    for (row, value) in result.iter_mut().enumerate() {
+        *value += (0..3).map(|k| matrix[k][row] * point[k]).sum::<f64>();
+    }
+    result
+}
+
+fn transform_direction(matrix: &Matrix, direction: [f64; 3]) -> [f64; 3] {
+    let mut result = [0.0; 3];
+    for (row, value) in result.iter_mut().enumerate() {
+        *value = (0..3).map(|k| matrix[k][row] * direction[k]).sum();
+    }
+    result
+}
+
+// Matrix taking normals along with `matrix`: the cofactor matrix of its linear part, which is the
+// inverse transpose up to the determinant. Returned with the sign of the determinant.
+fn normal_matrix(matrix: &Matrix) -> (Matrix, f64) {
+    let m = |row: usize, column: usize| matrix[column][row];
+    let mut cofactors = [[0.0; 4]; 4];
+    for row in 0..3 {
+        for column in 0..3 {
+            let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+            let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+            cofactors[column][row] = m(r0, c0) * m(r1, c1) - m(r0, c1) * m(r1, c0);
+        }
+    }
+    let determinant: f64 = (0..3).map(|column| m(0, column) * cofactors[column][0]).sum();
+    (cofactors, determinant)
+}
+
+fn to_vector<T: DataTypeTraits>(values: [f64; 3]) -> Vector3D<T> {
+    Vector3D { x: T::from(values[0]).unwrap(), y: T::from(values[1]).unwrap(), z: T::from(values[2]).unwrap() }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// IMPORT ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// State shared while walking the node tree
+struct Importer<'a, T: DataTypeTraits>
+{
+    buffers: &'a [gltf::buffer::Data],
+    // Materials by glTF material index (None for the default material)
+    materials: HashMap<Option<usize>, Arc<dyn Material<T> + Send + Sync>>,
+    scene: GltfScene<T>,
+}
+
+/// Reads the glTF file at `path`. Buffers are taken from the binary chunk of .glb files, from
+/// data URIs or from files next to `path`; nothing is fetched over the network.
+pub fn load_gltf<T: DataTypeTraits>(path: &Path) -> Result<GltfScene<T>, GltfError> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+    let scene = document.default_scene().or_else(|| document.scenes().next())
+        .ok_or_else(|| GltfError::Invalid("file contains no scene".to_string()))?;
+
+    let mut importer = Importer { buffers: &buffers, materials: HashMap::new(),
+                                  scene: GltfScene { meshes: Vec::new(), cameras: Vec::new() } };
+    let identity = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+    for node in scene.nodes() {
+        importer.visit(&node, &identity)?;
+    }
+    Ok(importer.scene)
+}
+
+impl<'a, T: DataTypeTraits> Importer<'a, T> {
+    fn visit(&mut self, node: &gltf::Node, parent: &Matrix) -> Result<(), GltfError> {
+        let world = multiply(parent, &to_matrix(node.transform().matrix()));
+        if let Some(mesh) = node.mesh() {
+            if let Some(mesh) = self.import_mesh(&mesh, &world)? {
+                self.scene.meshes.push(mesh);
+            }
+        }
+        if let Some(camera) = node.camera() {
+            if let gltf::camera::Projection::Perspective(perspective) = camera.projection() {
+                // glTF cameras look down their local -z axis with +y up
+                let direction = transform_direction(&world, [0.0, 0.0, -1.0]);
+                let look_from = transform_point(&world, [0.0, 0.0, 0.0]);
+                self.scene.cameras.push(GltfCamera {
+                    name: camera.name().map(|name| name.to_string()),
+                    look_from: to_vector(look_from),
+                    look_at: to_vector([look_from[0] + direction[0], look_from[1] + direction[1], look_from[2] + direction[2]]),
+                    view_up: to_vector(transform_direction(&world, [0.0, 1.0, 0.0])),
+                    vertical_fov: T::from((perspective.yfov() as f64).to_degrees()).unwrap(),
+                    aspect_ratio: perspective.aspect_ratio().map(|ratio| T::from(ratio).unwrap()),
+                });
+            }
+        }
+        for child in node.children() {
+            self.visit(&child, &world)?;
+        }
+        Ok(())
+    }
+
+    // Builds the primitives of `mesh` into a single triangle mesh, None if it has no triangles
+    fn import_mesh(&mut self, mesh: &gltf::Mesh, world: &Matrix) -> Result<Option<TriangleMesh<T>>, GltfError> {
+        let (normal_matrix, determinant) = normal_matrix(world);
+        let mesh_name = mesh.name().map_or_else(|| format!("mesh {}", mesh.index()), |name| format!("mesh `{}`", name));
+        let mut data: MeshData<T> = MeshData { positions: Vec::new(), normals: Vec::new(), uvs: Vec::new(), colors: Vec::new(),
+                                               triangles: Vec::new(), material_indices: Vec::new(), materials: Vec::new() };
+        let has_normals = mesh.primitives().any(|primitive| primitive.get(&gltf::Semantic::Normals).is_some());
+        let has_uvs = mesh.primitives().any(|primitive| primitive.get(&gltf::Semantic::TexCoords(0)).is_some());
+        let has_colors = mesh.primitives().any(|primitive| primitive.get(&gltf::Semantic::Colors(0)).is_some());
+
+        for primitive in mesh.primitives() {
+            if !matches!(primitive.mode(), Mode::Triangles | Mode::TriangleStrip | Mode::TriangleFan) {
+                continue;
+            }
+            let reader = primitive.reader(|buffer| Some(&self.buffers[buffer.index()]));
+            let positions: Vec<[f32; 3]> = match reader.read_positions() {
+                Some(positions) => positions.collect(),
+                None => return Err(GltfError::Invalid(format!("{} has a primitive without positions", mesh_name))),
+            };
+            let count = positions.len();
+            let first = data.positions.len() as u32;
+
+            data.positions.extend(positions.iter().map(|position| to_vector(transform_point(world, position.map(|value| value as f64)))));
+            if has_normals {
+                match reader.read_normals() {
+                    Some(normals) => data.normals.extend(normals.map(|normal| {
+                        let normal = transform_direction(&normal_matrix, normal.map(|value| value as f64)).map(|value| value * determinant.signum());
+                        let normal: Vector3D<T> = to_vector(normal);
+                        let norm = normal.norm();
+                        if norm > T::zero() { normal / norm } else { normal }
+                    })),
+                    // Zero normals are treated as missing by the mesh
+                    None => data.normals.extend(std::iter::repeat(Vector3D::default()).take(count)),
+                }
+            }
+            if has_uvs {
+                match reader.read_tex_coords(0) {
+                    Some(uvs) => data.uvs.extend(uvs.into_f32().map(|uv| Vector2D { x: T::from(uv[0]).unwrap(), y: T::from(uv[1]).unwrap() })),
+                    None => data.uvs.extend(std::iter::repeat(Vector2D::default()).take(count)),
+                }
+            }
+            let base_color = primitive.material().pbr_metallic_roughness().base_color_factor();
+            if has_colors {
+                let tint = |color: [f32; 3]| RGBColor { R: T::from(color[0] * base_color[0]).unwrap(),
+                                                        G: T::from(color[1] * base_color[1]).unwrap(),
+                                                        B: T::from(color[2] * base_color[2]).unwrap() };
+                match reader.read_colors(0) {
+                    Some(colors) => data.colors.extend(colors.into_rgb_f32().map(tint)),
+                    None => data.colors.extend(std::iter::repeat(tint([1.0; 3])).take(count)),
+                }
+            }
+            if data.normals.len() != data.positions.len() && has_normals
+                || data.uvs.len() != data.positions.len() && has_uvs
+                || data.colors.len() != data.positions.len() && has_colors {
+                return Err(GltfError::Invalid(format!("{} has attributes of different lengths", mesh_name)));
+            }
+
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..count as u32).collect(),
+            };
+            if let Some(index) = indices.iter().find(|&&index| index as usize >= count) {
+                return Err(GltfError::Invalid(format!("{} refers to vertex {} of a primitive with {} vertices", mesh_name, index, count)));
+            }
+            let mut triangles: Vec<[u32; 3]> = match primitive.mode() {
+                Mode::Triangles => indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect(),
+                // Every other triangle of a strip has its winding reversed
+                Mode::TriangleStrip => (2..indices.len()).map(|i| if i % 2 == 0 { [indices[i - 2], indices[i - 1], indices[i]] }
+                                                                   else { [indices[i - 1], indices[i - 2], indices[i]] }).collect(),
+                _ => (2..indices.len()).map(|i| [indices[0], indices[i - 1], indices[i]]).collect(),
+            };
+            // Mirroring transforms turn the triangles inside out
+            if determinant < 0.0 {
+                triangles.iter_mut().for_each(|triangle| triangle.swap(1, 2));
+            }
+
+            let material = self.material(&primitive.material());
+            let slot = match data.materials.iter().position(|existing| Arc::ptr_eq(existing, &material)) {
+                Some(slot) => slot,
+                None => {
+                    data.materials.push(material);
+                    data.materials.len() - 1
+                }
+            } as u32;
+            data.material_indices.extend(std::iter::repeat(slot).take(triangles.len()));
+            data.triangles.extend(triangles.into_iter().map(|triangle| triangle.map(|index| index + first)));
+        }
+        if data.triangles.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(TriangleMesh::new(data)))
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> Arc<dyn Material<T> + Send + Sync> {
+        self.materials.entry(material.index()).or_insert_with(|| {
+            let pbr = material.pbr_metallic_roughness();
+            let [r, g, b, alpha] = pbr.base_color_factor();
+            let base_color = RGBColor { R: T::from(r).unwrap(), G: T::from(g).unwrap(), B: T::from(b).unwrap() };
+            let transmission = material.transmission().map_or(0.0, |transmission| transmission.transmission_factor());
+            let blended = material.alpha_mode() == gltf::material::AlphaMode::Blend && alpha < 0.5;
+            if transmission > 0.5 || blended {
+                Arc::new(Dielectric::new(T::from(material.ior().unwrap_or(1.5)).unwrap()))
+            } else if pbr.metallic_factor() >= 0.5 {
+                Arc::new(Metal::new(base_color, T::from(pbr.roughness_factor()).unwrap()))
+            } else {
+                // Meshes with vertex colors store them already multiplied by the base color
+                Arc::new(Lambertian::textured(Arc::new(VertexColor::new(base_color))))
+            }
+        }).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes a glTF file with one mesh, the unit square in the xy-plane facing +z drawn with
+    // `indices` in `mode`, and a perspective camera, both placed by `nodes`. Loads the file.
+    fn load(test: &str, nodes: &str, mode: u32, indices: &[u16]) -> Result<GltfScene<f64>, GltfError> {
+        let directory = std::env::temp_dir().join(format!("gltf_lib_{}_{}", test, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let mut bytes: Vec<u8> = Vec::new();
+        for value in [0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0, 1.0, 1.0, 0.0, 0.0, 1.0, 0.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for _ in 0..4 {
+            for value in [0.0f32, 0.0, 1.0] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        for index in indices {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        let index_bytes = 2 * indices.len();
+        bytes.resize(96 + (index_bytes + 3) / 4 * 4, 0);
+        std::fs::write(directory.join("square.bin"), &bytes).unwrap();
+        let document = format!(r#"{{
+            "asset": {{ "version": "2.0" }},
+            "scene": 0,
+            "scenes": [{{ "nodes": [0] }}],
+            "nodes": {nodes},
+            "meshes": [{{ "primitives": [{{ "attributes": {{ "POSITION": 0, "NORMAL": 1 }}, "indices": 2, "mode": {mode} }}] }}],
+            "cameras": [{{ "type": "perspective", "perspective": {{ "yfov": 0.8, "znear": 0.1, "aspectRatio": 1.5 }} }}],
+            "accessors": [
+                {{ "bufferView": 0, "componentType": 5126, "count": 4, "type": "VEC3", "min": [0, 0, 0], "max": [1, 1, 0] }},
+                {{ "bufferView": 0, "byteOffset": 48, "componentType": 5126, "count": 4, "type": "VEC3" }},
+                {{ "bufferView": 1, "componentType": 5123, "count": {count}, "type": "SCALAR" }}
+            ],
+            "bufferViews": [
+                {{ "buffer": 0, "byteLength": 96, "target": 34962 }},
+                {{ "buffer": 0, "byteOffset": 96, "byteLength": {index_bytes}, "target": 34963 }}
+            ],
+            "buffers": [{{ "uri": "square.bin", "byteLength": {length} }}]
+        }}"#, nodes = nodes, mode = mode, count = indices.len(), index_bytes = index_bytes, length = bytes.len());
+        std::fs::write(directory.join("square.gltf"), document).unwrap();
+        let scene = load_gltf(&directory.join("square.gltf"));
+        std::fs::remove_dir_all(&directory).unwrap();
+        scene
+    }
+
+    const TRIANGLES: u32 = 4;
+    const TRIANGLE_STRIP: u32 = 5;
+    const TRIANGLE_FAN: u32 = 6;
+    const SQUARE_NODE: &str = r#"[{ "mesh": 0 }]"#;
+
+    fn assert_close(vector: &Vector3D<f64>, expected: [f64; 3]) {
+        assert!((vector.x - expected[0]).abs() < 1e-6 && (vector.y - expected[1]).abs() < 1e-6 && (vector.z - expected[2]).abs() < 1e-6,
+                "({}, {}, {}) != {:?}", vector.x, vector.y, vector.z, expected);
+    }
+
+    #[test]
+    fn node_transforms_are_baked_in() {
+        // The child mirrors the y axis, which must flip the winding but keep the normals facing +z
+        let nodes = r#"[{ "translation": [1, 2, 3], "children": [1] }, { "scale": [2, -1, 1], "mesh": 0 }]"#;
+        let scene = load("hierarchy", nodes, TRIANGLES, &[0, 1, 2]).unwrap();
+        let data = scene.meshes[0].data();
+        assert_close(&data.positions[0], [1.0, 2.0, 3.0]);
+        assert_close(&data.positions[1], [3.0, 2.0, 3.0]);
+        assert_close(&data.positions[2], [3.0, 1.0, 3.0]);
+        assert_eq!(data.triangles, vec![[0, 2, 1]]);
+        assert_close(&data.normals[0], [0.0, 0.0, 1.0]);
+        let [a, b, c] = data.triangles[0].map(|index| data.positions[index as usize]);
+        assert!((b - a).cross_product(&(c - a)).inner_product(&data.normals[0]) > 0.0);
+    }
+
+    #[test]
+    fn strips_and_fans_are_triangulated() {
+        let strip = load("strip", SQUARE_NODE, TRIANGLE_STRIP, &[0, 1, 3, 2]).unwrap();
+        assert_eq!(strip.meshes[0].data().triangles, vec![[0, 1, 3], [3, 1, 2]]);
+        let fan = load("fan", SQUARE_NODE, TRIANGLE_FAN, &[0, 1, 2, 3]).unwrap();
+        assert_eq!(fan.meshes[0].data().triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    }
+
+    #[test]
+    fn out_of_range_index_is_an_error() {
+        let result = load("range", SQUARE_NODE, TRIANGLES, &[0, 1, 7]);
+        assert!(matches!(&result, Err(GltfError::Invalid(message)) if message.contains("vertex 7")), "{:?}", result.err());
+    }
+
+    #[test]
+    fn perspective_camera_is_placed() {
+        // Turned a quarter to the left about +y, so that it looks along -x
+        let nodes = r#"[{ "translation": [0, 1, 0], "children": [1, 2] }, { "mesh": 0 },
+                        { "camera": 0, "translation": [0, 0, 5], "rotation": [0, 0.70710678, 0, 0.70710678] }]"#;
+        let scene = load("camera", nodes, TRIANGLES, &[0, 1, 2]).unwrap();
+        let camera = &scene.cameras[0];
+        assert_close(&camera.look_from, [0.0, 1.0, 5.0]);
+        assert_close(&camera.look_at, [-1.0, 1.0, 5.0]);
+        assert_close(&camera.view_up, [0.0, 1.0, 0.0]);
+        assert!((camera.vertical_fov - 0.8f64.to_degrees()).abs() < 1e-4);
+        assert!((camera.aspect_ratio.unwrap() - 1.5).abs() < 1e-6);
+    }
+}
@@ -1,22 +1,49 @@
+use std::sync::Arc;
+
 use ray_lib::Ray3D;
 
-use vector_lib::{Vector3D, VectorOperations};
+use vector_lib::{OrthonormalBasis, Vector2D, Vector3D, VectorOperations};
 use vector_lib::DataTypeTraits;
 
 use color_lib::RGBColor;
 
+use aabb_lib::AABB;
+
+use sampler_lib::Sampler;
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //////////////////////////////////////// MATERIAL TRAIT ////////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 pub trait Material<T: DataTypeTraits> {
+    // Chooses the direction the light arriving at `rec` along `ray_in` continues in, with `sampler`,
+    // and fills `scatter_record` with it. Returns false if the light is absorbed.
     fn scatter(
         &self,
         ray_in: &Ray3D<T>,
         rec: &HitRecord<T>,
-        attenuation: &mut RGBColor<T>,
-        scattered: &mut Ray3D<T>,
+        scatter_record: &mut ScatterRecord<T>,
+        sampler: &mut dyn Sampler<T>,
     ) -> bool;
+
+    // Light given off by the surface at the hit point towards where `ray_in` came from. Only
+    // light sources emit anything.
+    fn emitted(&self, _ray_in: &Ray3D<T>, _rec: &HitRecord<T>) -> RGBColor<T> {
+        RGBColor::zeros()
+    }
+
+    // BRDF for light arriving along `scattered` and leaving towards where `ray_in` came from.
+    // Materials scattering into single directions (mirrors, glass) keep the default of zero.
+    fn brdf(&self, _ray_in: &Ray3D<T>, _rec: &HitRecord<T>, _scattered: &Ray3D<T>) -> RGBColor<T> {
+        RGBColor::zeros()
+    }
+
+    // Density, over solid angle, of `scatter` choosing the direction of `scattered`. Materials
+    // scattering into single directions keep the default of zero, which leaves them out of the
+    // light sampling of the integrator.
+    fn scattering_pdf(&self, _ray_in: &Ray3D<T>, _rec: &HitRecord<T>, _scattered: &Ray3D<T>) -> T {
+        T::zero()
+    }
 }
 
 
@@ -26,13 +53,64 @@ impl<T: DataTypeTraits> Material<T> for NoMaterial {
         &self,
         _ray_in: &Ray3D<T>,
         _rec: &HitRecord<T>,
-        _attenuation: &mut RGBColor<T>,
-        _scattered: &mut Ray3D<T>,
+        _scatter_record: &mut ScatterRecord<T>,
+        _sampler: &mut dyn Sampler<T>,
     ) -> bool {
         false
     }
 }
 
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// SCATTERRECORD STRUCT ////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Outcome of a scattering event. For a direction drawn with density `pdf`, the light arriving along
+// `ray` contributes BRDF * cos(theta) / pdf times its radiance to the path: the `attenuation`.
+// Specular scattering (mirrors, glass) picks a single direction; it has no density and no BRDF
+// value (both zero) and only sets the attenuation.
+#[derive(Clone, Copy)]
+pub struct ScatterRecord<T: DataTypeTraits>
+{
+    pub ray: Ray3D<T>,
+    pub attenuation: RGBColor<T>,
+    pub brdf: RGBColor<T>,
+    pub pdf: T,
+}
+
+impl<T: DataTypeTraits> Default for ScatterRecord<T> {
+    fn default() -> Self {
+        ScatterRecord { ray: Ray3D { origin: Vector3D::zeros(), direction: Vector3D::zeros(), time: T::zero() },
+                        attenuation: RGBColor::zeros(),
+                        brdf: RGBColor::zeros(),
+                        pdf: T::zero() }
+    }
+}
+
+// Implementing ScatterRecord<T> initialization for a single outgoing direction through <T>::specular()
+impl<T: DataTypeTraits> ScatterRecord<T>
+{
+    pub fn specular(ray: Ray3D<T>, attenuation: RGBColor<T>) -> Self {
+        ScatterRecord { ray: ray, attenuation: attenuation, brdf: RGBColor::zeros(), pdf: T::zero() }
+    }
+}
+
+// Implementing ScatterRecord<T> initialization for a direction drawn with density `pdf` through
+// <T>::sampled(), where `cosine` is the cosine between the direction and the shading normal
+impl<T: DataTypeTraits> ScatterRecord<T>
+{
+    pub fn sampled(ray: Ray3D<T>, brdf: RGBColor<T>, cosine: T, pdf: T) -> Self {
+        let attenuation = if pdf > T::zero() { brdf * (cosine.abs() / pdf) } else { RGBColor::zeros() };
+        ScatterRecord { ray: ray, attenuation: attenuation, brdf: brdf, pdf: pdf }
+    }
+}
+
+impl<T: DataTypeTraits> ScatterRecord<T> {
+    #[inline(always)]
+    pub fn is_specular(&self) -> bool {
+        !(self.pdf > T::zero())
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////// HITRECORD STRUCT ///////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -41,9 +119,16 @@ impl<T: DataTypeTraits> Material<T> for NoMaterial {
 #[derive(Clone)]
 pub struct HitRecord<T: DataTypeTraits> {
     point: Vector3D<T>,
-    normal_vector: Vector3D<T>,
+    normal_vector: Vector3D<T>, // Shading normal, facing the incoming ray
+    geometric_normal: Vector3D<T>, // Normal of the actual surface, facing the incoming ray
+    tangent: Vector3D<T>, // Unit, perpendicular to the shading normal, along increasing u
+    bitangent: Vector3D<T>, // Unit, perpendicular to both others, on the side of increasing v
+    uv: Vector2D<T>, // Surface (texture) coordinates
+    barycentric: Vector3D<T>, // Barycentric coordinates of the point when hitting a triangle
+    vertex_color: Option<RGBColor<T>>, // Interpolated vertex color, for meshes that have them
     t: T,
     front_face: bool, // True if ray is hitting from outside object, false o.w.
+    material: Arc<dyn Material<T> + Send + Sync>, // Material of the object that was hit
 }
 
 
@@ -56,7 +141,59 @@ impl<T: DataTypeTraits> HitRecord<T> {
         // Positive inner product -> ray is inside the object, o.w ray is outside the object
         self.front_face = ray.direction.inner_product(&outwards_normal) < T::zero();
         self.normal_vector = if self.front_face { outwards_normal.clone() } else { outwards_normal.clone() * (-T::one()) };
+        self.geometric_normal = self.normal_vector;
+        (self.tangent, self.bitangent) = any_tangents(&self.normal_vector);
+        self.vertex_color = None;
     }
+
+    // For surfaces whose shading normal differs from the geometric one (e.g. interpolated vertex
+    // normals). The side that was hit is decided by the geometric normal and the shading normal is
+    // flipped to that same side.
+    // Both functions start a new hit, so they clear the attributes only some surfaces have and
+    // give the shading normal an arbitrary tangent frame.
+    pub fn set_face_normals(&mut self, ray: &Ray3D<T>, outwards_geometric_normal: &Vector3D<T>,
+                            outwards_shading_normal: &Vector3D<T>) {
+        self.front_face = ray.direction.inner_product(&outwards_geometric_normal) < T::zero();
+        let sign = if self.front_face { T::one() } else { -T::one() };
+        self.geometric_normal = outwards_geometric_normal.clone() * sign;
+        self.normal_vector = if outwards_shading_normal.inner_product(&self.geometric_normal) < T::zero() {
+            -(*outwards_shading_normal)
+        } else {
+            outwards_shading_normal.clone()
+        };
+        (self.tangent, self.bitangent) = any_tangents(&self.normal_vector);
+        self.vertex_color = None;
+    }
+
+    // Sets the tangent frame from the derivatives of the hit point with respect to u and v, made
+    // orthonormal to the shading normal (which must be set first). The frame keeps the handedness
+    // of the (u, v) mapping, so mirrored texture coordinates give mirrored normal maps. Degenerate
+    // derivatives leave an arbitrary frame.
+    pub fn set_tangents(&mut self, dpdu: &Vector3D<T>, dpdv: &Vector3D<T>) {
+        let normal = self.normal_vector;
+        let tolerance = T::epsilon().sqrt();
+        let tangent = dpdu - &(normal * normal.inner_product(dpdu));
+        let tangent_norm = tangent.norm();
+        if !(tangent_norm > tolerance * dpdu.norm()) || !tangent_norm.is_finite() {
+            (self.tangent, self.bitangent) = any_tangents(&normal);
+            return;
+        }
+        self.tangent = tangent / tangent_norm;
+        let bitangent = dpdv - &(normal * normal.inner_product(dpdv)) - self.tangent * self.tangent.inner_product(dpdv);
+        let bitangent_norm = bitangent.norm();
+        self.bitangent = if bitangent_norm > tolerance * dpdv.norm() && bitangent_norm.is_finite() {
+            bitangent / bitangent_norm
+        } else {
+            normal.cross_product(&self.tangent)
+        };
+    }
+}
+
+// Some unit tangent and bitangent completing the unit `normal` into a right-handed frame
+#[inline(always)]
+fn any_tangents<T: DataTypeTraits>(normal: &Vector3D<T>) -> (Vector3D<T>, Vector3D<T>) {
+    let basis = OrthonormalBasis::from_w(normal);
+    (basis.u, basis.v)
 }
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////// INITIALIZATION IMPL  /////////////////////////////////////////
@@ -67,8 +204,15 @@ impl<T: DataTypeTraits> Default for HitRecord<T> {
         HitRecord {
             point: Vector3D::default(),
             normal_vector: Vector3D::default(),
+            geometric_normal: Vector3D::default(),
+            tangent: Vector3D::default(),
+            bitangent: Vector3D::default(),
+            uv: Vector2D::default(),
+            barycentric: Vector3D::default(),
+            vertex_color: None,
             t: T::default(),
             front_face: bool::default(),
+            material: Arc::new(NoMaterial),
         }
     }
 }
@@ -90,12 +234,60 @@ impl<T: DataTypeTraits> HitRecord<T> {
     }
 }
 
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_geometric_normal(&self) -> Vector3D<T> {
+        self.geometric_normal.clone()
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_tangent(&self) -> Vector3D<T> {
+        self.tangent.clone()
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_bitangent(&self) -> Vector3D<T> {
+        self.bitangent.clone()
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_uv(&self) -> Vector2D<T> {
+        self.uv.clone()
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_barycentric(&self) -> Vector3D<T> {
+        self.barycentric.clone()
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_vertex_color(&self) -> Option<RGBColor<T>> {
+        self.vertex_color
+    }
+}
+
 impl<T: DataTypeTraits> HitRecord<T> {
     pub fn get_t(&self) -> T {
         self.t.clone()
     }
 }
 
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_front_face(&self) -> bool {
+        self.front_face
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn get_material(&self) -> Arc<dyn Material<T> + Send + Sync> {
+        self.material.clone()
+    }
+}
+
 // =========================================== SETTERS ========================================== //
 
 impl<T: DataTypeTraits> HitRecord<T> {
@@ -109,12 +301,42 @@ impl<T: DataTypeTraits> HitRecord<T> {
     }
 }
 
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn set_geometric_normal(&mut self, geometric_normal: Vector3D<T>){
+        self.geometric_normal = geometric_normal.clone();
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn set_uv(&mut self, uv: Vector2D<T>){
+        self.uv = uv.clone();
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn set_barycentric(&mut self, barycentric: Vector3D<T>){
+        self.barycentric = barycentric.clone();
+    }
+}
+
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn set_vertex_color(&mut self, vertex_color: Option<RGBColor<T>>){
+        self.vertex_color = vertex_color;
+    }
+}
+
 impl<T: DataTypeTraits> HitRecord<T> {
     pub fn set_t(&mut self, t: T){
         self.t = t.clone();
     }
 }
 
+impl<T: DataTypeTraits> HitRecord<T> {
+    pub fn set_material(&mut self, material: Arc<dyn Material<T> + Send + Sync>){
+        self.material = material;
+    }
+}
+
 
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -124,5 +346,40 @@ impl<T: DataTypeTraits> HitRecord<T> {
 // TODO: implement this trait for any new shape created in scene ObjectType enum
 pub trait Hittable<T: DataTypeTraits>
 {
-    fn hit(&mut self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: & mut HitRecord<T>) -> bool;
-}
\ No newline at end of file
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool;
+
+    // Box enclosing the object, or None for unbounded objects (which cannot be put in a BVH)
+    fn bounding_box(&self) -> Option<AABB<T>>;
+
+    // Density, over solid angle as seen from `origin` at `time`, of `random_direction` returning
+    // `direction`; zero if the direction misses the object. Objects that can be sampled as lights
+    // implement both functions, the others keep the defaults.
+    fn pdf_value(&self, _origin: &Vector3D<T>, _direction: &Vector3D<T>, _time: T) -> T {
+        T::zero()
+    }
+
+    // Random direction from `origin` towards the object as it is at `time`, drawn with `sampler`
+    fn random_direction(&self, _origin: &Vector3D<T>, _time: T, _sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        Vector3D { x: T::one(), y: T::zero(), z: T::zero() }
+    }
+}
+
+// Shared objects (e.g. a mesh placed many times through instances) are hittable themselves
+impl<T: DataTypeTraits, H: Hittable<T> + ?Sized> Hittable<T> for Arc<H>
+{
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool {
+        (**self).hit(ray, t_min, t_max, hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: &Vector3D<T>, direction: &Vector3D<T>, time: T) -> T {
+        (**self).pdf_value(origin, direction, time)
+    }
+
+    fn random_direction(&self, origin: &Vector3D<T>, time: T, sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        (**self).random_direction(origin, time, sampler)
+    }
+}
@@ -0,0 +1,264 @@
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+use film_lib::Film;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// ERROR TYPE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    UnsupportedFormat(String),
+    DimensionMismatch { width: usize, height: usize, pixels: usize },
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "I/O error while writing image: {}", e),
+            ImageError::Png(e) => write!(f, "PNG encoding error: {}", e),
+            ImageError::UnsupportedFormat(extension) =>
+                write!(f, "unsupported image format '{}' (expected png, ppm or hdr)", extension),
+            ImageError::DimensionMismatch { width, height, pixels } =>
+                write!(f, "image of {}x{} pixels cannot be built from {} pixel values", width, height, pixels),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(e) => Some(e),
+            ImageError::Png(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(e: std::io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(e: png::EncodingError) -> Self {
+        ImageError::Png(e)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// IMAGE FORMATS ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum ImageFormat {
+    Png8,  // 8 bits per channel, gamma corrected
+    Png16, // 16 bits per channel, gamma corrected
+    Ppm,   // Binary (P6) portable pixmap, gamma corrected
+    Hdr,   // Radiance RGBE, linear
+}
+
+impl ImageFormat {
+    // Picks the format from the extension of `path`. PNGs default to 8 bits per channel.
+    pub fn from_path(path: &Path) -> Result<Self, ImageError> {
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Ok(ImageFormat::Png8),
+            "ppm" => Ok(ImageFormat::Ppm),
+            "hdr" => Ok(ImageFormat::Hdr),
+            _ => Err(ImageError::UnsupportedFormat(extension)),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////// WRITERS ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+/// Writes the resolved (sample averaged) pixels of `film` to `path`, choosing the file format from
+/// the extension of `path`.
+pub fn write_film<T: DataTypeTraits>(path: &Path, film: &Film<T>) -> Result<(), ImageError> {
+    write_image(path, film.width(), film.height(), &film.resolve())
+}
+
+/// Writes the linear `pixels` (row-major, top row first) to `path`, choosing the file format from
+/// the extension of `path`.
+pub fn write_image<T: DataTypeTraits>(path: &Path, width: usize, height: usize,
+                                      pixels: &[RGBColor<T>]) -> Result<(), ImageError> {
+    write_image_as(path, ImageFormat::from_path(path)?, width, height, pixels)
+}
+
+/// Writes the linear `pixels` (row-major, top row first) to `path` in the given `format`.
+pub fn write_image_as<T: DataTypeTraits>(path: &Path, format: ImageFormat, width: usize, height: usize,
+                                         pixels: &[RGBColor<T>]) -> Result<(), ImageError> {
+    if width * height != pixels.len() {
+        return Err(ImageError::DimensionMismatch { width: width, height: height, pixels: pixels.len() });
+    }
+    let file = std::fs::File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    match format {
+        ImageFormat::Png8 => write_png(&mut writer, width, height, pixels, png::BitDepth::Eight)?,
+        ImageFormat::Png16 => write_png(&mut writer, width, height, pixels, png::BitDepth::Sixteen)?,
+        ImageFormat::Ppm => write_ppm(&mut writer, width, height, pixels)?,
+        ImageFormat::Hdr => write_hdr(&mut writer, width, height, pixels)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_png<W: Write, T: DataTypeTraits>(writer: W, width: usize, height: usize,
+                                          pixels: &[RGBColor<T>], bit_depth: png::BitDepth) -> Result<(), ImageError> {
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(bit_depth);
+    let mut png_writer = encoder.write_header()?;
+    let data: Vec<u8> = match bit_depth {
+        png::BitDepth::Sixteen => pixels.iter()
+            .flat_map(|pixel| gamma_corrected(pixel))
+            .flat_map(|channel| quantize(channel, u16::MAX as f64).to_be_bytes())
+            .collect(),
+        _ => pixels.iter()
+            .flat_map(|pixel| gamma_corrected(pixel))
+            .map(|channel| quantize(channel, u8::MAX as f64) as u8)
+            .collect(),
+    };
+    png_writer.write_image_data(&data)?;
+    png_writer.finish()?;
+    Ok(())
+}
+
+fn write_ppm<W: Write, T: DataTypeTraits>(mut writer: W, width: usize, height: usize,
+                                          pixels: &[RGBColor<T>]) -> Result<(), ImageError> {
+    // Header info for binary .ppm file
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+    let data: Vec<u8> = pixels.iter()
+        .flat_map(|pixel| gamma_corrected(pixel))
+        .map(|channel| quantize(channel, u8::MAX as f64) as u8)
+        .collect();
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+fn write_hdr<W: Write, T: DataTypeTraits>(mut writer: W, width: usize, height: usize,
+                                          pixels: &[RGBColor<T>]) -> Result<(), ImageError> {
+    // Header info for Radiance .hdr file (flat, i.e. not run-length encoded, scanlines)
+    write!(writer, "#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", height, width)?;
+    let data: Vec<u8> = pixels.iter().flat_map(|pixel| to_rgbe(pixel)).collect();
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// PIXEL CONVERSIONS //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Gamma-correct for gamma=2.0 by taking sqrt.
+#[inline(always)]
+fn gamma_corrected<T: DataTypeTraits>(pixel: &RGBColor<T>) -> [f64; 3] {
+    [pixel.R.to_f64().unwrap().max(0.0).sqrt(),
+     pixel.G.to_f64().unwrap().max(0.0).sqrt(),
+     pixel.B.to_f64().unwrap().max(0.0).sqrt()]
+}
+
+// Maps a channel value in [0, 1] onto the integer range [0, max_value]
+#[inline(always)]
+fn quantize(channel: f64, max_value: f64) -> u16 {
+    (channel.clamp(0.0, 1.0) * max_value).round() as u16
+}
+
+// Shared-exponent encoding of a linear color as used by the Radiance format
+#[inline(always)]
+fn to_rgbe<T: DataTypeTraits>(pixel: &RGBColor<T>) -> [u8; 4] {
+    let r = pixel.R.to_f64().unwrap().max(0.0);
+    let g = pixel.G.to_f64().unwrap().max(0.0);
+    let b = pixel.B.to_f64().unwrap().max(0.0);
+    let max_channel = r.max(g).max(b);
+    if max_channel < 1e-32 {
+        return [0, 0, 0, 0];
+    }
+    // max_channel = mantissa * 2^exponent with mantissa in [0.5, 1[
+    let exponent = max_channel.log2().floor() as i32 + 1;
+    let scale = 256.0 / 2.0_f64.powi(exponent);
+    [(r * scale).min(255.0) as u8,
+     (g * scale).min(255.0) as u8,
+     (b * scale).min(255.0) as u8,
+     (exponent + 128) as u8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color(r: f64, g: f64, b: f64) -> RGBColor<f64> {
+        RGBColor { R: r, G: g, B: b }
+    }
+
+    // Writes `pixels` as a 2x1 image in `format` and reads the file back
+    fn written(test: &str, format: ImageFormat, pixels: &[RGBColor<f64>]) -> Vec<u8> {
+        let path = std::env::temp_dir().join(format!("image_lib_{}_{}", test, std::process::id()));
+        write_image_as(&path, format, 2, 1, pixels).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn ppm_header_and_gamma_corrected_bytes() {
+        let bytes = written("ppm", ImageFormat::Ppm, &[color(1.0, 0.25, 0.0), color(4.0, -1.0, 0.01)]);
+        let header = b"P6\n2 1\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        // Gamma 2 takes square roots; values are clamped to [0, 1] and rounded
+        assert_eq!(&bytes[header.len()..], &[255, 128, 0, 255, 0, 26]);
+    }
+
+    #[test]
+    fn hdr_header_and_rgbe_bytes() {
+        let bytes = written("hdr", ImageFormat::Hdr, &[color(1.0, 0.5, 0.25), color(0.0, 0.0, 0.0)]);
+        let header = b"#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y 1 +X 2\n";
+        assert_eq!(&bytes[..header.len()], header);
+        // 1 = 128 / 256 * 2^(129 - 128), sharing its exponent with the other channels
+        assert_eq!(&bytes[header.len()..], &[128, 64, 32, 129, 0, 0, 0, 0]);
+        assert_eq!(to_rgbe(&color(3.0, 0.0, 1.5)), [192, 0, 96, 130]);
+        assert_eq!(to_rgbe(&color(0.1, -2.0, 0.05)), [204, 0, 102, 125]);
+    }
+
+    #[test]
+    fn png_bit_depths() {
+        let pixels = [color(0.25, 1.0, 0.0), color(0.0, 0.0, 2.0)];
+        for (format, bit_depth, data) in [(ImageFormat::Png8, png::BitDepth::Eight, vec![128, 255, 0, 0, 0, 255]),
+                                          (ImageFormat::Png16, png::BitDepth::Sixteen,
+                                           vec![128, 0, 255, 255, 0, 0, 0, 0, 0, 0, 255, 255])] {
+            let bytes = written("png", format, &pixels);
+            let mut reader = png::Decoder::new(bytes.as_slice()).read_info().unwrap();
+            let mut buffer = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut buffer).unwrap();
+            assert_eq!((info.width, info.height, info.bit_depth, info.color_type), (2, 1, bit_depth, png::ColorType::Rgb));
+            assert_eq!(&buffer[..info.buffer_size()], data.as_slice());
+        }
+    }
+
+    #[test]
+    fn formats_from_extensions() {
+        assert_eq!(ImageFormat::from_path(Path::new("out/image.png")).unwrap(), ImageFormat::Png8);
+        assert_eq!(ImageFormat::from_path(Path::new("image.PPM")).unwrap(), ImageFormat::Ppm);
+        assert_eq!(ImageFormat::from_path(Path::new("image.hdr")).unwrap(), ImageFormat::Hdr);
+        assert!(matches!(ImageFormat::from_path(Path::new("image.jpg")), Err(ImageError::UnsupportedFormat(e)) if e == "jpg"));
+        assert!(matches!(ImageFormat::from_path(Path::new("image")), Err(ImageError::UnsupportedFormat(_))));
+    }
+
+    #[test]
+    fn pixel_count_must_match_the_size() {
+        let path = std::env::temp_dir().join(format!("image_lib_mismatch_{}.ppm", std::process::id()));
+        let result = write_image(&path, 2, 2, &[color(0.0, 0.0, 0.0); 3]);
+        assert!(matches!(result, Err(ImageError::DimensionMismatch { width: 2, height: 2, pixels: 3 })));
+        assert!(!path.exists());
+    }
+}
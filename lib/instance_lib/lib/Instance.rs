@@ -0,0 +1,417 @@
+use std::sync::Arc;
+
+use hittable_material_traits::{HitRecord, Hittable};
+
+use vector_lib::{Matrix4, Vector3D};
+use vector_lib::VectorOperations;
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+use aabb_lib::AABB;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// STRUCT DEFINITION //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// An object placed in the scene through an affine transform. Rays are taken into the space of the
+// object instead of transforming the object, so any number of instances can share one object (e.g.
+// a mesh) without copying it.
+#[derive(Clone)]
+pub struct Instance<T: DataTypeTraits>
+{
+    pub object: Arc<dyn Hittable<T> + Send + Sync>,
+    transform: Matrix4<T>, // Object to world space
+    inverse: Matrix4<T>, // World to object space
+    normal_matrix: Matrix4<T>, // Inverse transpose, taking normals to world space
+    motion: Option<Motion<T>>, // For moving instances, replaces the matrices above in `hit`
+    bbox: Option<AABB<T>>,
+}
+
+// Transform split into a translation, a rotation and the remaining scale and shear. These can be
+// interpolated separately without the object shrinking halfway through a rotation. The scale and
+// shear are kept inverted, so that the inverse of an interpolated transform is found directly.
+#[derive(Clone, Copy)]
+struct DecomposedTransform<T: DataTypeTraits>
+{
+    translation: Vector3D<T>,
+    rotation: [T; 4], // Unit quaternion (w, x, y, z)
+    inverse_stretch: Matrix4<T>,
+}
+
+// Transform going from `start` at `time_start` to `end` at `time_end`, fixed before and after
+#[derive(Clone, Copy)]
+struct Motion<T: DataTypeTraits>
+{
+    start: DecomposedTransform<T>,
+    end: DecomposedTransform<T>,
+    time_start: T,
+    time_end: T,
+}
+
+// Number of steps the motion is sampled at to bound it
+const MOTION_BOX_STEPS: usize = 64;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////// STRUCT IMPL DEFINITIONS ///////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Implementing Instance<T> initialization through <T>::new(). Panics if `transform` is singular.
+impl<T: DataTypeTraits> Instance<T>
+{
+    pub fn new(object: Arc<dyn Hittable<T> + Send + Sync>, transform: Matrix4<T>) -> Self {
+        let inverse = transform.inverse().expect("instance transform must be invertible");
+        // The box around the transformed corners of the object's box encloses the transformed object
+        let bbox = object.bounding_box().map(|bbox| AABB::from_points(&box_corners(&bbox).map(|corner| transform.transform_point(&corner))));
+        Instance { object: object, transform: transform, inverse: inverse, normal_matrix: inverse.transpose(), motion: None, bbox: bbox }
+    }
+}
+
+// Implementing moving Instance<T> initialization through <T>::moving(). The object is placed by
+// `transform_start` up to `time_start` and by `transform_end` from `time_end` on, moving smoothly
+// in between: translation and inverse scale are interpolated linearly and rotation along the
+// shortest arc. Panics if either transform is singular.
+impl<T: DataTypeTraits> Instance<T>
+{
+    pub fn moving(object: Arc<dyn Hittable<T> + Send + Sync>, transform_start: Matrix4<T>, transform_end: Matrix4<T>,
+                  time_start: T, time_end: T) -> Self {
+        let mut instance = Instance::new(object, transform_start);
+        assert!(transform_end.inverse().is_some(), "instance transform must be invertible");
+        let motion = Motion { start: decompose(&transform_start), end: decompose(&transform_end),
+                              time_start: time_start, time_end: time_end };
+
+        // Box around the object at evenly spaced instants. Between two instants a corner follows an
+        // arc that strays less than the chord between its ends from them, so padding by the longest
+        // chord keeps the box conservative.
+        instance.bbox = instance.object.bounding_box().map(|bbox| {
+            let corners = box_corners(&bbox);
+            let mut previous: Option<[Vector3D<T>; 8]> = None;
+            let mut points = Vec::with_capacity(8 * (MOTION_BOX_STEPS + 1));
+            let mut longest_chord = T::zero();
+            for step in 0..=MOTION_BOX_STEPS {
+                // An object mirrored at one end only is flat at some instant, where it needs no box
+                let Some(transform) = motion.interpolate(T::from(step as f64 / MOTION_BOX_STEPS as f64).unwrap()) else {
+                    continue;
+                };
+                let moved = corners.map(|corner| transform.transform_point(&corner));
+                if let Some(previous) = previous {
+                    for (a, b) in previous.iter().zip(moved.iter()) {
+                        longest_chord = longest_chord.max((b - a).norm());
+                    }
+                }
+                points.extend_from_slice(&moved);
+                previous = Some(moved);
+            }
+            AABB::from_points(&points).pad(longest_chord)
+        });
+        instance.motion = Some(motion);
+        instance
+    }
+}
+
+impl<T: DataTypeTraits> Instance<T> {
+    pub fn get_transform(&self) -> Matrix4<T> {
+        self.transform
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Instance<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        // Matrices taking rays into object space, normals out of it and tangents out of it (the
+        // latter up to a positive factor, as only the directions of tangents matter)
+        let moved;
+        let (inverse, normal_matrix, tangent_matrix) = match &self.motion {
+            None => (&self.inverse, &self.normal_matrix, &self.transform),
+            Some(motion) => {
+                let (translation, rotation, inverse_stretch) = motion.parts_at(ray.time);
+                let inverse = inverse_stretch * quaternion_to_matrix(conjugate(rotation)) * Matrix4::translation(&-translation);
+                // inverse(stretch) = adjugate(inverse_stretch) / determinant(inverse_stretch)
+                let mut tangent_matrix = quaternion_to_matrix(rotation) * adjugate(&inverse_stretch);
+                if inverse_stretch.linear_determinant() < T::zero() {
+                    tangent_matrix = tangent_matrix * Matrix4::scaling(&Vector3D { x: -T::one(), y: -T::one(), z: -T::one() });
+                }
+                moved = (inverse, inverse.transpose(), tangent_matrix);
+                (&moved.0, &moved.1, &moved.2)
+            }
+        };
+        // The direction is not normalized, so distances along the ray are the same in both spaces
+        let object_ray = Ray3D { origin: inverse.transform_point(&ray.origin),
+                                 direction: inverse.transform_vector(&ray.direction),
+                                 time: ray.time };
+        if !self.object.hit(&object_ray, t_min, t_max, hit_record) {
+            return false;
+        }
+        // The inverse transpose keeps normals perpendicular to the surface and on the side of the
+        // incoming ray, so the object's front face decision still holds
+        (*hit_record).set_point(ray.origin + ray.direction * hit_record.get_t());
+        (*hit_record).set_normal_vector(normal_matrix.transform_vector(&hit_record.get_normal_vector()).unit_vector());
+        (*hit_record).set_geometric_normal(normal_matrix.transform_vector(&hit_record.get_geometric_normal()).unit_vector());
+        // Tangents are directions along the surface, which the transform itself carries over
+        let (tangent, bitangent) = (tangent_matrix.transform_vector(&hit_record.get_tangent()), tangent_matrix.transform_vector(&hit_record.get_bitangent()));
+        (*hit_record).set_tangents(&tangent, &bitangent);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        self.bbox
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////// MOTION //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> Motion<T> {
+    // Translation, rotation and inverse stretch at `time`
+    fn parts_at(&self, time: T) -> (Vector3D<T>, [T; 4], Matrix4<T>) {
+        if self.time_end <= self.time_start {
+            return self.parts(if time < self.time_end { T::zero() } else { T::one() });
+        }
+        self.parts(((time - self.time_start) / (self.time_end - self.time_start)).max(T::zero()).min(T::one()))
+    }
+
+    // Translation, rotation and inverse stretch at `fraction` of the way from the start to the end
+    fn parts(&self, fraction: T) -> (Vector3D<T>, [T; 4], Matrix4<T>) {
+        let (start, end) = (&self.start, &self.end);
+        let translation = start.translation + (end.translation - start.translation) * fraction;
+        let mut inverse_stretch = start.inverse_stretch;
+        for (row, end_row) in inverse_stretch.rows.iter_mut().zip(end.inverse_stretch.rows.iter()) {
+            for (value, end_value) in row.iter_mut().zip(end_row.iter()) {
+                *value = *value + (*end_value - *value) * fraction;
+            }
+        }
+        (translation, slerp(&start.rotation, &end.rotation, fraction), inverse_stretch)
+    }
+
+    // Transform at `fraction` of the way from the start to the end, None where it is singular
+    fn interpolate(&self, fraction: T) -> Option<Matrix4<T>> {
+        let (translation, rotation, inverse_stretch) = self.parts(fraction);
+        let stretch = inverse_stretch.inverse()?;
+        Some(Matrix4::translation(&translation) * quaternion_to_matrix(rotation) * stretch)
+    }
+}
+
+fn box_corners<T: DataTypeTraits>(bbox: &AABB<T>) -> [Vector3D<T>; 8] {
+    std::array::from_fn(|corner| Vector3D { x: if corner & 1 == 0 { bbox.minimum.x } else { bbox.maximum.x },
+                                            y: if corner & 2 == 0 { bbox.minimum.y } else { bbox.maximum.y },
+                                            z: if corner & 4 == 0 { bbox.minimum.z } else { bbox.maximum.z } })
+}
+
+// Splits `transform` into translation * rotation * stretch, with the rotation found by polar
+// decomposition of the linear part (Shoemake and Duff, "Matrix animation and polar decomposition")
+fn decompose<T: DataTypeTraits>(transform: &Matrix4<T>) -> DecomposedTransform<T> {
+    let translation = Vector3D { x: transform.rows[0][3], y: transform.rows[1][3], z: transform.rows[2][3] };
+    let mut linear = *transform;
+    for row in 0..3 {
+        linear.rows[row][3] = T::zero();
+    }
+
+    // Averaging a matrix with its inverse transpose converges to the closest orthogonal matrix
+    let half = T::from(0.5).unwrap();
+    let mut rotation = linear;
+    for _ in 0..100 {
+        let inverse_transpose = match rotation.inverse() {
+            Some(inverse) => inverse.transpose(),
+            None => break,
+        };
+        let mut next = rotation;
+        let mut change = T::zero();
+        for row in 0..3 {
+            for column in 0..3 {
+                next.rows[row][column] = half * (rotation.rows[row][column] + inverse_transpose.rows[row][column]);
+                change = change.max((next.rows[row][column] - rotation.rows[row][column]).abs());
+            }
+        }
+        rotation = next;
+        if change < T::from(1e-7).unwrap() {
+            break;
+        }
+    }
+    // A mirroring transform gives a reflection; its opposite is a rotation, and the stretch takes
+    // the reflection instead
+    if rotation.linear_determinant() < T::zero() {
+        for row in 0..3 {
+            for column in 0..3 {
+                rotation.rows[row][column] = -rotation.rows[row][column];
+            }
+        }
+    }
+    let stretch = rotation.transpose() * linear;
+    DecomposedTransform { translation: translation, rotation: matrix_to_quaternion(&rotation),
+                          inverse_stretch: stretch.inverse().expect("instance transform must be invertible") }
+}
+
+fn matrix_to_quaternion<T: DataTypeTraits>(matrix: &Matrix4<T>) -> [T; 4] {
+    let m = &matrix.rows;
+    let (one, two, quarter) = (T::one(), T::from(2.0).unwrap(), T::from(0.25).unwrap());
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    // Computed from the largest of the four components, for accuracy
+    let quaternion = if trace > T::zero() {
+        let s = (trace + one).sqrt() * two;
+        [quarter * s, (m[2][1] - m[1][2]) / s, (m[0][2] - m[2][0]) / s, (m[1][0] - m[0][1]) / s]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = (one + m[0][0] - m[1][1] - m[2][2]).sqrt() * two;
+        [(m[2][1] - m[1][2]) / s, quarter * s, (m[0][1] + m[1][0]) / s, (m[0][2] + m[2][0]) / s]
+    } else if m[1][1] > m[2][2] {
+        let s = (one + m[1][1] - m[0][0] - m[2][2]).sqrt() * two;
+        [(m[0][2] - m[2][0]) / s, (m[0][1] + m[1][0]) / s, quarter * s, (m[1][2] + m[2][1]) / s]
+    } else {
+        let s = (one + m[2][2] - m[0][0] - m[1][1]).sqrt() * two;
+        [(m[1][0] - m[0][1]) / s, (m[0][2] + m[2][0]) / s, (m[1][2] + m[2][1]) / s, quarter * s]
+    };
+    normalized(quaternion)
+}
+
+fn quaternion_to_matrix<T: DataTypeTraits>(quaternion: [T; 4]) -> Matrix4<T> {
+    let [w, x, y, z] = quaternion;
+    let (zero, one, two) = (T::zero(), T::one(), T::from(2.0).unwrap());
+    Matrix4::from_rows([[one - two * (y * y + z * z), two * (x * y - w * z), two * (x * z + w * y), zero],
+                        [two * (x * y + w * z), one - two * (x * x + z * z), two * (y * z - w * x), zero],
+                        [two * (x * z - w * y), two * (y * z + w * x), one - two * (x * x + y * y), zero],
+                        [zero, zero, zero, one]])
+}
+
+// Inverse rotation of a unit quaternion
+fn conjugate<T: DataTypeTraits>(quaternion: [T; 4]) -> [T; 4] {
+    let [w, x, y, z] = quaternion;
+    [w, -x, -y, -z]
+}
+
+// Adjugate of the linear part of `matrix`, which is its inverse times its determinant
+fn adjugate<T: DataTypeTraits>(matrix: &Matrix4<T>) -> Matrix4<T> {
+    let m = &matrix.rows;
+    let (zero, one) = (T::zero(), T::one());
+    let cofactor = |row: usize, column: usize| {
+        let (r0, r1) = ((row + 1) % 3, (row + 2) % 3);
+        let (c0, c1) = ((column + 1) % 3, (column + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+    Matrix4::from_rows([[cofactor(0, 0), cofactor(1, 0), cofactor(2, 0), zero],
+                        [cofactor(0, 1), cofactor(1, 1), cofactor(2, 1), zero],
+                        [cofactor(0, 2), cofactor(1, 2), cofactor(2, 2), zero],
+                        [zero, zero, zero, one]])
+}
+
+// Spherical linear interpolation of unit quaternions, along the shortest arc
+fn slerp<T: DataTypeTraits>(a: &[T; 4], b: &[T; 4], fraction: T) -> [T; 4] {
+    let mut cosine = (0..4).fold(T::zero(), |sum, index| sum + a[index] * b[index]);
+    let mut b = *b;
+    if cosine < T::zero() {
+        b = b.map(|component| -component);
+        cosine = -cosine;
+    }
+    // Nearly equal rotations: linear interpolation is accurate and avoids dividing by ~0
+    if cosine > T::from(0.9995).unwrap() {
+        return normalized(std::array::from_fn(|index| a[index] + (b[index] - a[index]) * fraction));
+    }
+    let angle = cosine.min(T::one()).acos();
+    let (weight_a, weight_b) = (((T::one() - fraction) * angle).sin(), (fraction * angle).sin());
+    normalized(std::array::from_fn(|index| a[index] * weight_a + b[index] * weight_b))
+}
+
+fn normalized<T: DataTypeTraits>(quaternion: [T; 4]) -> [T; 4] {
+    let length = quaternion.iter().fold(T::zero(), |sum, component| sum + *component * *component).sqrt();
+    quaternion.map(|component| component / length)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_lib::RGBColor;
+    use material_lib::Lambertian;
+    use sphere_lib::Sphere;
+
+    fn assert_identity(matrix: &Matrix4<f64>) {
+        for (row, values) in matrix.rows.iter().enumerate() {
+            for (column, value) in values.iter().enumerate() {
+                let expected = if row == column { 1.0 } else { 0.0 };
+                assert!((value - expected).abs() < 1e-9, "{:?}", matrix.rows);
+            }
+        }
+    }
+
+    // The inverse built from the interpolated parts undoes the interpolated transform
+    #[test]
+    fn moving_inverse_matches_transform() {
+        let start = Matrix4::translation(&Vector3D { x: 1.0, y: -2.0, z: 0.5 })
+            * Matrix4::rotation(&Vector3D { x: 0.0, y: 1.0, z: 0.0 }, 0.3)
+            * Matrix4::scaling(&Vector3D { x: 2.0, y: 1.0, z: 0.5 });
+        let end = Matrix4::translation(&Vector3D { x: -3.0, y: 0.0, z: 4.0 })
+            * Matrix4::rotation(&Vector3D { x: 1.0, y: 1.0, z: 0.0 }.unit_vector(), 2.0)
+            * Matrix4::scaling(&Vector3D { x: 1.0, y: 3.0, z: 1.0 });
+        let motion = Motion { start: decompose(&start), end: decompose(&end), time_start: 0.0, time_end: 1.0 };
+        for time in [0.0, 0.25, 0.5, 0.9, 1.0] {
+            let (translation, rotation, inverse_stretch) = motion.parts_at(time);
+            let inverse = inverse_stretch * quaternion_to_matrix(conjugate(rotation)) * Matrix4::translation(&-translation);
+            assert_identity(&(inverse * motion.interpolate(time).unwrap()));
+            // The adjugate is the inverse scaled by the determinant
+            let stretch = adjugate(&inverse_stretch) * Matrix4::scaling(&(Vector3D { x: 1.0, y: 1.0, z: 1.0 } / inverse_stretch.linear_determinant()));
+            assert_identity(&(stretch * inverse_stretch));
+        }
+        assert_identity(&(motion.interpolate(0.0).unwrap() * start.inverse().unwrap()));
+        assert_identity(&(motion.interpolate(1.0).unwrap() * end.inverse().unwrap()));
+    }
+
+    fn vector(x: f64, y: f64, z: f64) -> Vector3D<f64> {
+        Vector3D { x: x, y: y, z: z }
+    }
+
+    fn assert_close(actual: &Vector3D<f64>, expected: Vector3D<f64>) {
+        assert!((*actual - expected).norm() < 1e-9, "({}, {}, {}) != ({}, {}, {})",
+                actual.x, actual.y, actual.z, expected.x, expected.y, expected.z);
+    }
+
+    // Unit sphere stretched to 2 along its x axis, which is turned onto the world y axis, and moved
+    // to (1, 2, 3): the ellipsoid (x - 1)² + (y - 2)² / 4 + (z - 3)² = 1
+    fn ellipsoid() -> Instance<f64> {
+        let material = Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 }));
+        let sphere = Arc::new(Sphere::new(vector(0.0, 0.0, 0.0), 1.0, material));
+        let transform = Matrix4::translation(&vector(1.0, 2.0, 3.0))
+            * Matrix4::rotation(&vector(0.0, 0.0, 1.0), std::f64::consts::FRAC_PI_2)
+            * Matrix4::scaling(&vector(2.0, 1.0, 1.0));
+        Instance::new(sphere, transform)
+    }
+
+    fn hit(instance: &Instance<f64>, origin: Vector3D<f64>, direction: Vector3D<f64>) -> Option<HitRecord<f64>> {
+        let mut hit_record = HitRecord::default();
+        let ray = Ray3D { origin: origin, direction: direction, time: 0.0 };
+        if instance.hit(&ray, 1e-6, f64::INFINITY, &mut hit_record) { Some(hit_record) } else { None }
+    }
+
+    #[test]
+    fn static_instance_hits_in_world_space() {
+        let ellipsoid = ellipsoid();
+        // Straight down onto the ellipsoid where y - 2 = 1, i.e. half way along its long axis
+        let record = hit(&ellipsoid, vector(1.0, 3.0, 10.0), vector(0.0, 0.0, -2.0)).unwrap();
+        let height = 0.75_f64.sqrt();
+        assert!((record.get_t() - (7.0 - height) / 2.0).abs() < 1e-9);
+        assert_close(&record.get_point(), vector(1.0, 3.0, 3.0 + height));
+        // The gradient (x - 1, (y - 2) / 4, z - 3) of the ellipsoid, not the transformed sphere
+        // normal (0, 1, height)
+        let normal = vector(0.0, 0.25, height).unit_vector();
+        assert_close(&record.get_normal_vector(), normal);
+        assert_close(&record.get_geometric_normal(), normal);
+        assert!(record.get_front_face());
+        // The tangents stay on the surface
+        assert!(record.get_tangent().inner_product(&normal).abs() < 1e-9);
+        assert!(record.get_bitangent().inner_product(&normal).abs() < 1e-9);
+
+        // Along the long axis from inside, and beside the short one
+        let record = hit(&ellipsoid, vector(1.0, 2.0, 3.0), vector(0.0, 1.0, 0.0)).unwrap();
+        assert!((record.get_t() - 2.0).abs() < 1e-9);
+        assert_close(&record.get_point(), vector(1.0, 4.0, 3.0));
+        assert_close(&record.get_normal_vector(), vector(0.0, -1.0, 0.0));
+        assert!(!record.get_front_face());
+        assert!(hit(&ellipsoid, vector(2.5, 2.0, 10.0), vector(0.0, 0.0, -1.0)).is_none());
+        assert!(hit(&ellipsoid, vector(1.0, 3.5, 10.0), vector(0.0, 0.0, -1.0)).is_some());
+    }
+
+    #[test]
+    fn static_instance_bounding_box() {
+        let bbox = ellipsoid().bounding_box().unwrap();
+        assert_close(&bbox.minimum, vector(0.0, 0.0, 2.0));
+        assert_close(&bbox.maximum, vector(2.0, 4.0, 4.0));
+        assert_identity(&(ellipsoid().get_transform() * ellipsoid().inverse));
+    }
+}
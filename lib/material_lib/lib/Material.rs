@@ -0,0 +1,383 @@
+use std::sync::Arc;
+
+use hittable_material_traits::{HitRecord, Material, ScatterRecord};
+
+use vector_lib::DataTypeTraits;
+use vector_lib::{Vector3D, VectorOperations};
+
+use color_lib::RGBColor;
+
+use ray_lib::Ray3D;
+
+use texture_lib::{SolidColor, Texture};
+
+use sampler_lib::{CosineHemisphere, Sampler};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// LAMBERTIAN MATERIAL ////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Ideal diffuse surface: reflects light equally in all directions, with BRDF albedo / pi. Directions
+// are importance sampled with the cosine-weighted distribution around the shading normal, so each
+// bounce is attenuated by the albedo alone.
+#[derive(Clone)]
+pub struct Lambertian<T: DataTypeTraits>
+{
+    pub albedo: Arc<dyn Texture<T> + Send + Sync>,
+}
+
+// Implementing Lambertian<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Lambertian<T>
+{
+    pub fn new(albedo: RGBColor<T>) -> Self {
+        Lambertian { albedo: Arc::new(SolidColor::new(albedo)) }
+    }
+}
+
+// Implementing Lambertian<T> initialization with a varying albedo through <T>::textured()
+impl<T: DataTypeTraits> Lambertian<T>
+{
+    pub fn textured(albedo: Arc<dyn Texture<T> + Send + Sync>) -> Self {
+        Lambertian { albedo: albedo }
+    }
+}
+
+impl<T: DataTypeTraits> Material<T> for Lambertian<T> {
+    fn scatter(
+        &self,
+        ray_in: &Ray3D<T>,
+        rec: &HitRecord<T>,
+        scatter_record: &mut ScatterRecord<T>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        let normal = rec.get_normal_vector();
+        let distribution = CosineHemisphere::new(&normal);
+        let direction = distribution.sample(sampler);
+        let scattered = Ray3D { origin: rec.get_point(), direction: direction, time: ray_in.time };
+        *scatter_record = ScatterRecord::sampled(scattered, self.albedo.value_at(rec) / T::PI(),
+                                                 direction.inner_product(&normal), distribution.pdf(&direction));
+        true
+    }
+
+    fn brdf(&self, _ray_in: &Ray3D<T>, rec: &HitRecord<T>, scattered: &Ray3D<T>) -> RGBColor<T> {
+        // No light is reflected from below the surface
+        if scattered.direction.inner_product(&rec.get_normal_vector()) <= T::zero() {
+            return RGBColor::zeros();
+        }
+        self.albedo.value_at(rec) / T::PI()
+    }
+
+    fn scattering_pdf(&self, _ray_in: &Ray3D<T>, rec: &HitRecord<T>, scattered: &Ray3D<T>) -> T {
+        CosineHemisphere::new(&rec.get_normal_vector()).pdf(&scattered.direction)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// METAL MATERIAL ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Specular reflector. The reflected direction is perturbed by a random point on a sphere of
+// radius `fuzz`, going from a perfect mirror (fuzz = 0) to brushed metal (fuzz = 1).
+#[derive(Clone)]
+pub struct Metal<T: DataTypeTraits>
+{
+    pub albedo: Arc<dyn Texture<T> + Send + Sync>,
+    pub fuzz: T,
+}
+
+// Implementing Metal<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Metal<T>
+{
+    pub fn new(albedo: RGBColor<T>, fuzz: T) -> Self {
+        Metal::textured(Arc::new(SolidColor::new(albedo)), fuzz)
+    }
+}
+
+// Implementing Metal<T> initialization with a varying albedo through <T>::textured()
+impl<T: DataTypeTraits> Metal<T>
+{
+    pub fn textured(albedo: Arc<dyn Texture<T> + Send + Sync>, fuzz: T) -> Self {
+        Metal { albedo: albedo,
+                fuzz: utilities_lib::clamp(fuzz, T::zero(), T::one()) }
+    }
+}
+
+impl<T: DataTypeTraits> Material<T> for Metal<T> {
+    fn scatter(
+        &self,
+        ray_in: &Ray3D<T>,
+        rec: &HitRecord<T>,
+        scatter_record: &mut ScatterRecord<T>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        let normal = rec.get_normal_vector();
+        let reflected = utilities_lib::reflect(&ray_in.direction.unit_vector(), &normal);
+        let fuzzed = reflected + sampler.sample_sphere() * self.fuzz;
+        let scattered = Ray3D { origin: rec.get_point(), direction: fuzzed, time: ray_in.time };
+        *scatter_record = ScatterRecord::specular(scattered, self.albedo.value_at(rec));
+        // Fuzzed rays ending up below the surface are absorbed
+        fuzzed.inner_product(&normal) > T::zero()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// DIELECTRIC MATERIAL ////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Clear refractive material (glass, water, ...) described by its index of refraction. Rays are
+// either reflected or refracted, chosen randomly with the Fresnel reflectance as probability.
+#[derive(Clone, Copy)]
+pub struct Dielectric<T: DataTypeTraits>
+{
+    pub index_of_refraction: T,
+}
+
+// Implementing Dielectric<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Dielectric<T>
+{
+    pub fn new(index_of_refraction: T) -> Self {
+        Dielectric { index_of_refraction: index_of_refraction }
+    }
+}
+
+impl<T: DataTypeTraits> Dielectric<T>
+{
+    // Schlick's approximation of the Fresnel reflectance
+    #[inline(always)]
+    fn reflectance(cosine: T, refraction_ratio: T) -> T {
+        let r0 = (T::one() - refraction_ratio) / (T::one() + refraction_ratio);
+        let r0 = r0 * r0;
+        r0 + (T::one() - r0) * (T::one() - cosine).powi(5)
+    }
+}
+
+impl<T: DataTypeTraits> Material<T> for Dielectric<T> {
+    fn scatter(
+        &self,
+        ray_in: &Ray3D<T>,
+        rec: &HitRecord<T>,
+        scatter_record: &mut ScatterRecord<T>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        let refraction_ratio = if rec.get_front_face() { T::one() / self.index_of_refraction }
+                               else { self.index_of_refraction };
+
+        let unit_direction = ray_in.direction.unit_vector();
+        let normal = rec.get_normal_vector();
+        let cos_theta = (-unit_direction).inner_product(&normal).min(T::one());
+        let sin_theta = (T::one() - cos_theta * cos_theta).sqrt();
+
+        // No solution to Snell's law -> total internal reflection
+        let cannot_refract = refraction_ratio * sin_theta > T::one();
+        let direction = if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > sampler.get_1d() {
+            utilities_lib::reflect(&unit_direction, &normal)
+        } else {
+            utilities_lib::refract(&unit_direction, &normal, refraction_ratio)
+        };
+        // Glass absorbs nothing
+        let scattered = Ray3D { origin: rec.get_point(), direction: direction, time: ray_in.time };
+        *scatter_record = ScatterRecord::specular(scattered, RGBColor::ones());
+        true
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// DIFFUSE LIGHT MATERIAL //////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Area light: emits the same radiance in all directions and absorbs all incoming light. One-sided
+// lights only emit from the front of the surface (the side the outward normal points to).
+#[derive(Clone)]
+pub struct DiffuseLight<T: DataTypeTraits>
+{
+    pub emit: Arc<dyn Texture<T> + Send + Sync>,
+    pub two_sided: bool,
+}
+
+// Implementing DiffuseLight<T> initialization through <T>::new(), emitting from both sides
+impl<T: DataTypeTraits> DiffuseLight<T>
+{
+    pub fn new(emit: RGBColor<T>) -> Self {
+        DiffuseLight { emit: Arc::new(SolidColor::new(emit)), two_sided: true }
+    }
+}
+
+// Implementing DiffuseLight<T> initialization with a varying radiance through <T>::textured()
+impl<T: DataTypeTraits> DiffuseLight<T>
+{
+    pub fn textured(emit: Arc<dyn Texture<T> + Send + Sync>, two_sided: bool) -> Self {
+        DiffuseLight { emit: emit, two_sided: two_sided }
+    }
+}
+
+impl<T: DataTypeTraits> Material<T> for DiffuseLight<T> {
+    fn scatter(
+        &self,
+        _ray_in: &Ray3D<T>,
+        _rec: &HitRecord<T>,
+        _scatter_record: &mut ScatterRecord<T>,
+        _sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        false
+    }
+
+    fn emitted(&self, _ray_in: &Ray3D<T>, rec: &HitRecord<T>) -> RGBColor<T> {
+        if !self.two_sided && !rec.get_front_face() {
+            return RGBColor::zeros();
+        }
+        self.emit.value_at(rec)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// SURFACE DETAIL //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Copy of `rec` shaded with the unit normal `normal`, given on the outer side of the surface. A
+// normal that would face into the surface as seen by the incoming ray leaves `rec` as it is.
+fn with_shading_normal<T: DataTypeTraits>(rec: &HitRecord<T>, normal: Vector3D<T>) -> HitRecord<T> {
+    let normal = if rec.get_front_face() { normal } else { -normal };
+    let mut perturbed = rec.clone();
+    if !(normal.inner_product(&rec.get_geometric_normal()) > T::zero()) {
+        return perturbed;
+    }
+    perturbed.set_normal_vector(normal);
+    perturbed.set_tangents(&rec.get_tangent(), &rec.get_bitangent());
+    perturbed
+}
+
+// Shading normal of `rec` on the outer side of the surface
+#[inline(always)]
+fn outwards_normal<T: DataTypeTraits>(rec: &HitRecord<T>) -> Vector3D<T> {
+    if rec.get_front_face() { rec.get_normal_vector() } else { -rec.get_normal_vector() }
+}
+
+// Wraps a material, bending its shading normal by a tangent-space normal map: the color channels
+// (r, g, b) in [0, 1] encode the normal 2 (r, g, b) - 1 along (tangent, bitangent, normal), with
+// green pointing towards increasing v (the OpenGL convention). The map should hold linear values.
+// `strength` scales the tilt, 0 leaving the surface flat.
+#[derive(Clone)]
+pub struct NormalMapped<T: DataTypeTraits>
+{
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    pub normal_map: Arc<dyn Texture<T> + Send + Sync>,
+    pub strength: T,
+}
+
+// Implementing NormalMapped<T> initialization through <T>::new()
+impl<T: DataTypeTraits> NormalMapped<T>
+{
+    pub fn new(material: Arc<dyn Material<T> + Send + Sync>, normal_map: Arc<dyn Texture<T> + Send + Sync>, strength: T) -> Self {
+        NormalMapped { material: material, normal_map: normal_map, strength: strength }
+    }
+}
+
+impl<T: DataTypeTraits> NormalMapped<T>
+{
+    fn perturbed(&self, rec: &HitRecord<T>) -> HitRecord<T> {
+        let color = self.normal_map.value_at(rec);
+        let two = T::from(2.0).unwrap();
+        let (x, y, z) = ((two * color.R - T::one()) * self.strength, (two * color.G - T::one()) * self.strength, two * color.B - T::one());
+        let normal = rec.get_tangent() * x + rec.get_bitangent() * y + outwards_normal(rec) * z;
+        let norm = normal.norm();
+        if !(norm > T::zero()) || !norm.is_finite() {
+            return rec.clone();
+        }
+        with_shading_normal(rec, normal / norm)
+    }
+}
+
+impl<T: DataTypeTraits> Material<T> for NormalMapped<T> {
+    fn scatter(
+        &self,
+        ray_in: &Ray3D<T>,
+        rec: &HitRecord<T>,
+        scatter_record: &mut ScatterRecord<T>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        self.material.scatter(ray_in, &self.perturbed(rec), scatter_record, sampler)
+    }
+
+    fn emitted(&self, ray_in: &Ray3D<T>, rec: &HitRecord<T>) -> RGBColor<T> {
+        self.material.emitted(ray_in, &self.perturbed(rec))
+    }
+
+    fn brdf(&self, ray_in: &Ray3D<T>, rec: &HitRecord<T>, scattered: &Ray3D<T>) -> RGBColor<T> {
+        self.material.brdf(ray_in, &self.perturbed(rec), scattered)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray3D<T>, rec: &HitRecord<T>, scattered: &Ray3D<T>) -> T {
+        self.material.scattering_pdf(ray_in, &self.perturbed(rec), scattered)
+    }
+}
+
+// Step in (u, v), and in space along the tangent frame, of the finite differences of bump maps
+const BUMP_DELTA: f64 = 0.0005;
+
+// Wraps a material, bending its shading normal as if the surface were displaced along its normal
+// by `scale` times the height texture (the mean of its color channels). Heights are differentiated
+// with respect to u and v, so `scale` is relative to the (u, v) mapping of the surface.
+#[derive(Clone)]
+pub struct BumpMapped<T: DataTypeTraits>
+{
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    pub height: Arc<dyn Texture<T> + Send + Sync>,
+    pub scale: T,
+}
+
+// Implementing BumpMapped<T> initialization through <T>::new()
+impl<T: DataTypeTraits> BumpMapped<T>
+{
+    pub fn new(material: Arc<dyn Material<T> + Send + Sync>, height: Arc<dyn Texture<T> + Send + Sync>, scale: T) -> Self {
+        BumpMapped { material: material, height: height, scale: scale }
+    }
+}
+
+impl<T: DataTypeTraits> BumpMapped<T>
+{
+    fn perturbed(&self, rec: &HitRecord<T>) -> HitRecord<T> {
+        let delta = T::from(BUMP_DELTA).unwrap();
+        let (uv, point) = (rec.get_uv(), rec.get_point());
+        let (tangent, bitangent, normal) = (rec.get_tangent(), rec.get_bitangent(), outwards_normal(rec));
+        let height = |u: T, v: T, point: &Vector3D<T>| {
+            let color = self.height.value(u, v, point);
+            (color.R + color.G + color.B) / T::from(3.0).unwrap() * self.scale
+        };
+        let base = height(uv.x, uv.y, &point);
+        let slope_u = (height(uv.x + delta, uv.y, &(point + tangent * delta)) - base) / delta;
+        let slope_v = (height(uv.x, uv.y + delta, &(point + bitangent * delta)) - base) / delta;
+        // Normal of the displaced surface, from its derivatives along u and v
+        let displaced = (tangent + normal * slope_u).cross_product(&(bitangent + normal * slope_v));
+        let norm = displaced.norm();
+        if !(norm > T::zero()) || !norm.is_finite() {
+            return rec.clone();
+        }
+        // Left-handed frames (mirrored texture coordinates) give the inwards normal
+        let displaced = if displaced.inner_product(&normal) < T::zero() { -displaced } else { displaced };
+        with_shading_normal(rec, displaced / norm)
+    }
+}
+
+impl<T: DataTypeTraits> Material<T> for BumpMapped<T> {
+    fn scatter(
+        &self,
+        ray_in: &Ray3D<T>,
+        rec: &HitRecord<T>,
+        scatter_record: &mut ScatterRecord<T>,
+        sampler: &mut dyn Sampler<T>,
+    ) -> bool {
+        self.material.scatter(ray_in, &self.perturbed(rec), scatter_record, sampler)
+    }
+
+    fn emitted(&self, ray_in: &Ray3D<T>, rec: &HitRecord<T>) -> RGBColor<T> {
+        self.material.emitted(ray_in, &self.perturbed(rec))
+    }
+
+    fn brdf(&self, ray_in: &Ray3D<T>, rec: &HitRecord<T>, scattered: &Ray3D<T>) -> RGBColor<T> {
+        self.material.brdf(ray_in, &self.perturbed(rec), scattered)
+    }
+
+    fn scattering_pdf(&self, ray_in: &Ray3D<T>, rec: &HitRecord<T>, scattered: &Ray3D<T>) -> T {
+        self.material.scattering_pdf(ray_in, &self.perturbed(rec), scattered)
+    }
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use hittable_material_traits::{HitRecord, Hittable, Material};
+
+use vector_lib::{Vector2D, Vector3D};
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+use ray_lib::Ray3D;
+
+use aabb_lib::AABB;
+
+use bvh_lib::BVH;
+
+use triangle_lib::{intersect_triangle, set_triangle_hit_record};
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// STRUCT DEFINITIONS //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Vertex and index buffers of a triangle mesh. Vertices are shared between the triangles that
+// reference them. `normals`, `uvs` and `colors` are either empty or hold one entry per position; a zero
+// normal marks a vertex without one, in which case the triangle is shaded with its geometric
+// normal. Triangle `i` uses `materials[material_indices[i]]`.
+#[derive(Clone)]
+pub struct MeshData<T: DataTypeTraits>
+{
+    pub positions: Vec<Vector3D<T>>,
+    pub normals: Vec<Vector3D<T>>,
+    pub uvs: Vec<Vector2D<T>>,
+    pub colors: Vec<RGBColor<T>>,
+    pub triangles: Vec<[u32; 3]>,
+    pub material_indices: Vec<u32>,
+    pub materials: Vec<Arc<dyn Material<T> + Send + Sync>>,
+}
+
+// Indexed triangle mesh. The triangles only refer to the shared mesh data and are kept in a
+// bounding volume hierarchy of their own.
+pub struct TriangleMesh<T: DataTypeTraits>
+{
+    data: Arc<MeshData<T>>,
+    bvh: BVH<T>,
+}
+
+// Single triangle of a mesh, as stored in the hierarchy of the mesh
+struct MeshTriangle<T: DataTypeTraits>
+{
+    mesh: Arc<MeshData<T>>,
+    index: usize,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////// INITIALIZATION IMPL  /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Implementing MeshData<T> initialization through <T>::new()
+impl<T: DataTypeTraits> MeshData<T>
+{
+    // Mesh without vertex attributes, every triangle using `material`
+    pub fn new(positions: Vec<Vector3D<T>>, triangles: Vec<[u32; 3]>, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let material_indices = vec![0; triangles.len()];
+        MeshData { positions: positions, normals: Vec::new(), uvs: Vec::new(), colors: Vec::new(), triangles: triangles,
+                   material_indices: material_indices, materials: vec![material] }
+    }
+}
+
+// Implementing TriangleMesh<T> initialization through <T>::new()
+impl<T: DataTypeTraits> TriangleMesh<T>
+{
+    pub fn new(data: MeshData<T>) -> Self {
+        let vertex_count = data.positions.len();
+        assert!(data.normals.is_empty() || data.normals.len() == vertex_count,
+                "mesh must have no normals or one normal per vertex");
+        assert!(data.uvs.is_empty() || data.uvs.len() == vertex_count,
+                "mesh must have no texture coordinates or one pair per vertex");
+        assert!(data.colors.is_empty() || data.colors.len() == vertex_count,
+                "mesh must have no vertex colors or one color per vertex");
+        assert!(data.material_indices.len() == data.triangles.len(),
+                "mesh must have one material index per triangle");
+        assert!(data.triangles.iter().flatten().all(|&index| (index as usize) < vertex_count),
+                "mesh triangle refers to a vertex that does not exist");
+        assert!(data.material_indices.iter().all(|&index| (index as usize) < data.materials.len()),
+                "mesh triangle refers to a material that does not exist");
+
+        let data = Arc::new(data);
+        let triangles = (0..data.triangles.len())
+            .map(|index| Box::new(MeshTriangle { mesh: data.clone(), index: index }) as Box<dyn Hittable<T> + Send + Sync>)
+            .collect();
+        TriangleMesh { data: data, bvh: BVH::new(triangles) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// GETTER IMPLS /////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> TriangleMesh<T> {
+    pub fn data(&self) -> &MeshData<T> {
+        &self.data
+    }
+}
+
+impl<T: DataTypeTraits> TriangleMesh<T> {
+    // Number of triangles in the mesh
+    pub fn len(&self) -> usize {
+        self.data.triangles.len()
+    }
+}
+
+impl<T: DataTypeTraits> TriangleMesh<T> {
+    pub fn is_empty(&self) -> bool {
+        self.data.triangles.is_empty()
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// HITTABLE IMPLS ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl<T: DataTypeTraits> Hittable<T> for MeshTriangle<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let mesh = &self.mesh;
+        let [i0, i1, i2] = mesh.triangles[self.index].map(|index| index as usize);
+        let vertices = [&mesh.positions[i0], &mesh.positions[i1], &mesh.positions[i2]];
+        let intersection = match intersect_triangle(ray, vertices[0], vertices[1], vertices[2], t_min, t_max) {
+            Some(intersection) => intersection,
+            None => return false,
+        };
+        let normals = if mesh.normals.is_empty() { None } else { Some([&mesh.normals[i0], &mesh.normals[i1], &mesh.normals[i2]]) };
+        let normals = normals.filter(|normals| normals.iter().all(|normal| **normal != Vector3D::default()));
+        let uvs = if mesh.uvs.is_empty() { None } else { Some([&mesh.uvs[i0], &mesh.uvs[i1], &mesh.uvs[i2]]) };
+        let material = &mesh.materials[mesh.material_indices[self.index] as usize];
+        set_triangle_hit_record(hit_record, ray, &intersection, vertices, normals, uvs, material);
+        if !mesh.colors.is_empty() {
+            let (b1, b2) = (intersection.b1, intersection.b2);
+            let b0 = T::one() - b1 - b2;
+            (*hit_record).set_vertex_color(Some(mesh.colors[i0] * b0 + mesh.colors[i1] * b1 + mesh.colors[i2] * b2));
+        }
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let mesh = &self.mesh;
+        let [i0, i1, i2] = mesh.triangles[self.index].map(|index| index as usize);
+        let bbox = AABB::from_points(&[mesh.positions[i0], mesh.positions[i1], mesh.positions[i2]]);
+        let extent = bbox.extent();
+        let largest_extent = extent.x.max(extent.y).max(extent.z);
+        Some(bbox.ensure_thickness(largest_extent * T::from(1e-6).unwrap() + T::min_positive_value()))
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for TriangleMesh<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        self.bvh.hit(ray, t_min, t_max, hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        self.bvh.bounding_box()
+    }
+}
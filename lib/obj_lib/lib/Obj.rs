@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use vector_lib::{Vector2D, Vector3D};
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+use material_lib::{Dielectric, Lambertian, Metal};
+
+use mesh_lib::{MeshData, TriangleMesh};
+
+use hittable_material_traits::Material;
+
+/*
+    Supported OBJ statements: v, vt, vn, f (any polygon, fan triangulated, with v, v/vt, v//vn or
+    v/vt/vn vertices and negative indices counting back from the last element), g and o (groups),
+    mtllib and usemtl. Other statements (s, l, p, ...) are ignored. Material libraries that cannot
+    be read and materials missing from them leave the faces with the default material, unless the
+    file is loaded strictly.
+
+    Supported MTL statements: newmtl, Kd, Ks, Ns, Ni, d, Tr and illum. Materials become
+      - Dielectric when they are transparent (d < 1, Tr > 0 or illum 4, 6, 7 or 9),
+      - Metal when they are mirror-like (illum 3 or 5, or a specular colour with a Phong exponent
+        Ns of at least 900), with Ns turned into a fuzz of sqrt(2 / (Ns + 2)),
+      - Lambertian otherwise.
+ */
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// ERROR TYPE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum ObjError {
+    Io { path: PathBuf, error: std::io::Error },
+    Parse { path: PathBuf, line: usize, message: String },
+}
+
+impl std::fmt::Display for ObjError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "{}: {}", path.display(), error),
+            ObjError::Parse { path, line, message } => write!(f, "{}:{}: {}", path.display(), line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { error, .. } => Some(error),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// STRUCT DEFINITIONS //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Named range of triangles of the mesh, from a `g` or `o` statement
+#[derive(Clone, Debug)]
+pub struct ObjGroup
+{
+    pub name: String,
+    pub triangles: Range<usize>,
+}
+
+// Mesh read from an OBJ file, with the material problems it was loaded in spite of
+pub struct ObjModel<T: DataTypeTraits>
+{
+    pub mesh: TriangleMesh<T>,
+    pub groups: Vec<ObjGroup>,
+    pub warnings: Vec<ObjError>,
+}
+
+// Material read from an MTL file
+#[derive(Clone, Debug)]
+pub struct MtlMaterial
+{
+    pub name: String,
+    pub diffuse: [f64; 3],
+    pub specular: [f64; 3],
+    pub shininess: f64,
+    pub index_of_refraction: f64,
+    pub dissolve: f64,
+    pub illumination_model: Option<u32>,
+}
+
+impl MtlMaterial {
+    fn new(name: &str) -> Self {
+        MtlMaterial { name: name.to_string(), diffuse: [0.8; 3], specular: [0.0; 3], shininess: 0.0,
+                      index_of_refraction: 1.5, dissolve: 1.0, illumination_model: None }
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.dissolve < 1.0 || matches!(self.illumination_model, Some(4 | 6 | 7 | 9))
+    }
+
+    // Ordinary highlights (e.g. of plastics) keep a material diffuse; only reflection models and
+    // nearly perfect highlights make it a mirror
+    fn is_mirror(&self) -> bool {
+        matches!(self.illumination_model, Some(3 | 5))
+            || (self.shininess >= MIRROR_SHININESS && max_component(&self.specular) > 0.0)
+    }
+
+    // Closest material of the renderer
+    pub fn to_material<T: DataTypeTraits>(&self) -> Arc<dyn Material<T> + Send + Sync> {
+        let to_color = |values: &[f64; 3]| RGBColor { R: T::from(values[0]).unwrap(),
+                                                      G: T::from(values[1]).unwrap(),
+                                                      B: T::from(values[2]).unwrap() };
+        if self.is_transparent() {
+            Arc::new(Dielectric::new(T::from(self.index_of_refraction).unwrap()))
+        } else if self.is_mirror() {
+            let albedo = if max_component(&self.specular) > 0.0 { &self.specular } else { &self.diffuse };
+            let fuzz = (2.0 / (self.shininess.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(to_color(albedo), T::from(fuzz).unwrap()))
+        } else {
+            Arc::new(Lambertian::new(to_color(&self.diffuse)))
+        }
+    }
+}
+
+// Phong exponent from which materials with a specular colour are treated as mirrors
+const MIRROR_SHININESS: f64 = 900.0;
+
+#[inline(always)]
+fn max_component(values: &[f64; 3]) -> f64 {
+    values[0].max(values[1]).max(values[2])
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// PARSING //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Splits `source` into statements, joining lines that end in a backslash and dropping comments.
+// Every statement comes with the line it starts on.
+fn statements(source: &str) -> Vec<(usize, String)> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut start = 0;
+    for (index, line) in source.lines().enumerate() {
+        if current.is_empty() {
+            start = index + 1;
+        }
+        let line = line.split('#').next().unwrap_or("");
+        match line.trim_end().strip_suffix('\\') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                let statement = current.trim().to_string();
+                if !statement.is_empty() {
+                    statements.push((start, statement));
+                }
+                current.clear();
+            }
+        }
+    }
+    if !current.trim().is_empty() {
+        statements.push((start, current.trim().to_string()));
+    }
+    statements
+}
+
+fn parse_numbers(arguments: &[&str], minimum: usize, maximum: usize) -> Result<Vec<f64>, String> {
+    if arguments.len() < minimum || arguments.len() > maximum {
+        return Err(if minimum == maximum { format!("expected {} numbers", minimum) }
+                   else { format!("expected {} to {} numbers", minimum, maximum) });
+    }
+    arguments.iter().map(|argument| {
+        argument.parse::<f64>().ok().filter(|value| value.is_finite())
+            .ok_or_else(|| format!("invalid number `{}`", argument))
+    }).collect()
+}
+
+// Converts a 1-based (or negative, relative) OBJ index into a 0-based one
+fn resolve_index(argument: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let index: i64 = argument.parse().map_err(|_| format!("invalid {} index `{}`", kind, argument))?;
+    let resolved = if index > 0 { index - 1 } else { count as i64 + index };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} index {} is out of range ({} defined)", kind, index, count));
+    }
+    Ok(resolved as usize)
+}
+
+/// Reads the materials of the MTL file at `path`.
+pub fn load_mtl(path: &Path) -> Result<Vec<MtlMaterial>, ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|error| ObjError::Io { path: path.to_path_buf(), error: error })?;
+    parse_mtl(&source).map_err(|(line, message)| ObjError::Parse { path: path.to_path_buf(), line: line, message: message })
+}
+
+fn parse_mtl(source: &str) -> Result<Vec<MtlMaterial>, (usize, String)> {
+    let mut materials: Vec<MtlMaterial> = Vec::new();
+    for (line, statement) in statements(source) {
+        let mut words = statement.split_whitespace();
+        let keyword = words.next().unwrap();
+        let arguments: Vec<&str> = words.collect();
+        if keyword == "newmtl" {
+            if arguments.is_empty() {
+                return Err((line, "`newmtl` needs a material name".to_string()));
+            }
+            materials.push(MtlMaterial::new(&arguments.join(" ")));
+            continue;
+        }
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            None => return Err((line, format!("`{}` before any `newmtl`", keyword))),
+        };
+        let number = |arguments: &[&str]| parse_numbers(arguments, 1, 1).map(|values| values[0]);
+        // A single value stands for a grey colour
+        let color = |arguments: &[&str]| parse_numbers(arguments, 1, 3).map(|values| match values.len() {
+            3 => [values[0], values[1], values[2]],
+            _ => [values[0]; 3],
+        });
+        let result = match keyword {
+            "Kd" => color(&arguments).map(|value| material.diffuse = value),
+            "Ks" => color(&arguments).map(|value| material.specular = value),
+            "Ns" => number(&arguments).map(|value| material.shininess = value),
+            "Ni" => number(&arguments).map(|value| material.index_of_refraction = value),
+            "d" => number(&arguments).map(|value| material.dissolve = value),
+            "Tr" => number(&arguments).map(|value| material.dissolve = 1.0 - value),
+            "illum" => number(&arguments).map(|value| material.illumination_model = Some(value as u32)),
+            _ => Ok(()),
+        };
+        result.map_err(|message| (line, message))?;
+    }
+    Ok(materials)
+}
+
+/// Reads the OBJ file at `path` into a single triangle mesh. Material libraries are looked up
+/// relative to the directory of the file, and faces without a `usemtl` get `default_material`.
+/// So do the faces whose material library cannot be read or lacks their material; these problems
+/// end up in the warnings of the model.
+pub fn load_obj<T: DataTypeTraits>(path: &Path, default_material: Arc<dyn Material<T> + Send + Sync>) -> Result<ObjModel<T>, ObjError> {
+    read_obj(path, default_material, false)
+}
+
+/// Like `load_obj`, but fails on material libraries that cannot be read and unknown materials.
+pub fn load_obj_strict<T: DataTypeTraits>(path: &Path, default_material: Arc<dyn Material<T> + Send + Sync>) -> Result<ObjModel<T>, ObjError> {
+    read_obj(path, default_material, true)
+}
+
+fn read_obj<T: DataTypeTraits>(path: &Path, default_material: Arc<dyn Material<T> + Send + Sync>, strict: bool) -> Result<ObjModel<T>, ObjError> {
+    let source = std::fs::read_to_string(path).map_err(|error| ObjError::Io { path: path.to_path_buf(), error: error })?;
+    let directory = path.parent().unwrap_or(Path::new("."));
+    let parse_error = |(line, message): (usize, String)| ObjError::Parse { path: path.to_path_buf(), line: line, message: message };
+
+    let mut positions: Vec<Vector3D<T>> = Vec::new();
+    let mut texture_coordinates: Vec<Vector2D<T>> = Vec::new();
+    let mut normals: Vec<Vector3D<T>> = Vec::new();
+
+    // Library materials by name, and the materials of the mesh (the first one being the default)
+    let mut library: HashMap<String, MtlMaterial> = HashMap::new();
+    let mut materials = vec![default_material];
+    let mut material_slots: HashMap<String, u32> = HashMap::new();
+    let mut current_material = 0;
+
+    // Every distinct (position, uv, normal) triple of the faces becomes one mesh vertex
+    let mut vertex_lookup: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+    let mut vertices: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+    let mut triangles: Vec<[u32; 3]> = Vec::new();
+    let mut material_indices: Vec<u32> = Vec::new();
+
+    let mut groups: Vec<ObjGroup> = Vec::new();
+    let mut warnings: Vec<ObjError> = Vec::new();
+    let mut group_name = "default".to_string();
+    let mut group_start = 0;
+
+    for (line, statement) in statements(&source) {
+        let mut words = statement.split_whitespace();
+        let keyword = words.next().unwrap();
+        let arguments: Vec<&str> = words.collect();
+        let at_line = |message: String| (line, message);
+        match keyword {
+            "v" => {
+                // The optional weight w is ignored
+                let values = parse_numbers(&arguments, 3, 4).map_err(at_line).map_err(parse_error)?;
+                positions.push(Vector3D { x: T::from(values[0]).unwrap(), y: T::from(values[1]).unwrap(), z: T::from(values[2]).unwrap() });
+            }
+            "vt" => {
+                let values = parse_numbers(&arguments, 1, 3).map_err(at_line).map_err(parse_error)?;
+                let v = if values.len() > 1 { values[1] } else { 0.0 };
+                texture_coordinates.push(Vector2D { x: T::from(values[0]).unwrap(), y: T::from(v).unwrap() });
+            }
+            "vn" => {
+                let values = parse_numbers(&arguments, 3, 3).map_err(at_line).map_err(parse_error)?;
+                normals.push(Vector3D { x: T::from(values[0]).unwrap(), y: T::from(values[1]).unwrap(), z: T::from(values[2]).unwrap() });
+            }
+            "f" => {
+                if arguments.len() < 3 {
+                    return Err(parse_error((line, "a face needs at least 3 vertices".to_string())));
+                }
+                let mut face = Vec::with_capacity(arguments.len());
+                for argument in arguments.iter() {
+                    let mut parts = argument.split('/');
+                    let position = resolve_index(parts.next().unwrap(), positions.len(), "vertex").map_err(at_line).map_err(parse_error)?;
+                    let uv = match parts.next() {
+                        Some(part) if !part.is_empty() => Some(resolve_index(part, texture_coordinates.len(), "texture coordinate")
+                                                                   .map_err(at_line).map_err(parse_error)?),
+                        _ => None,
+                    };
+                    let normal = match parts.next() {
+                        Some(part) if !part.is_empty() => Some(resolve_index(part, normals.len(), "normal")
+                                                                   .map_err(at_line).map_err(parse_error)?),
+                        _ => None,
+                    };
+                    if parts.next().is_some() {
+                        return Err(parse_error((line, format!("invalid face vertex `{}`", argument))));
+                    }
+                    let key = (position, uv, normal);
+                    let index = *vertex_lookup.entry(key).or_insert_with(|| {
+                        vertices.push(key);
+                        (vertices.len() - 1) as u32
+                    });
+                    face.push(index);
+                }
+                // Fan triangulation around the first vertex
+                for i in 1..face.len() - 1 {
+                    triangles.push([face[0], face[i], face[i + 1]]);
+                    material_indices.push(current_material);
+                }
+            }
+            "g" | "o" => {
+                if triangles.len() > group_start {
+                    groups.push(ObjGroup { name: group_name, triangles: group_start..triangles.len() });
+                }
+                group_name = if arguments.is_empty() { "default".to_string() } else { arguments.join(" ") };
+                group_start = triangles.len();
+            }
+            "mtllib" => {
+                if arguments.is_empty() {
+                    return Err(parse_error((line, "`mtllib` needs a file name".to_string())));
+                }
+                for file in arguments.iter() {
+                    match load_mtl(&directory.join(file)) {
+                        Ok(library_materials) => {
+                            for material in library_materials {
+                                library.insert(material.name.clone(), material);
+                            }
+                        }
+                        Err(error) if strict => return Err(error),
+                        Err(error) => warnings.push(error),
+                    }
+                }
+            }
+            "usemtl" => {
+                let name = arguments.join(" ");
+                current_material = match material_slots.get(&name) {
+                    Some(&slot) => slot,
+                    None => {
+                        // Unknown materials are only reported once, and fall back to the default
+                        let slot = match library.get(&name) {
+                            Some(material) => {
+                                materials.push(material.to_material());
+                                (materials.len() - 1) as u32
+                            }
+                            None if strict => return Err(parse_error((line, format!("unknown material `{}`", name)))),
+                            None => {
+                                warnings.push(parse_error((line, format!("unknown material `{}`", name))));
+                                0
+                            }
+                        };
+                        material_slots.insert(name, slot);
+                        slot
+                    }
+                };
+            }
+            _ => {}
+        }
+    }
+    if triangles.len() > group_start {
+        groups.push(ObjGroup { name: group_name, triangles: group_start..triangles.len() });
+    }
+
+    // Vertices without a normal get a zero one, which the mesh treats as missing
+    let has_normals = vertices.iter().any(|vertex| vertex.2.is_some());
+    let has_uvs = vertices.iter().any(|vertex| vertex.1.is_some());
+    let mesh = MeshData {
+        positions: vertices.iter().map(|vertex| positions[vertex.0]).collect(),
+        normals: if has_normals { vertices.iter().map(|vertex| vertex.2.map_or(Vector3D::default(), |i| normals[i])).collect() } else { Vec::new() },
+        uvs: if has_uvs { vertices.iter().map(|vertex| vertex.1.map_or(Vector2D::default(), |i| texture_coordinates[i])).collect() } else { Vec::new() },
+        colors: Vec::new(),
+        triangles: triangles,
+        material_indices: material_indices,
+        materials: materials,
+    };
+    Ok(ObjModel { mesh: TriangleMesh::new(mesh), groups: groups, warnings: warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Writes `files` to a fresh directory and loads the first one
+    fn load(test: &str, files: &[(&str, &str)], strict: bool) -> Result<ObjModel<f64>, ObjError> {
+        let directory = std::env::temp_dir().join(format!("obj_lib_{}_{}", test, std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        for (name, contents) in files {
+            std::fs::write(directory.join(name), contents).unwrap();
+        }
+        let default_material: Arc<dyn Material<f64> + Send + Sync> = Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 }));
+        let path = directory.join(files[0].0);
+        let model = if strict { load_obj_strict(&path, default_material) } else { load_obj(&path, default_material) };
+        std::fs::remove_dir_all(&directory).unwrap();
+        model
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    #[test]
+    fn polygon_with_negative_indices_is_fan_triangulated() {
+        let model = load("fan", &[("pentagon.obj", "v 0 0 0\nv 1 0 0\nv 2 1 0\nv 1 2 0\nv 0 1 0\nf -5 -4 -3 -2 -1\n")], false).unwrap();
+        let data = model.mesh.data();
+        assert_eq!(data.triangles, vec![[0, 1, 2], [0, 2, 3], [0, 3, 4]]);
+        assert_eq!(data.positions[4], Vector3D { x: 0.0, y: 1.0, z: 0.0 });
+    }
+
+    #[test]
+    fn usemtl_selects_material_slots() {
+        let obj = format!("mtllib box.mtl\n{}f 1 2 3\nusemtl red\nf 1 2 3\nusemtl blue\nf 1 3 4\nusemtl red\nf 2 3 4\n", SQUARE);
+        let mtl = "newmtl blue\nKd 0 0 1\nnewmtl red\nKd 1 0 0\n";
+        let model = load("usemtl", &[("box.obj", &obj), ("box.mtl", mtl)], false).unwrap();
+        let data = model.mesh.data();
+        assert_eq!(data.material_indices, vec![0, 1, 2, 1]);
+        assert_eq!(data.materials.len(), 3);
+        assert!(model.warnings.is_empty());
+    }
+
+    #[test]
+    fn missing_materials_fall_back_to_the_default() {
+        let obj = format!("mtllib missing.mtl\n{}usemtl red\nf 1 2 3\nusemtl red\nf 1 3 4\n", SQUARE);
+        let model = load("fallback", &[("box.obj", &obj)], false).unwrap();
+        let data = model.mesh.data();
+        assert_eq!(data.material_indices, vec![0, 0]);
+        assert_eq!(data.materials.len(), 1);
+        assert!(matches!(model.warnings.as_slice(), [ObjError::Io { .. }, ObjError::Parse { line: 6, .. }]));
+    }
+
+    #[test]
+    fn missing_materials_fail_strict_loads() {
+        let obj = format!("mtllib missing.mtl\n{}usemtl red\nf 1 2 3\n", SQUARE);
+        assert!(matches!(load("strict_library", &[("box.obj", &obj)], true), Err(ObjError::Io { .. })));
+        let obj = format!("{}usemtl red\nf 1 2 3\n", SQUARE);
+        assert!(matches!(load("strict_material", &[("box.obj", &obj)], true), Err(ObjError::Parse { line: 5, .. })));
+    }
+
+    #[test]
+    fn dark_plastic_stays_diffuse() {
+        let materials = parse_mtl("newmtl plastic\nKd 0.05 0.05 0.05\nKs 0.5 0.5 0.5\nNs 96\nillum 2\n\n\
+                                   newmtl chrome\nKd 0.05 0.05 0.05\nKs 0.9 0.9 0.9\nillum 3\n\n\
+                                   newmtl polished\nKs 0.9 0.9 0.9\nNs 1000\nillum 2\n").unwrap();
+        assert!(!materials[0].is_mirror() && !materials[0].is_transparent());
+        assert!(materials[1].is_mirror());
+        assert!(materials[2].is_mirror());
+    }
+
+    #[test]
+    fn out_of_range_index_is_an_error() {
+        let obj = format!("{}f 1 2 5\n", SQUARE);
+        assert!(matches!(load("range", &[("box.obj", &obj)], false), Err(ObjError::Parse { line: 5, .. })));
+    }
+}
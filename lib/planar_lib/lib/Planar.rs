@@ -0,0 +1,573 @@
+use std::sync::Arc;
+
+use hittable_material_traits::{HitRecord, Hittable, Material};
+
+use vector_lib::{Vector2D, Vector3D};
+use vector_lib::VectorOperations;
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+use aabb_lib::{axis_value, AABB};
+
+use sampler_lib::Sampler;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// HELPERS //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Unit vectors (tangent, bitangent) spanning the plane with unit normal `normal`. The tangent is
+// taken perpendicular to the coordinate axis least aligned with the normal.
+fn plane_basis<T: DataTypeTraits>(normal: &Vector3D<T>) -> (Vector3D<T>, Vector3D<T>) {
+    let (x, y, z) = (normal.x.abs(), normal.y.abs(), normal.z.abs());
+    let axis = if x <= y && x <= z { unit_axis(0) } else if y <= z { unit_axis(1) } else { unit_axis(2) };
+    let tangent = axis.cross_product(normal).unit_vector();
+    let bitangent = normal.cross_product(&tangent);
+    (tangent, bitangent)
+}
+
+// Unit vector along `axis` (0 = x, 1 = y, 2 = z)
+fn unit_axis<T: DataTypeTraits>(axis: usize) -> Vector3D<T> {
+    let component = |index: usize| if index == axis { T::one() } else { T::zero() };
+    Vector3D { x: component(0), y: component(1), z: component(2) }
+}
+
+// Distance along `ray` to the plane through `point` with normal `normal`, if the ray is not
+// parallel to the plane and the distance lies in [t_min, t_max]
+#[inline(always)]
+fn plane_intersection<T: DataTypeTraits>(ray: &Ray3D<T>, point: &Vector3D<T>, normal: &Vector3D<T>, t_min: T, t_max: T) -> Option<T> {
+    let denominator = normal.inner_product(&ray.direction);
+    if denominator.abs() < T::epsilon() {
+        return None;
+    }
+    let t = normal.inner_product(&(point - ray.origin)) / denominator;
+    if t < t_min || t > t_max {
+        return None;
+    }
+    Some(t)
+}
+
+// Hit on a flat surface with outwards normal `normal`, along which u and v increase in the
+// directions `uv_directions`
+struct PlanarHit<T: DataTypeTraits>
+{
+    t: T,
+    point: Vector3D<T>,
+    normal: Vector3D<T>,
+    uv: Vector2D<T>,
+    uv_directions: (Vector3D<T>, Vector3D<T>),
+}
+
+// Records a hit on a flat surface
+#[inline(always)]
+fn set_planar_hit_record<T: DataTypeTraits>(hit_record: &mut HitRecord<T>, ray: &Ray3D<T>, hit: PlanarHit<T>,
+                                            material: &Arc<dyn Material<T> + Send + Sync>) {
+    (*hit_record).set_t(hit.t);
+    (*hit_record).set_point(hit.point);
+    (*hit_record).set_face_normal(ray, &hit.normal);
+    (*hit_record).set_uv(hit.uv);
+    (*hit_record).set_tangents(&hit.uv_directions.0, &hit.uv_directions.1);
+    (*hit_record).set_material(material.clone());
+}
+
+// Density over solid angle, as seen from `origin`, of sampling `direction` by picking a uniformly
+// distributed point on the flat `shape` of area `area`
+fn planar_pdf<T: DataTypeTraits>(shape: &dyn Hittable<T>, area: T, origin: &Vector3D<T>, direction: &Vector3D<T>, time: T) -> T {
+    let mut hit_record = HitRecord::default();
+    let ray = Ray3D { origin: *origin, direction: *direction, time: time };
+    if !shape.hit(&ray, T::from(0.0001).unwrap(), T::infinity(), &mut hit_record) {
+        return T::zero();
+    }
+    let t = hit_record.get_t();
+    let distance_squared = t * t * direction.inner_product(direction);
+    let cosine = direction.inner_product(&hit_record.get_geometric_normal()).abs() / direction.norm();
+    distance_squared / (cosine * area)
+}
+
+// Flat surfaces get boxes of zero thickness, which the slab test could miss
+#[inline(always)]
+fn thickened<T: DataTypeTraits>(bbox: AABB<T>) -> AABB<T> {
+    let extent = bbox.extent();
+    let largest_extent = extent.x.max(extent.y).max(extent.z);
+    bbox.ensure_thickness(largest_extent * T::from(1e-6).unwrap() + T::min_positive_value())
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////// PLANE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Infinite plane through `point`. UVs are the coordinates of the hit point (in scene units) along
+// two fixed directions of the plane, so textures repeat over it.
+#[derive(Clone)]
+pub struct Plane<T: DataTypeTraits>
+{
+    pub point: Vector3D<T>,
+    pub normal: Vector3D<T>, // Unit length
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    tangent: Vector3D<T>,
+    bitangent: Vector3D<T>,
+}
+
+// Implementing Plane<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Plane<T>
+{
+    pub fn new(point: Vector3D<T>, normal: Vector3D<T>, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let normal = normal.unit_vector();
+        let (tangent, bitangent) = plane_basis(&normal);
+        Plane { point: point, normal: normal, material: material, tangent: tangent, bitangent: bitangent }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Plane<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let t = match plane_intersection(ray, &self.point, &self.normal, t_min, t_max) {
+            Some(t) => t,
+            None => return false,
+        };
+        let point = ray.at(t);
+        let offset = point - self.point;
+        let uv = Vector2D { x: offset.inner_product(&self.tangent), y: offset.inner_product(&self.bitangent) };
+        set_planar_hit_record(hit_record, ray, PlanarHit { t: t, point: point, normal: self.normal, uv: uv,
+                                                       uv_directions: (self.tangent, self.bitangent) }, &self.material);
+        true
+    }
+
+    // Unbounded, so kept out of the bounding volume hierarchy
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        None
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////// DISK ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Disk of radius `radius` around `center`. UVs map the square circumscribing the disk to [0, 1]².
+#[derive(Clone)]
+pub struct Disk<T: DataTypeTraits>
+{
+    pub center: Vector3D<T>,
+    pub normal: Vector3D<T>, // Unit length
+    pub radius: T,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    tangent: Vector3D<T>,
+    bitangent: Vector3D<T>,
+}
+
+// Implementing Disk<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Disk<T>
+{
+    pub fn new(center: Vector3D<T>, normal: Vector3D<T>, radius: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let normal = normal.unit_vector();
+        let (tangent, bitangent) = plane_basis(&normal);
+        Disk { center: center, normal: normal, radius: radius.abs(), material: material, tangent: tangent, bitangent: bitangent }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Disk<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let t = match plane_intersection(ray, &self.center, &self.normal, t_min, t_max) {
+            Some(t) => t,
+            None => return false,
+        };
+        let point = ray.at(t);
+        let offset = point - self.center;
+        if offset.inner_product(&offset) > self.radius * self.radius {
+            return false;
+        }
+        let half = T::from(0.5).unwrap();
+        let scale = half / self.radius;
+        let uv = Vector2D { x: half + offset.inner_product(&self.tangent) * scale,
+                            y: half + offset.inner_product(&self.bitangent) * scale };
+        set_planar_hit_record(hit_record, ray, PlanarHit { t: t, point: point, normal: self.normal, uv: uv,
+                                                       uv_directions: (self.tangent, self.bitangent) }, &self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        // Half extent of the disk along each axis is radius * sqrt(1 - n_axis²)
+        let extent = |n: T| self.radius * (T::one() - n * n).max(T::zero()).sqrt();
+        let extent = Vector3D { x: extent(self.normal.x), y: extent(self.normal.y), z: extent(self.normal.z) };
+        Some(thickened(AABB::new(self.center - extent, self.center + extent)))
+    }
+
+    fn pdf_value(&self, origin: &Vector3D<T>, direction: &Vector3D<T>, time: T) -> T {
+        planar_pdf(self, T::PI() * self.radius * self.radius, origin, direction, time)
+    }
+
+    fn random_direction(&self, origin: &Vector3D<T>, _time: T, sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        let point = sampler.sample_disk() * self.radius;
+        self.center + self.tangent * point.x + self.bitangent * point.y - origin
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////// QUAD ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Parallelogram with corners corner, corner + edge_u, corner + edge_v and corner + edge_u + edge_v.
+// The normal is edge_u x edge_v and the UVs are the coordinates of the hit point along the edges.
+#[derive(Clone)]
+pub struct Quad<T: DataTypeTraits>
+{
+    pub corner: Vector3D<T>,
+    pub edge_u: Vector3D<T>,
+    pub edge_v: Vector3D<T>,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    normal: Vector3D<T>,
+    w: Vector3D<T>, // (edge_u x edge_v) / |edge_u x edge_v|², to find the UVs of a point
+}
+
+// Implementing Quad<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Quad<T>
+{
+    pub fn new(corner: Vector3D<T>, edge_u: Vector3D<T>, edge_v: Vector3D<T>, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let n = edge_u.cross_product(&edge_v);
+        Quad { corner: corner, edge_u: edge_u, edge_v: edge_v, material: material,
+               normal: n.unit_vector(), w: n / n.inner_product(&n) }
+    }
+}
+
+impl<T: DataTypeTraits> Quad<T> {
+    pub fn get_normal(&self) -> Vector3D<T> {
+        self.normal
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Quad<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let t = match plane_intersection(ray, &self.corner, &self.normal, t_min, t_max) {
+            Some(t) => t,
+            None => return false,
+        };
+        let point = ray.at(t);
+        let offset = point - self.corner;
+        let alpha = self.w.inner_product(&offset.cross_product(&self.edge_v));
+        let beta = self.w.inner_product(&self.edge_u.cross_product(&offset));
+        if alpha < T::zero() || alpha > T::one() || beta < T::zero() || beta > T::one() {
+            return false;
+        }
+        set_planar_hit_record(hit_record, ray, PlanarHit { t: t, point: point, normal: self.normal, uv: Vector2D { x: alpha, y: beta },
+                                                       uv_directions: (self.edge_u, self.edge_v) }, &self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let corners = [self.corner, self.corner + self.edge_u, self.corner + self.edge_v, self.corner + self.edge_u + self.edge_v];
+        Some(thickened(AABB::from_points(&corners)))
+    }
+
+    fn pdf_value(&self, origin: &Vector3D<T>, direction: &Vector3D<T>, time: T) -> T {
+        planar_pdf(self, self.edge_u.cross_product(&self.edge_v).norm(), origin, direction, time)
+    }
+
+    fn random_direction(&self, origin: &Vector3D<T>, _time: T, sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        let u = sampler.get_2d();
+        self.corner + self.edge_u * u.x + self.edge_v * u.y - origin
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////// RECT ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Coordinate plane a rectangle lies in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RectPlane {
+    XY,
+    XZ,
+    YZ,
+}
+
+impl RectPlane {
+    // Axes spanning the plane and the axis of its normal
+    fn axes(self) -> (usize, usize, usize) {
+        match self {
+            RectPlane::XY => (0, 1, 2),
+            RectPlane::XZ => (0, 2, 1),
+            RectPlane::YZ => (1, 2, 0),
+        }
+    }
+}
+
+// Axis-aligned rectangle [a0, a1] x [b0, b1] at `k` along the remaining axis, e.g. x in [a0, a1],
+// y in [b0, b1] and z = k for RectPlane::XY. The normal points along the positive remaining axis
+// and the UVs map the rectangle to [0, 1]².
+#[derive(Clone)]
+pub struct Rect<T: DataTypeTraits>
+{
+    pub plane: RectPlane,
+    pub a0: T,
+    pub a1: T,
+    pub b0: T,
+    pub b1: T,
+    pub k: T,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+}
+
+// Implementing Rect<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Rect<T>
+{
+    pub fn new(plane: RectPlane, a0: T, a1: T, b0: T, b1: T, k: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Rect { plane: plane, a0: a0.min(a1), a1: a0.max(a1), b0: b0.min(b1), b1: b0.max(b1), k: k, material: material }
+    }
+}
+
+// Shorthands for the three coordinate planes
+impl<T: DataTypeTraits> Rect<T>
+{
+    pub fn xy(x0: T, x1: T, y0: T, y1: T, z: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Self::new(RectPlane::XY, x0, x1, y0, y1, z, material)
+    }
+
+    pub fn xz(x0: T, x1: T, z0: T, z1: T, y: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Self::new(RectPlane::XZ, x0, x1, z0, z1, y, material)
+    }
+
+    pub fn yz(y0: T, y1: T, z0: T, z1: T, x: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Self::new(RectPlane::YZ, y0, y1, z0, z1, x, material)
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Rect<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let (a_axis, b_axis, k_axis) = self.plane.axes();
+        let t = (self.k - axis_value(&ray.origin, k_axis)) / axis_value(&ray.direction, k_axis);
+        // Also rejects the NaN of rays parallel to the rectangle
+        if !(t >= t_min && t <= t_max) {
+            return false;
+        }
+        let point = ray.at(t);
+        let (a, b) = (axis_value(&point, a_axis), axis_value(&point, b_axis));
+        if a < self.a0 || a > self.a1 || b < self.b0 || b > self.b1 {
+            return false;
+        }
+        let uv = Vector2D { x: (a - self.a0) / (self.a1 - self.a0), y: (b - self.b0) / (self.b1 - self.b0) };
+        set_planar_hit_record(hit_record, ray, PlanarHit { t: t, point: point, normal: unit_axis(k_axis), uv: uv,
+                                                       uv_directions: (unit_axis(a_axis), unit_axis(b_axis)) }, &self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let (a_axis, b_axis, _) = self.plane.axes();
+        let corner = |a: T, b: T| {
+            let component = |axis: usize| if axis == a_axis { a } else if axis == b_axis { b } else { self.k };
+            Vector3D { x: component(0), y: component(1), z: component(2) }
+        };
+        Some(thickened(AABB::new(corner(self.a0, self.b0), corner(self.a1, self.b1))))
+    }
+
+    fn pdf_value(&self, origin: &Vector3D<T>, direction: &Vector3D<T>, time: T) -> T {
+        planar_pdf(self, (self.a1 - self.a0) * (self.b1 - self.b0), origin, direction, time)
+    }
+
+    fn random_direction(&self, origin: &Vector3D<T>, _time: T, sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        let (a_axis, b_axis, _) = self.plane.axes();
+        let u = sampler.get_2d();
+        let (a, b) = (self.a0 + (self.a1 - self.a0) * u.x, self.b0 + (self.b1 - self.b0) * u.y);
+        let component = |axis: usize| if axis == a_axis { a } else if axis == b_axis { b } else { self.k };
+        Vector3D { x: component(0), y: component(1), z: component(2) } - origin
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// AXIS-ALIGNED BOX /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Solid box between `minimum` and `maximum`, with outward normals. The UVs of each face map it to
+// [0, 1]² along the two axes it spans.
+#[derive(Clone)]
+pub struct AxisAlignedBox<T: DataTypeTraits>
+{
+    pub minimum: Vector3D<T>,
+    pub maximum: Vector3D<T>,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+}
+
+// Implementing AxisAlignedBox<T> initialization through <T>::new()
+impl<T: DataTypeTraits> AxisAlignedBox<T>
+{
+    pub fn new(corner_a: Vector3D<T>, corner_b: Vector3D<T>, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let minimum = Vector3D { x: corner_a.x.min(corner_b.x), y: corner_a.y.min(corner_b.y), z: corner_a.z.min(corner_b.z) };
+        let maximum = Vector3D { x: corner_a.x.max(corner_b.x), y: corner_a.y.max(corner_b.y), z: corner_a.z.max(corner_b.z) };
+        AxisAlignedBox { minimum: minimum, maximum: maximum, material: material }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for AxisAlignedBox<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        // Slab test keeping track of the faces where the ray enters and leaves the box
+        let mut t_enter = T::neg_infinity();
+        let mut t_exit = T::infinity();
+        let (mut enter_axis, mut exit_axis) = (0, 0);
+        for axis in 0..3 {
+            let origin = axis_value(&ray.origin, axis);
+            let direction = axis_value(&ray.direction, axis);
+            let (minimum, maximum) = (axis_value(&self.minimum, axis), axis_value(&self.maximum, axis));
+            if direction == T::zero() {
+                if origin < minimum || origin > maximum {
+                    return false;
+                }
+                continue;
+            }
+            let mut t0 = (minimum - origin) / direction;
+            let mut t1 = (maximum - origin) / direction;
+            if direction < T::zero() {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            if t0 > t_enter {
+                t_enter = t0;
+                enter_axis = axis;
+            }
+            if t1 < t_exit {
+                t_exit = t1;
+                exit_axis = axis;
+            }
+        }
+        if t_enter > t_exit {
+            return false;
+        }
+        // The exit face is hit from the inside when the entry point is out of range
+        let (t, axis, sign) = if t_enter >= t_min && t_enter <= t_max {
+            (t_enter, enter_axis, -T::one())
+        } else if t_exit >= t_min && t_exit <= t_max {
+            (t_exit, exit_axis, T::one())
+        } else {
+            return false;
+        };
+        let direction = axis_value(&ray.direction, axis);
+        let outwards_normal = unit_axis::<T>(axis) * if direction < T::zero() { -sign } else { sign };
+
+        let point = ray.at(t);
+        let (a_axis, b_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+        let coordinate = |axis: usize| (axis_value(&point, axis) - axis_value(&self.minimum, axis))
+                                       / (axis_value(&self.maximum, axis) - axis_value(&self.minimum, axis));
+        let uv = Vector2D { x: coordinate(a_axis), y: coordinate(b_axis) };
+        set_planar_hit_record(hit_record, ray, PlanarHit { t: t, point: point, normal: outwards_normal, uv: uv,
+                                                       uv_directions: (unit_axis(a_axis), unit_axis(b_axis)) }, &self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        Some(thickened(AABB::new(self.minimum, self.maximum)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_lib::RGBColor;
+    use material_lib::Lambertian;
+
+    fn material() -> Arc<dyn Material<f64> + Send + Sync> {
+        Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 }))
+    }
+
+    fn vector(x: f64, y: f64, z: f64) -> Vector3D<f64> {
+        Vector3D { x: x, y: y, z: z }
+    }
+
+    fn hit(object: &dyn Hittable<f64>, origin: Vector3D<f64>, direction: Vector3D<f64>) -> Option<HitRecord<f64>> {
+        let ray = Ray3D { origin: origin, direction: direction, time: 0.0 };
+        let mut hit_record = HitRecord::default();
+        if object.hit(&ray, 1e-6, f64::INFINITY, &mut hit_record) { Some(hit_record) } else { None }
+    }
+
+    fn assert_close(actual: &Vector3D<f64>, expected: Vector3D<f64>) {
+        assert!((*actual - expected).norm() < 1e-9, "({}, {}, {}) != ({}, {}, {})",
+                actual.x, actual.y, actual.z, expected.x, expected.y, expected.z);
+    }
+
+    // Checks the distance, point, normal facing the ray, side and UVs of a hit
+    fn assert_hit(record: &HitRecord<f64>, t: f64, point: Vector3D<f64>, normal: Vector3D<f64>, front_face: bool, uv: (f64, f64)) {
+        assert!((record.get_t() - t).abs() < 1e-9, "t = {} != {}", record.get_t(), t);
+        assert_close(&record.get_point(), point);
+        assert_close(&record.get_normal_vector(), normal);
+        assert_eq!(record.get_front_face(), front_face);
+        let actual_uv = record.get_uv();
+        assert!((actual_uv.x - uv.0).abs() < 1e-9 && (actual_uv.y - uv.1).abs() < 1e-9, "uv = ({}, {}) != {:?}", actual_uv.x, actual_uv.y, uv);
+    }
+
+    #[test]
+    fn plane_hits() {
+        let plane = Plane::new(vector(0.0, 0.0, 0.0), vector(0.0, 3.0, 0.0), material());
+        // The basis of a y normal is (z, x)
+        let record = hit(&plane, vector(2.0, 3.0, -1.0), vector(0.0, -1.0, 0.0)).unwrap();
+        assert_hit(&record, 3.0, vector(2.0, 0.0, -1.0), vector(0.0, 1.0, 0.0), true, (-1.0, 2.0));
+        assert_close(&record.get_tangent(), vector(0.0, 0.0, 1.0));
+        let record = hit(&plane, vector(0.0, -2.0, 0.0), vector(0.0, 4.0, 0.0)).unwrap();
+        assert_hit(&record, 0.5, vector(0.0, 0.0, 0.0), vector(0.0, -1.0, 0.0), false, (0.0, 0.0));
+        assert!(hit(&plane, vector(0.0, 1.0, 0.0), vector(1.0, 0.0, 1.0)).is_none());
+        assert!(hit(&plane, vector(0.0, 1.0, 0.0), vector(0.0, 1.0, 0.0)).is_none());
+        assert!(plane.bounding_box().is_none());
+    }
+
+    #[test]
+    fn disk_hits() {
+        let disk = Disk::new(vector(1.0, 1.0, 1.0), vector(0.0, 0.0, 2.0), 2.0, material());
+        // The basis of a z normal is (-y, x)
+        let record = hit(&disk, vector(2.0, 1.0, 5.0), vector(0.0, 0.0, -1.0)).unwrap();
+        assert_hit(&record, 4.0, vector(2.0, 1.0, 1.0), vector(0.0, 0.0, 1.0), true, (0.5, 0.75));
+        let record = hit(&disk, vector(1.0, 0.0, -1.0), vector(0.0, 0.0, 1.0)).unwrap();
+        assert_hit(&record, 2.0, vector(1.0, 0.0, 1.0), vector(0.0, 0.0, -1.0), false, (0.75, 0.5));
+        assert!(hit(&disk, vector(3.5, 1.0, 5.0), vector(0.0, 0.0, -1.0)).is_none());
+        let bbox = disk.bounding_box().unwrap();
+        assert!(bbox.minimum.x == -1.0 && bbox.minimum.y == -1.0 && bbox.minimum.z < 1.0);
+        assert!(bbox.maximum.x == 3.0 && bbox.maximum.y == 3.0 && bbox.maximum.z > 1.0);
+    }
+
+    #[test]
+    fn quad_hits() {
+        let quad = Quad::new(vector(0.0, 0.0, 0.0), vector(2.0, 0.0, 0.0), vector(0.0, 4.0, 0.0), material());
+        let record = hit(&quad, vector(0.5, 3.0, 1.0), vector(0.0, 0.0, -1.0)).unwrap();
+        assert_hit(&record, 1.0, vector(0.5, 3.0, 0.0), vector(0.0, 0.0, 1.0), true, (0.25, 0.75));
+        assert_close(&record.get_tangent(), vector(1.0, 0.0, 0.0));
+        // Slanted ray from behind
+        let record = hit(&quad, vector(0.0, 0.0, -2.0), vector(1.0, 2.0, 2.0)).unwrap();
+        assert_hit(&record, 1.0, vector(1.0, 2.0, 0.0), vector(0.0, 0.0, -1.0), false, (0.5, 0.5));
+        assert!(hit(&quad, vector(2.5, 1.0, 1.0), vector(0.0, 0.0, -1.0)).is_none());
+        assert!(hit(&quad, vector(1.0, -0.5, 1.0), vector(0.0, 0.0, -1.0)).is_none());
+    }
+
+    #[test]
+    fn rect_hits() {
+        let rect = Rect::xz(2.0, 0.0, 0.0, 4.0, 1.0, material());
+        let record = hit(&rect, vector(1.0, 5.0, 1.0), vector(0.0, -1.0, 0.0)).unwrap();
+        assert_hit(&record, 4.0, vector(1.0, 1.0, 1.0), vector(0.0, 1.0, 0.0), true, (0.5, 0.25));
+        let record = hit(&rect, vector(1.0, -1.0, 3.0), vector(0.0, 1.0, 0.0)).unwrap();
+        assert_hit(&record, 2.0, vector(1.0, 1.0, 3.0), vector(0.0, -1.0, 0.0), false, (0.5, 0.75));
+        assert!(hit(&rect, vector(1.0, 5.0, 4.5), vector(0.0, -1.0, 0.0)).is_none());
+        assert!(hit(&rect, vector(1.0, 1.0, 1.0), vector(1.0, 0.0, 0.0)).is_none());
+        let rect = Rect::yz(0.0, 1.0, 0.0, 1.0, -2.0, material());
+        let record = hit(&rect, vector(0.0, 0.25, 0.5), vector(-1.0, 0.0, 0.0)).unwrap();
+        assert_hit(&record, 2.0, vector(-2.0, 0.25, 0.5), vector(1.0, 0.0, 0.0), true, (0.25, 0.5));
+    }
+
+    #[test]
+    fn box_hits_from_outside_and_inside() {
+        let aabox = AxisAlignedBox::new(vector(2.0, 0.0, 8.0), vector(0.0, 4.0, 0.0), material());
+        assert_close(&aabox.minimum, vector(0.0, 0.0, 0.0));
+        assert_close(&aabox.maximum, vector(2.0, 4.0, 8.0));
+        // Entering through x = 0, whose UVs run along y and z
+        let record = hit(&aabox, vector(-1.0, 1.0, 2.0), vector(1.0, 0.0, 0.0)).unwrap();
+        assert_hit(&record, 1.0, vector(0.0, 1.0, 2.0), vector(-1.0, 0.0, 0.0), true, (0.25, 0.25));
+        let record = hit(&aabox, vector(1.0, 6.0, 6.0), vector(0.0, -1.0, 0.0)).unwrap();
+        assert_hit(&record, 2.0, vector(1.0, 4.0, 6.0), vector(0.0, 1.0, 0.0), true, (0.75, 0.5));
+        // From inside, the exit face is hit on its back
+        let record = hit(&aabox, vector(1.0, 1.0, 2.0), vector(1.0, 0.0, 0.0)).unwrap();
+        assert_hit(&record, 1.0, vector(2.0, 1.0, 2.0), vector(-1.0, 0.0, 0.0), false, (0.25, 0.25));
+        let record = hit(&aabox, vector(1.0, 1.0, 2.0), vector(0.0, 0.0, -2.0)).unwrap();
+        assert_hit(&record, 1.0, vector(1.0, 1.0, 0.0), vector(0.0, 0.0, 1.0), false, (0.5, 0.25));
+        // Missing beside the box, and pointing away from it
+        assert!(hit(&aabox, vector(-1.0, 5.0, 2.0), vector(1.0, 0.0, 0.0)).is_none());
+        assert!(hit(&aabox, vector(-1.0, 1.0, 2.0), vector(-1.0, 0.0, 0.0)).is_none());
+        let bbox = aabox.bounding_box().unwrap();
+        assert_close(&bbox.minimum, vector(0.0, 0.0, 0.0));
+        assert_close(&bbox.maximum, vector(2.0, 4.0, 8.0));
+    }
+}
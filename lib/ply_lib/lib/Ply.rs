@@ -0,0 +1,467 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use vector_lib::{Vector2D, Vector3D};
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+use mesh_lib::{MeshData, TriangleMesh};
+
+use hittable_material_traits::Material;
+
+/*
+    Reads the `vertex` and `face` elements of PLY files in any of the three encodings (ascii,
+    binary_little_endian and binary_big_endian). Recognised vertex properties are
+      - x, y, z (required),
+      - nx, ny, nz (normals),
+      - u, v / s, t / texture_u, texture_v / texture_s, texture_t (texture coordinates),
+      - red, green, blue / diffuse_red, diffuse_green, diffuse_blue (vertex colors, integer
+        types are scaled to [0, 1]).
+    Faces are read from the `vertex_indices` (or `vertex_index`) list and fan triangulated; faces
+    with fewer than 3 vertices are skipped. Every other element and property is skipped.
+ */
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// ERROR TYPE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(std::io::Error),
+    // Invalid header, `line` counting from 1
+    Header { line: usize, message: String },
+    // Invalid or missing data for entry `index` of element `element`
+    Data { element: String, index: usize, message: String },
+}
+
+impl std::fmt::Display for PlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PlyError::Io(e) => write!(f, "{}", e),
+            PlyError::Header { line, message } => write!(f, "header line {}: {}", line, message),
+            PlyError::Data { element, index, message } => write!(f, "{} {}: {}", element, index, message),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlyError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PlyError {
+    fn from(e: std::io::Error) -> Self {
+        PlyError::Io(e)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////// HEADER ////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(ScalarType::Int8),
+            "uchar" | "uint8" => Some(ScalarType::UInt8),
+            "short" | "int16" => Some(ScalarType::Int16),
+            "ushort" | "uint16" => Some(ScalarType::UInt16),
+            "int" | "int32" => Some(ScalarType::Int32),
+            "uint" | "uint32" => Some(ScalarType::UInt32),
+            "float" | "float32" => Some(ScalarType::Float32),
+            "double" | "float64" => Some(ScalarType::Float64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    // Largest value of integer types, used to scale colors to [0, 1]
+    fn integer_range(self) -> Option<f64> {
+        match self {
+            ScalarType::Int8 => Some(i8::MAX as f64),
+            ScalarType::UInt8 => Some(u8::MAX as f64),
+            ScalarType::Int16 => Some(i16::MAX as f64),
+            ScalarType::UInt16 => Some(u16::MAX as f64),
+            ScalarType::Int32 => Some(i32::MAX as f64),
+            ScalarType::UInt32 => Some(u32::MAX as f64),
+            ScalarType::Float32 | ScalarType::Float64 => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PropertyType {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+#[derive(Clone, Debug)]
+struct Property {
+    name: String,
+    kind: PropertyType,
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    line: usize,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    // Offset of the first byte after `end_header`
+    body_start: usize,
+}
+
+fn parse_header(bytes: &[u8]) -> Result<Header, PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut position = 0;
+    let mut line_number = 0;
+    loop {
+        let end = match bytes[position..].iter().position(|&byte| byte == b'\n') {
+            Some(length) => position + length,
+            None => return Err(PlyError::Header { line: line_number + 1, message: "missing `end_header`".to_string() }),
+        };
+        line_number += 1;
+        let error = |message: String| PlyError::Header { line: line_number, message: message };
+        let line = std::str::from_utf8(&bytes[position..end]).map_err(|_| error("header is not valid text".to_string()))?;
+        position = end + 1;
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        if line_number == 1 {
+            if words != ["ply"] {
+                return Err(error("not a PLY file (missing `ply` magic number)".to_string()));
+            }
+            continue;
+        }
+        match words.first().copied() {
+            None | Some("comment") | Some("obj_info") => {}
+            Some("format") => {
+                if words.len() != 3 {
+                    return Err(error("expected `format <encoding> <version>`".to_string()));
+                }
+                format = Some(match words[1] {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    other => return Err(error(format!("unknown format `{}`", other))),
+                });
+                if words[2] != "1.0" {
+                    return Err(error(format!("unsupported version `{}`", words[2])));
+                }
+            }
+            Some("element") => {
+                if words.len() != 3 {
+                    return Err(error("expected `element <name> <count>`".to_string()));
+                }
+                let count = words[2].parse().map_err(|_| error(format!("invalid element count `{}`", words[2])))?;
+                elements.push(Element { name: words[1].to_string(), line: line_number, count: count, properties: Vec::new() });
+            }
+            Some("property") => {
+                let element = elements.last_mut().ok_or_else(|| error("`property` before any `element`".to_string()))?;
+                let scalar = |name: &str| ScalarType::from_name(name).ok_or_else(|| error(format!("unknown type `{}`", name)));
+                let (kind, name) = match words.as_slice() {
+                    ["property", "list", count, item, name] => {
+                        let count = scalar(count)?;
+                        if count.integer_range().is_none() {
+                            return Err(error("list counts must have an integer type".to_string()));
+                        }
+                        (PropertyType::List { count: count, item: scalar(item)? }, name)
+                    }
+                    ["property", kind, name] => (PropertyType::Scalar(scalar(kind)?), name),
+                    _ => return Err(error("expected `property <type> <name>` or `property list <type> <type> <name>`".to_string())),
+                };
+                element.properties.push(Property { name: name.to_string(), kind: kind });
+            }
+            Some("end_header") => break,
+            Some(other) => return Err(error(format!("unknown keyword `{}`", other))),
+        }
+    }
+    let format = format.ok_or_else(|| PlyError::Header { line: line_number, message: "missing `format` line".to_string() })?;
+    Ok(Header { format: format, elements: elements, body_start: position })
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// BODY /////////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Reads the values of the body one at a time, whatever the encoding
+struct BodyReader<'a>
+{
+    format: Format,
+    bytes: &'a [u8],
+    position: usize,
+    tokens: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> BodyReader<'a> {
+    fn new(format: Format, bytes: &'a [u8]) -> Result<Self, String> {
+        let text = match format {
+            Format::Ascii => std::str::from_utf8(bytes).map_err(|_| "ASCII body is not valid text".to_string())?,
+            _ => "",
+        };
+        Ok(BodyReader { format: format, bytes: bytes, position: 0, tokens: text.split_ascii_whitespace() })
+    }
+
+    fn read(&mut self, kind: ScalarType) -> Result<f64, String> {
+        if self.format == Format::Ascii {
+            let token = self.tokens.next().ok_or_else(|| "unexpected end of file".to_string())?;
+            return token.parse().map_err(|_| format!("invalid number `{}`", token));
+        }
+        let size = kind.size();
+        let bytes = self.bytes.get(self.position..self.position + size).ok_or_else(|| "unexpected end of file".to_string())?;
+        self.position += size;
+        let mut buffer = [0u8; 8];
+        buffer[..size].copy_from_slice(bytes);
+        if self.format == Format::BinaryBigEndian {
+            buffer[..size].reverse();
+        }
+        // The buffer now holds the value in little endian order
+        let value = match kind {
+            ScalarType::Int8 => buffer[0] as i8 as f64,
+            ScalarType::UInt8 => buffer[0] as f64,
+            ScalarType::Int16 => i16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            ScalarType::UInt16 => u16::from_le_bytes([buffer[0], buffer[1]]) as f64,
+            ScalarType::Int32 => i32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+            ScalarType::UInt32 => u32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+            ScalarType::Float32 => f32::from_le_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as f64,
+            ScalarType::Float64 => f64::from_le_bytes(buffer),
+        };
+        Ok(value)
+    }
+
+    // Number of items of a list property
+    fn read_count(&mut self, kind: ScalarType) -> Result<usize, String> {
+        let count = self.read(kind)?;
+        if count < 0.0 || count.fract() != 0.0 {
+            return Err(format!("invalid list length {}", count));
+        }
+        Ok(count as usize)
+    }
+
+    fn skip(&mut self, kind: PropertyType) -> Result<(), String> {
+        match kind {
+            PropertyType::Scalar(kind) => {
+                self.read(kind)?;
+            }
+            PropertyType::List { count, item } => {
+                for _ in 0..self.read_count(count)? {
+                    self.read(item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Index of the first property with one of the given names
+fn find_property(element: &Element, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| element.properties.iter().position(|property| property.name == *name))
+}
+
+/// Builds the triangles of the PLY file in `bytes`, all of them using `material`.
+pub fn parse_ply<T: DataTypeTraits>(bytes: &[u8], material: Arc<dyn Material<T> + Send + Sync>) -> Result<MeshData<T>, PlyError> {
+    let header = parse_header(bytes)?;
+    let vertex_element = header.elements.iter().find(|element| element.name == "vertex")
+        .ok_or_else(|| PlyError::Header { line: 1, message: "no `vertex` element".to_string() })?;
+    if !header.elements.iter().any(|element| element.name == "face") {
+        return Err(PlyError::Header { line: 1, message: "no `face` element".to_string() });
+    }
+    let vertex_count = vertex_element.count;
+
+    let mut reader = BodyReader::new(header.format, &bytes[header.body_start..])
+        .map_err(|message| PlyError::Data { element: vertex_element.name.clone(), index: 0, message: message })?;
+    // Every vertex takes at least one byte, which bounds the allocation for corrupt headers
+    let mut mesh = MeshData::new(Vec::with_capacity(vertex_count.min(bytes.len())), Vec::new(), material);
+
+    for element in header.elements.iter() {
+        let data_error = |index: usize| move |message: String| PlyError::Data { element: element.name.clone(), index: index, message: message };
+        match element.name.as_str() {
+            "vertex" => {
+                let position = [find_property(element, &["x"]), find_property(element, &["y"]), find_property(element, &["z"])];
+                let normal = [find_property(element, &["nx"]), find_property(element, &["ny"]), find_property(element, &["nz"])];
+                let uv = [find_property(element, &["u", "s", "texture_u", "texture_s"]),
+                          find_property(element, &["v", "t", "texture_v", "texture_t"])];
+                let color = [find_property(element, &["red", "diffuse_red"]),
+                             find_property(element, &["green", "diffuse_green"]),
+                             find_property(element, &["blue", "diffuse_blue"])];
+                if position.iter().any(|index| index.is_none()) {
+                    return Err(PlyError::Header { line: element.line, message: "`vertex` element needs x, y and z properties".to_string() });
+                }
+                let has_normals = normal.iter().all(|index| index.is_some());
+                let has_uvs = uv.iter().all(|index| index.is_some());
+                let has_colors = color.iter().all(|index| index.is_some());
+                // Colors stored as integers are scaled to [0, 1]
+                let color_scale: Vec<f64> = element.properties.iter().map(|property| match property.kind {
+                    PropertyType::Scalar(kind) => kind.integer_range().map_or(1.0, |range| 1.0 / range),
+                    PropertyType::List { .. } => 1.0,
+                }).collect();
+
+                let mut values = vec![0.0; element.properties.len()];
+                for index in 0..element.count {
+                    for (slot, property) in element.properties.iter().enumerate() {
+                        match property.kind {
+                            PropertyType::Scalar(kind) => values[slot] = reader.read(kind).map_err(data_error(index))?,
+                            PropertyType::List { .. } => reader.skip(property.kind).map_err(data_error(index))?,
+                        }
+                    }
+                    let value = |slot: Option<usize>| T::from(values[slot.unwrap()]).unwrap();
+                    mesh.positions.push(Vector3D { x: value(position[0]), y: value(position[1]), z: value(position[2]) });
+                    if has_normals {
+                        mesh.normals.push(Vector3D { x: value(normal[0]), y: value(normal[1]), z: value(normal[2]) });
+                    }
+                    if has_uvs {
+                        mesh.uvs.push(Vector2D { x: value(uv[0]), y: value(uv[1]) });
+                    }
+                    if has_colors {
+                        let channel = |slot: Option<usize>| T::from(values[slot.unwrap()] * color_scale[slot.unwrap()]).unwrap();
+                        mesh.colors.push(RGBColor { R: channel(color[0]), G: channel(color[1]), B: channel(color[2]) });
+                    }
+                }
+            }
+            "face" => {
+                let indices = find_property(element, &["vertex_indices", "vertex_index"])
+                    .filter(|&slot| matches!(element.properties[slot].kind, PropertyType::List { .. }))
+                    .ok_or_else(|| PlyError::Header { line: element.line, message: "`face` element needs a `vertex_indices` list".to_string() })?;
+                let mut face: Vec<u32> = Vec::new();
+                for index in 0..element.count {
+                    for (slot, property) in element.properties.iter().enumerate() {
+                        match property.kind {
+                            PropertyType::List { count, item } if slot == indices => {
+                                face.clear();
+                                for _ in 0..reader.read_count(count).map_err(data_error(index))? {
+                                    let vertex = reader.read(item).map_err(data_error(index))?;
+                                    if vertex < 0.0 || vertex.fract() != 0.0 || vertex >= vertex_count as f64 {
+                                        return Err(data_error(index)(format!("vertex index {} is out of range ({} vertices)", vertex, vertex_count)));
+                                    }
+                                    face.push(vertex as u32);
+                                }
+                            }
+                            _ => reader.skip(property.kind).map_err(data_error(index))?,
+                        }
+                    }
+                    // Points and lines have no surface
+                    for i in 1..face.len().saturating_sub(1) {
+                        mesh.triangles.push([face[0], face[i], face[i + 1]]);
+                    }
+                }
+            }
+            _ => {
+                for index in 0..element.count {
+                    for property in element.properties.iter() {
+                        reader.skip(property.kind).map_err(data_error(index))?;
+                    }
+                }
+            }
+        }
+    }
+    mesh.material_indices = vec![0; mesh.triangles.len()];
+    Ok(mesh)
+}
+
+/// Reads the PLY file at `path` into a triangle mesh, all of it using `material`. Vertex colors
+/// are available to materials through `texture_lib::VertexColor`.
+pub fn load_ply<T: DataTypeTraits>(path: &Path, material: Arc<dyn Material<T> + Send + Sync>) -> Result<TriangleMesh<T>, PlyError> {
+    let bytes = std::fs::read(path)?;
+    Ok(TriangleMesh::new(parse_ply(&bytes, material)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use material_lib::Lambertian;
+
+    fn parse(bytes: &[u8]) -> Result<MeshData<f64>, PlyError> {
+        let grey = RGBColor { R: 0.5, G: 0.5, B: 0.5 };
+        parse_ply(bytes, Arc::new(Lambertian::new(grey)))
+    }
+
+    const HEADER: &str = "ply\nformat ascii 1.0\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+                          element face 1\nproperty list uchar int vertex_indices\nend_header\n";
+    const VERTICES: &str = "0 0 0\n1 0 0\n1 1 0\n0 1 0\n";
+
+    #[test]
+    fn ascii_quad_is_fan_triangulated() {
+        let mesh = parse(format!("{}{}4 0 1 2 3\n", HEADER, VERTICES).as_bytes()).unwrap();
+        assert_eq!(mesh.positions.len(), 4);
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+        assert_eq!(mesh.material_indices, vec![0, 0]);
+    }
+
+    #[test]
+    fn missing_end_header_is_an_error() {
+        let result = parse(b"ply\nformat ascii 1.0\nelement vertex 0\nproperty float x\n");
+        assert!(matches!(result, Err(PlyError::Header { line: 5, .. })), "{:?}", result.err());
+    }
+
+    #[test]
+    fn unknown_property_type_is_an_error() {
+        let result = parse(b"ply\nformat ascii 1.0\nelement vertex 1\nproperty quaternion x\nend_header\n");
+        match result {
+            Err(PlyError::Header { line, message }) => {
+                assert_eq!(line, 4);
+                assert!(message.contains("quaternion"), "{}", message);
+            }
+            other => panic!("expected a header error, got {:?}", other.err()),
+        }
+    }
+
+    #[test]
+    fn truncated_binary_body_is_an_error() {
+        let mut bytes = b"ply\nformat binary_little_endian 1.0\nelement vertex 2\nproperty float x\nproperty float y\n\
+                          property float z\nelement face 0\nproperty list uchar int vertex_indices\nend_header\n".to_vec();
+        for value in [0.0f32, 0.0, 0.0, 1.0] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let result = parse(&bytes);
+        assert!(matches!(&result, Err(PlyError::Data { element, index: 1, .. }) if element == "vertex"), "{:?}", result.err());
+    }
+
+    #[test]
+    fn out_of_range_face_index_is_an_error() {
+        let result = parse(format!("{}{}3 0 1 4\n", HEADER, VERTICES).as_bytes());
+        assert!(matches!(&result, Err(PlyError::Data { element, index: 0, .. }) if element == "face"), "{:?}", result.err());
+    }
+
+    #[test]
+    fn negative_face_index_is_an_error() {
+        let result = parse(format!("{}{}3 0 -1 2\n", HEADER, VERTICES).as_bytes());
+        assert!(matches!(&result, Err(PlyError::Data { element, index: 0, .. }) if element == "face"), "{:?}", result.err());
+    }
+}
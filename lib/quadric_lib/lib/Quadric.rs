@@ -0,0 +1,621 @@
+use std::sync::Arc;
+
+use hittable_material_traits::{HitRecord, Hittable, Material};
+
+use vector_lib::{OrthonormalBasis, Vector2D, Vector3D};
+use vector_lib::VectorOperations;
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+use aabb_lib::AABB;
+
+/*
+    Every shape is intersected in a local frame with its axis along z. The UVs of the curved
+    surfaces are (angle around the axis, position along it), both in [0, 1]; flat caps map the
+    square circumscribing them to [0, 1]².
+ */
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// POLYNOMIAL ROOTS /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Coefficients closer to zero than this are treated as zero by the cubic and quartic solvers,
+// which are used on problems scaled to unit size
+const ROOT_EPSILON: f64 = 1e-9;
+
+/// Real roots of a x² + b x + c = 0 in increasing order, computed without cancellation.
+pub fn solve_quadratic<T: DataTypeTraits>(a: T, b: T, c: T) -> Option<(T, T)> {
+    if a == T::zero() {
+        if b == T::zero() {
+            return None;
+        }
+        let root = -c / b;
+        return Some((root, root));
+    }
+    let discriminant = b * b - T::from(4.0).unwrap() * a * c;
+    if discriminant < T::zero() {
+        return None;
+    }
+    let q = -T::from(0.5).unwrap() * (b + discriminant.sqrt().copysign(b));
+    // q is only zero when b and c are; then both roots are zero
+    if q == T::zero() {
+        return Some((T::zero(), T::zero()));
+    }
+    let (r0, r1) = (q / a, c / q);
+    Some((r0.min(r1), r0.max(r1)))
+}
+
+/// Real roots of x³ + a x² + b x + c = 0 (Cardano, with the trigonometric form for three roots).
+pub fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // Substituting x = y - a/3 gives the depressed cubic y³ + 3p y + 2q = 0
+    let a_squared = a * a;
+    let p = (-a_squared / 3.0 + b) / 3.0;
+    let q = (2.0 / 27.0 * a * a_squared - a * b / 3.0 + c) / 2.0;
+    let p_cubed = p * p * p;
+    let discriminant = q * q + p_cubed;
+
+    let mut roots = if discriminant.abs() < ROOT_EPSILON {
+        if q.abs() < ROOT_EPSILON {
+            vec![0.0]
+        } else {
+            let u = (-q).cbrt();
+            vec![2.0 * u, -u]
+        }
+    } else if discriminant < 0.0 {
+        let phi = (-q / (-p_cubed).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        vec![t * phi.cos(), -t * (phi + std::f64::consts::FRAC_PI_3).cos(), -t * (phi - std::f64::consts::FRAC_PI_3).cos()]
+    } else {
+        let root = discriminant.sqrt();
+        vec![(root - q).cbrt() - (root + q).cbrt()]
+    };
+    roots.iter_mut().for_each(|root| *root -= a / 3.0);
+    roots
+}
+
+/// Real roots of x⁴ + a x³ + b x² + c x + d = 0 (Ferrari). The roots are polished with Newton's
+/// method on the original polynomial, which makes up for most of the cancellation in the formulas.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    // Substituting x = y - a/4 gives the depressed quartic y⁴ + p y² + q y + r = 0
+    let a_squared = a * a;
+    let p = -3.0 / 8.0 * a_squared + b;
+    let q = a_squared * a / 8.0 - a * b / 2.0 + c;
+    let r = -3.0 / 256.0 * a_squared * a_squared + a_squared * b / 16.0 - a * c / 4.0 + d;
+
+    let mut roots = Vec::with_capacity(4);
+    if r.abs() < ROOT_EPSILON {
+        // y (y³ + p y + q) = 0
+        roots.push(0.0);
+        roots.extend(solve_cubic(0.0, p, q));
+    } else {
+        // Any real root of the resolvent cubic splits the quartic into two quadratics, but only the
+        // largest is sure to keep 2 z - p from going negative through rounding
+        let z = solve_cubic(-p / 2.0, -r, r * p / 2.0 - q * q / 8.0).into_iter().fold(f64::NEG_INFINITY, f64::max);
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if u.abs() < ROOT_EPSILON { 0.0 } else if u > 0.0 { u.sqrt() } else { return roots };
+        let v = if v.abs() < ROOT_EPSILON { 0.0 } else if v > 0.0 { v.sqrt() } else { return roots };
+        let v = if q < 0.0 { -v } else { v };
+        for (linear, constant) in [(v, z - u), (-v, z + u)] {
+            if let Some((r0, r1)) = solve_quadratic(1.0, linear, constant) {
+                roots.push(r0);
+                roots.push(r1);
+            }
+        }
+    }
+    let polynomial = |x: f64| (((x + a) * x + b) * x + c) * x + d;
+    let derivative = |x: f64| ((4.0 * x + 3.0 * a) * x + 2.0 * b) * x + c;
+    for root in roots.iter_mut() {
+        *root -= a / 4.0;
+        for _ in 0..2 {
+            let slope = derivative(*root);
+            if slope != 0.0 {
+                *root -= polynomial(*root) / slope;
+            }
+        }
+    }
+    roots
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// HELPERS //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Orthonormal frame placed at `origin`, with `axis` as its z axis
+#[derive(Clone, Copy)]
+struct Frame<T: DataTypeTraits>
+{
+    origin: Vector3D<T>,
+    basis: OrthonormalBasis<T>,
+}
+
+impl<T: DataTypeTraits> Frame<T> {
+    fn new(origin: Vector3D<T>, axis: Vector3D<T>) -> Self {
+        let axis = axis.unit_vector();
+        let (x, y, z) = (axis.x.abs(), axis.y.abs(), axis.z.abs());
+        // Start from the coordinate axis least aligned with `axis`
+        let helper = if x <= y && x <= z { Vector3D { x: T::one(), y: T::zero(), z: T::zero() } }
+                     else if y <= z { Vector3D { x: T::zero(), y: T::one(), z: T::zero() } }
+                     else { Vector3D { x: T::zero(), y: T::zero(), z: T::one() } };
+        let tangent = helper.cross_product(&axis).unit_vector();
+        let bitangent = axis.cross_product(&tangent);
+        Frame { origin: origin, basis: OrthonormalBasis::new(tangent, bitangent, axis) }
+    }
+
+    #[inline(always)]
+    fn axis(&self) -> Vector3D<T> {
+        self.basis.w
+    }
+
+    fn to_local_direction(&self, direction: &Vector3D<T>) -> Vector3D<T> {
+        self.basis.to_local(direction)
+    }
+
+    fn to_local_point(&self, point: &Vector3D<T>) -> Vector3D<T> {
+        self.to_local_direction(&(point - self.origin))
+    }
+
+    fn to_world_direction(&self, direction: &Vector3D<T>) -> Vector3D<T> {
+        self.basis.to_world(direction)
+    }
+
+    // Half extents of a disk of radius `radius` perpendicular to the axis
+    fn disk_extent(&self, radius: T) -> Vector3D<T> {
+        let extent = |a: T| radius * (T::one() - a * a).max(T::zero()).sqrt();
+        let axis = self.axis();
+        Vector3D { x: extent(axis.x), y: extent(axis.y), z: extent(axis.z) }
+    }
+}
+
+// Intersection found in the local frame of a shape
+struct Candidate<T: DataTypeTraits>
+{
+    t: T,
+    normal: Vector3D<T>, // Local, outwards, not necessarily unit length
+    uv: Vector2D<T>,
+}
+
+// Keeps `candidate` if it lies in [t_min, t_max] and is closer than the current one
+#[inline(always)]
+fn consider<T: DataTypeTraits>(closest: &mut Option<Candidate<T>>, candidate: Candidate<T>, t_min: T, t_max: T) {
+    if candidate.t >= t_min && candidate.t <= t_max && closest.as_ref().is_none_or(|closest| candidate.t < closest.t) {
+        *closest = Some(candidate);
+    }
+}
+
+// Angle of local point (x, y) around the z axis, mapped to [0, 1]
+#[inline(always)]
+fn angle_coordinate<T: DataTypeTraits>(x: T, y: T) -> T {
+    (y.atan2(x) + T::PI()) / T::TAU()
+}
+
+// Records the closest candidate, if any
+#[inline(always)]
+fn record_closest<T: DataTypeTraits>(closest: Option<Candidate<T>>, frame: &Frame<T>, ray: &Ray3D<T>,
+                                     material: &Arc<dyn Material<T> + Send + Sync>, hit_record: &mut HitRecord<T>) -> bool {
+    let candidate = match closest {
+        Some(candidate) => candidate,
+        None => return false,
+    };
+    let outwards_normal = frame.to_world_direction(&candidate.normal).unit_vector();
+    let point = ray.at(candidate.t);
+    (*hit_record).set_t(candidate.t);
+    (*hit_record).set_point(point);
+    (*hit_record).set_face_normal(ray, &outwards_normal);
+    (*hit_record).set_uv(candidate.uv);
+    // u is the angle around the axis for every shape; the bitangent completes a right-handed frame
+    let local = frame.to_local_point(&point);
+    let dpdu = frame.to_world_direction(&Vector3D { x: -local.y, y: local.x, z: T::zero() });
+    (*hit_record).set_tangents(&dpdu, &outwards_normal.cross_product(&dpdu));
+    (*hit_record).set_material(material.clone());
+    true
+}
+
+// Local frustum whose radius goes linearly from `base_radius` at z = 0 to `top_radius` at
+// z = height, closed by caps if `capped`
+#[derive(Clone, Copy)]
+struct Frustum<T: DataTypeTraits>
+{
+    base_radius: T,
+    top_radius: T,
+    height: T,
+    capped: bool,
+}
+
+impl<T: DataTypeTraits> Frustum<T> {
+    // Intersects the local ray (origin, direction) with the frustum
+    fn hit(&self, origin: &Vector3D<T>, direction: &Vector3D<T>, t_min: T, t_max: T) -> Option<Candidate<T>> {
+        let Frustum { base_radius, top_radius, height, capped } = *self;
+        let mut closest = None;
+        // x² + y² = (base_radius - slope z)²
+        let slope = (base_radius - top_radius) / height;
+        let radius_at_origin = base_radius - slope * origin.z;
+        let a = direction.x * direction.x + direction.y * direction.y - slope * slope * direction.z * direction.z;
+        let b = T::from(2.0).unwrap() * (origin.x * direction.x + origin.y * direction.y + slope * direction.z * radius_at_origin);
+        let c = origin.x * origin.x + origin.y * origin.y - radius_at_origin * radius_at_origin;
+        if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+            for t in [t0, t1] {
+                let z = origin.z + t * direction.z;
+                if z < T::zero() || z > height {
+                    continue;
+                }
+                let (x, y) = (origin.x + t * direction.x, origin.y + t * direction.y);
+                let radius = base_radius - slope * z;
+                let normal = Vector3D { x: x, y: y, z: slope * radius };
+                consider(&mut closest, Candidate { t: t, normal: normal, uv: Vector2D { x: angle_coordinate(x, y), y: z / height } }, t_min, t_max);
+            }
+        }
+        if capped && direction.z != T::zero() {
+            let half = T::from(0.5).unwrap();
+            for (z, radius, normal_z) in [(T::zero(), base_radius, -T::one()), (height, top_radius, T::one())] {
+                if radius <= T::zero() {
+                    continue;
+                }
+                let t = (z - origin.z) / direction.z;
+                let (x, y) = (origin.x + t * direction.x, origin.y + t * direction.y);
+                if x * x + y * y > radius * radius {
+                    continue;
+                }
+                let uv = Vector2D { x: half + x / (T::from(2.0).unwrap() * radius), y: half + y / (T::from(2.0).unwrap() * radius) };
+                consider(&mut closest, Candidate { t: t, normal: Vector3D { x: T::zero(), y: T::zero(), z: normal_z }, uv: uv }, t_min, t_max);
+            }
+        }
+        closest
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// CYLINDER /////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Finite cylinder from `base` to `top` (which must differ), optionally closed by flat caps
+#[derive(Clone)]
+pub struct Cylinder<T: DataTypeTraits>
+{
+    pub base: Vector3D<T>,
+    pub top: Vector3D<T>,
+    pub radius: T,
+    pub capped: bool,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    frame: Frame<T>,
+    height: T,
+}
+
+// Implementing Cylinder<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Cylinder<T>
+{
+    pub fn new(base: Vector3D<T>, top: Vector3D<T>, radius: T, capped: bool, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let axis = top - base;
+        Cylinder { base: base, top: top, radius: radius.abs(), capped: capped, material: material,
+                   frame: Frame::new(base, axis), height: axis.norm() }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Cylinder<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let origin = self.frame.to_local_point(&ray.origin);
+        let direction = self.frame.to_local_direction(&ray.direction);
+        let closest = Frustum { base_radius: self.radius, top_radius: self.radius, height: self.height, capped: self.capped }
+            .hit(&origin, &direction, t_min, t_max);
+        record_closest(closest, &self.frame, ray, &self.material, hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let extent = self.frame.disk_extent(self.radius);
+        Some(AABB::from_points(&[self.base - extent, self.base + extent, self.top - extent, self.top + extent]))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////// CONE ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Finite cone from a disk of `base_radius` at `base` to one of `top_radius` at `top` (which must
+// differ): a pointed cone for a zero top radius, a frustum otherwise. Optionally closed by caps.
+#[derive(Clone)]
+pub struct Cone<T: DataTypeTraits>
+{
+    pub base: Vector3D<T>,
+    pub top: Vector3D<T>,
+    pub base_radius: T,
+    pub top_radius: T,
+    pub capped: bool,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    frame: Frame<T>,
+    height: T,
+}
+
+// Implementing Cone<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Cone<T>
+{
+    pub fn new(base: Vector3D<T>, top: Vector3D<T>, base_radius: T, top_radius: T, capped: bool,
+               material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let axis = top - base;
+        Cone { base: base, top: top, base_radius: base_radius.abs(), top_radius: top_radius.abs(), capped: capped,
+               material: material, frame: Frame::new(base, axis), height: axis.norm() }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Cone<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let origin = self.frame.to_local_point(&ray.origin);
+        let direction = self.frame.to_local_direction(&ray.direction);
+        let closest = Frustum { base_radius: self.base_radius, top_radius: self.top_radius, height: self.height, capped: self.capped }
+            .hit(&origin, &direction, t_min, t_max);
+        record_closest(closest, &self.frame, ray, &self.material, hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let (base_extent, top_extent) = (self.frame.disk_extent(self.base_radius), self.frame.disk_extent(self.top_radius));
+        Some(AABB::from_points(&[self.base - base_extent, self.base + base_extent, self.top - top_extent, self.top + top_extent]))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////////// TORUS //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Torus around `axis` through `center`: the surface at distance `minor_radius` from the circle of
+// radius `major_radius`. The v coordinate goes around the tube.
+#[derive(Clone)]
+pub struct Torus<T: DataTypeTraits>
+{
+    pub center: Vector3D<T>,
+    pub axis: Vector3D<T>,
+    pub major_radius: T,
+    pub minor_radius: T,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    frame: Frame<T>,
+}
+
+// Implementing Torus<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Torus<T>
+{
+    pub fn new(center: Vector3D<T>, axis: Vector3D<T>, major_radius: T, minor_radius: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let frame = Frame::new(center, axis);
+        Torus { center: center, axis: frame.axis(), major_radius: major_radius.abs(), minor_radius: minor_radius.abs(),
+                material: material, frame: frame }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Torus<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        // The quartic is solved in f64, in units of the outer radius and along a unit direction
+        let to_f64 = |vector: Vector3D<T>| [vector.x.to_f64().unwrap(), vector.y.to_f64().unwrap(), vector.z.to_f64().unwrap()];
+        let scale = (self.major_radius + self.minor_radius).to_f64().unwrap();
+        let (major, minor) = (self.major_radius.to_f64().unwrap() / scale, self.minor_radius.to_f64().unwrap() / scale);
+        let mut origin = to_f64(self.frame.to_local_point(&ray.origin)).map(|value| value / scale);
+        let direction = to_f64(self.frame.to_local_direction(&ray.direction));
+        let direction_length = (direction[0] * direction[0] + direction[1] * direction[1] + direction[2] * direction[2]).sqrt();
+        let direction = direction.map(|value| value / direction_length);
+        let dot = |a: &[f64; 3], b: &[f64; 3]| a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+
+        // Moving the origin up to the bounding sphere keeps the coefficients well conditioned
+        let projection = dot(&origin, &direction);
+        let discriminant = projection * projection - (dot(&origin, &origin) - 1.0);
+        if discriminant < 0.0 {
+            return false;
+        }
+        let shift = (-projection - discriminant.sqrt()).max(0.0);
+        for (component, step) in origin.iter_mut().zip(direction.iter()) {
+            *component += shift * step;
+        }
+
+        // (|p|² - R² - r²)² = 4 R² (r² - z²) along p = origin + s direction
+        let f = dot(&origin, &direction);
+        let e = dot(&origin, &origin) - major * major - minor * minor;
+        let four_major_squared = 4.0 * major * major;
+        let roots = solve_quartic(4.0 * f,
+                                  4.0 * f * f + 2.0 * e + four_major_squared * direction[2] * direction[2],
+                                  4.0 * f * e + 2.0 * four_major_squared * origin[2] * direction[2],
+                                  e * e - four_major_squared * (minor * minor - origin[2] * origin[2]));
+
+        let mut closest = None;
+        for root in roots {
+            let t = T::from((root + shift) * scale / direction_length).unwrap();
+            let point = [origin[0] + root * direction[0], origin[1] + root * direction[1], origin[2] + root * direction[2]];
+            let radial = (point[0] * point[0] + point[1] * point[1]).sqrt();
+            // Normal points away from the nearest point of the center circle
+            let towards_circle = if radial > 0.0 { major / radial } else { 0.0 };
+            let normal = [point[0] * (1.0 - towards_circle), point[1] * (1.0 - towards_circle), point[2]];
+            let tube_angle = (point[2].atan2(radial - major) + std::f64::consts::PI) / std::f64::consts::TAU;
+            let candidate = Candidate {
+                t: t,
+                normal: Vector3D { x: T::from(normal[0]).unwrap(), y: T::from(normal[1]).unwrap(), z: T::from(normal[2]).unwrap() },
+                uv: Vector2D { x: angle_coordinate(T::from(point[0]).unwrap(), T::from(point[1]).unwrap()), y: T::from(tube_angle).unwrap() },
+            };
+            consider(&mut closest, candidate, t_min, t_max);
+        }
+        record_closest(closest, &self.frame, ray, &self.material, hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let in_plane = self.frame.disk_extent(self.major_radius);
+        let extent = Vector3D { x: in_plane.x + self.minor_radius, y: in_plane.y + self.minor_radius, z: in_plane.z + self.minor_radius };
+        Some(AABB::new(self.center - extent, self.center + extent))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////// CAPSULE /////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Points within `radius` of the segment from `start` to `end`: a cylinder closed by two
+// hemispheres. The v coordinate runs along the axis from the tip of one hemisphere to the other.
+#[derive(Clone)]
+pub struct Capsule<T: DataTypeTraits>
+{
+    pub start: Vector3D<T>,
+    pub end: Vector3D<T>,
+    pub radius: T,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+    frame: Frame<T>,
+    length: T,
+}
+
+// Implementing Capsule<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Capsule<T>
+{
+    pub fn new(start: Vector3D<T>, end: Vector3D<T>, radius: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        let axis = end - start;
+        let length = axis.norm();
+        // A capsule of zero length is a sphere, any axis will do
+        let axis = if length > T::zero() { axis } else { Vector3D { x: T::zero(), y: T::zero(), z: T::one() } };
+        Capsule { start: start, end: end, radius: radius.abs(), material: material, frame: Frame::new(start, axis), length: length }
+    }
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Capsule<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let origin = self.frame.to_local_point(&ray.origin);
+        let direction = self.frame.to_local_direction(&ray.direction);
+        let radius = self.radius;
+        let total_length = self.length + radius + radius;
+        let uv = |x: T, y: T, z: T| Vector2D { x: angle_coordinate(x, y), y: ((z + radius) / total_length).max(T::zero()).min(T::one()) };
+        let mut closest = None;
+
+        // Cylindrical body, 0 < z < length
+        if self.length > T::zero() {
+            let a = direction.x * direction.x + direction.y * direction.y;
+            let b = T::from(2.0).unwrap() * (origin.x * direction.x + origin.y * direction.y);
+            let c = origin.x * origin.x + origin.y * origin.y - radius * radius;
+            if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+                for t in [t0, t1] {
+                    let point = origin + direction * t;
+                    if point.z > T::zero() && point.z < self.length {
+                        let normal = Vector3D { x: point.x, y: point.y, z: T::zero() };
+                        consider(&mut closest, Candidate { t: t, normal: normal, uv: uv(point.x, point.y, point.z) }, t_min, t_max);
+                    }
+                }
+            }
+        }
+        // Hemispheres, below the start and above the end of the segment
+        for (center_z, below) in [(T::zero(), true), (self.length, false)] {
+            let offset = Vector3D { x: origin.x, y: origin.y, z: origin.z - center_z };
+            let a = direction.inner_product(&direction);
+            let b = T::from(2.0).unwrap() * offset.inner_product(&direction);
+            let c = offset.inner_product(&offset) - radius * radius;
+            if let Some((t0, t1)) = solve_quadratic(a, b, c) {
+                for t in [t0, t1] {
+                    let point = origin + direction * t;
+                    if (below && point.z <= center_z) || (!below && point.z >= center_z) {
+                        let normal = Vector3D { x: point.x, y: point.y, z: point.z - center_z };
+                        consider(&mut closest, Candidate { t: t, normal: normal, uv: uv(point.x, point.y, point.z) }, t_min, t_max);
+                    }
+                }
+            }
+        }
+        record_closest(closest, &self.frame, ray, &self.material, hit_record)
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let extent = Vector3D { x: self.radius, y: self.radius, z: self.radius };
+        Some(AABB::from_points(&[self.start - extent, self.start + extent, self.end - extent, self.end + extent]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_lib::RGBColor;
+    use material_lib::Lambertian;
+
+    fn assert_roots(mut roots: Vec<f64>, expected: &[f64]) {
+        roots.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        roots.dedup_by(|a, b| (*a - *b).abs() < 1e-6);
+        assert_eq!(roots.len(), expected.len(), "{:?} != {:?}", roots, expected);
+        for (root, expected) in roots.iter().zip(expected.iter()) {
+            assert!((root - expected).abs() < 1e-6, "{:?} != {:?}", roots, expected);
+        }
+    }
+
+    // Coefficients of the monic polynomial with the given roots, highest power first (excluded)
+    fn monic(roots: &[f64]) -> Vec<f64> {
+        let mut coefficients = vec![1.0];
+        for root in roots {
+            let mut next = vec![0.0; coefficients.len() + 1];
+            for (power, coefficient) in coefficients.iter().enumerate() {
+                next[power] += coefficient;
+                next[power + 1] -= coefficient * root;
+            }
+            coefficients = next;
+        }
+        coefficients[1..].to_vec()
+    }
+
+    #[test]
+    fn cubic_roots() {
+        let c = monic(&[1.0, 2.0, 3.0]);
+        assert_roots(solve_cubic(c[0], c[1], c[2]), &[1.0, 2.0, 3.0]);
+        // Double root, with both signs of the depressed cubic's u
+        let c = monic(&[1.0, 1.0, -2.0]);
+        assert_roots(solve_cubic(c[0], c[1], c[2]), &[-2.0, 1.0]);
+        let c = monic(&[-1.0, -1.0, 2.0]);
+        assert_roots(solve_cubic(c[0], c[1], c[2]), &[-1.0, 2.0]);
+        // One real root: (x - 2)(x² + 1)
+        assert_roots(solve_cubic(-2.0, 1.0, -2.0), &[2.0]);
+    }
+
+    #[test]
+    fn quartic_roots() {
+        for expected in [[-3.0, -1.0, 1.0, 2.0], [-1.0, -0.5, 0.5, 1.0], [0.1, 0.2, 0.7, 0.9], [-2.0, -1.0, 1.0, 2.0], [-0.9, -0.8, 0.3, 0.4]] {
+            let c = monic(&expected);
+            assert_roots(solve_quartic(c[0], c[1], c[2], c[3]), &expected);
+        }
+        // Two real roots: (x² - 1)(x² + 1)
+        assert_roots(solve_quartic(0.0, 0.0, 0.0, -1.0), &[-1.0, 1.0]);
+        // No real roots: x⁴ + 1
+        assert_roots(solve_quartic(0.0, 0.0, 0.0, 1.0), &[]);
+    }
+
+    fn torus() -> Torus<f64> {
+        let material = Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 }));
+        Torus::new(Vector3D { x: 0.0, y: 0.0, z: 0.0 }, Vector3D { x: 0.0, y: 0.0, z: 1.0 }, 2.0, 0.5, material)
+    }
+
+    fn hit(object: &dyn Hittable<f64>, origin: [f64; 3], direction: [f64; 3]) -> Option<HitRecord<f64>> {
+        let ray = Ray3D { origin: Vector3D { x: origin[0], y: origin[1], z: origin[2] },
+                          direction: Vector3D { x: direction[0], y: direction[1], z: direction[2] },
+                          time: 0.0 };
+        let mut hit_record = HitRecord::default();
+        if object.hit(&ray, 1e-6, f64::INFINITY, &mut hit_record) { Some(hit_record) } else { None }
+    }
+
+    fn assert_close(vector: &Vector3D<f64>, expected: [f64; 3]) {
+        assert!((vector.x - expected[0]).abs() < 1e-6 && (vector.y - expected[1]).abs() < 1e-6 && (vector.z - expected[2]).abs() < 1e-6,
+                "({}, {}, {}) != {:?}", vector.x, vector.y, vector.z, expected);
+    }
+
+    #[test]
+    fn torus_hits() {
+        let torus = torus();
+        // From outside, through the tube
+        let record = hit(&torus, [-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]).unwrap();
+        assert!((record.get_t() - 2.5).abs() < 1e-6);
+        assert_close(&record.get_normal_vector(), [-1.0, 0.0, 0.0]);
+        assert!(record.get_front_face());
+        // From the middle of the hole outwards, hitting the inner side
+        let record = hit(&torus, [0.0, 0.0, 0.0], [2.0, 0.0, 0.0]).unwrap();
+        assert!((record.get_t() - 0.75).abs() < 1e-6);
+        assert_close(&record.get_point(), [1.5, 0.0, 0.0]);
+        assert_close(&record.get_normal_vector(), [-1.0, 0.0, 0.0]);
+        // From above onto the top of the tube
+        let record = hit(&torus, [0.0, 2.0, 5.0], [0.0, 0.0, -1.0]).unwrap();
+        assert!((record.get_t() - 4.5).abs() < 1e-6);
+        assert_close(&record.get_normal_vector(), [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn torus_misses_through_the_hole() {
+        let torus = torus();
+        assert!(hit(&torus, [0.0, 0.0, 5.0], [0.0, 0.0, -1.0]).is_none());
+        assert!(hit(&torus, [0.5, -0.5, 5.0], [-0.1, 0.1, -1.0]).is_none());
+        // Passing just beside the tube
+        assert!(hit(&torus, [-5.0, 0.0, 0.6], [1.0, 0.0, 0.0]).is_none());
+    }
+}
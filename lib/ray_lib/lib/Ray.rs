@@ -5,7 +5,8 @@ use vector_lib::DataTypeTraits;
 #[derive(PartialEq, Clone, Copy)]
 pub struct Ray3D<T: DataTypeTraits> {
     pub origin: Vector3D<T>,
-    pub direction: Vector3D<T>
+    pub direction: Vector3D<T>,
+    pub time: T // Instant the ray was sent at, within the shutter interval of the camera
 }
 
 impl<T: DataTypeTraits> Ray3D<T> {
@@ -0,0 +1,230 @@
+use rayon::prelude::*;
+
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+use camera_lib::Camera;
+
+use scene_lib::Scene;
+
+use film_lib::Film;
+
+use utilities_lib::Background;
+
+use sampler_lib::SamplerKind;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////// STRUCT DEFINITIONS //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Clone, Copy, Debug)]
+pub struct RenderSettings
+{
+    pub width: usize,
+    pub height: usize,
+    pub samples_per_pixel: u32,
+    pub max_depth: i32,
+    pub tile_size: usize,
+    pub threads: usize, // 0 -> use all available cores
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    pub crop: Option<Tile>, // Only render the pixels inside this window (None -> whole image)
+    pub background: Background,
+}
+
+// Rectangular block of pixels [x0, x1[ x [y0, y1[ rendered as one unit of work
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Tile
+{
+    pub x0: usize,
+    pub y0: usize,
+    pub x1: usize,
+    pub y1: usize,
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////// INITIALIZATION IMPL  /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings { width: 600,
+                         height: 337,
+                         samples_per_pixel: 100,
+                         max_depth: 50,
+                         tile_size: 32,
+                         threads: 0,
+                         seed: 0,
+                         sampler: SamplerKind::Independent,
+                         crop: None,
+                         background: Background::Sky }
+    }
+}
+
+impl Tile {
+    // Part of this tile inside `window`, or None if they do not overlap
+    pub fn intersection(&self, window: &Tile) -> Option<Tile> {
+        let tile = Tile { x0: self.x0.max(window.x0),
+                          y0: self.y0.max(window.y0),
+                          x1: self.x1.min(window.x1),
+                          y1: self.y1.min(window.y1) };
+        if tile.x0 < tile.x1 && tile.y0 < tile.y1 { Some(tile) } else { None }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////////// RENDERING /////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Splits the image into tiles of at most `tile_size` x `tile_size` pixels, in scanline order.
+pub fn make_tiles(width: usize, height: usize, tile_size: usize) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let mut tiles = Vec::new();
+    for y0 in (0..height).step_by(tile_size) {
+        for x0 in (0..width).step_by(tile_size) {
+            tiles.push(Tile { x0: x0,
+                              y0: y0,
+                              x1: (x0 + tile_size).min(width),
+                              y1: (y0 + tile_size).min(height) });
+        }
+    }
+    tiles
+}
+
+/// Renders `scene` as seen from `camera` into a new film, distributing image tiles over a pool of
+/// `settings.threads` worker threads. The random numbers of every pixel sample are derived from
+/// `settings.seed`, the pixel and the sample index only, so a fixed seed gives bit-identical images
+/// for any number of threads, tile size or crop window.
+/// With a crop window only the pixels inside it receive samples. `progress` is advanced by one for
+/// every finished tile.
+pub fn render<T: DataTypeTraits>(scene: &Scene<T>, camera: &Camera<T>, settings: &RenderSettings,
+                                 progress: &indicatif::ProgressBar) -> Film<T> {
+    let tiles: Vec<Tile> = make_tiles(settings.width, settings.height, settings.tile_size)
+        .into_iter()
+        .filter_map(|tile| match &settings.crop {
+            Some(window) => tile.intersection(window),
+            None => Some(tile),
+        })
+        .collect();
+    progress.set_length(tiles.len() as u64);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(settings.threads)
+        .build()
+        .expect("Failed to build render thread pool");
+    let rendered_tiles: Vec<Film<T>> = pool.install(|| {
+        tiles.par_iter().map(|tile| {
+            let tile_film = render_tile(scene, camera, settings, tile);
+            progress.inc(1);
+            tile_film
+        }).collect()
+    });
+    progress.finish();
+
+    let mut film = Film::new(settings.width, settings.height);
+    for (tile, tile_film) in tiles.iter().zip(rendered_tiles.iter()) {
+        film.merge(tile_film, tile.x0, tile.y0);
+    }
+    film
+}
+
+// Renders the pixels of a single tile into a film of the tile's size. The sampler restarts at every
+// pixel sample, so the result does not depend on which tile (or thread) a pixel is rendered in.
+pub fn render_tile<T: DataTypeTraits>(scene: &Scene<T>, camera: &Camera<T>, settings: &RenderSettings,
+                                      tile: &Tile) -> Film<T> {
+    let mut sampler = settings.sampler.build::<T>(settings.samples_per_pixel, settings.seed);
+    let mut film = Film::new(tile.x1 - tile.x0, tile.y1 - tile.y0);
+    let width_scale = T::from(settings.width.max(2) - 1).unwrap();
+    let height_scale = T::from(settings.height.max(2) - 1).unwrap();
+    for y in tile.y0..tile.y1 {
+        // Camera space has v = 0 at the bottom edge while the film has its first row at the top
+        let j = T::from(settings.height - 1 - y).unwrap();
+        for x in tile.x0..tile.x1 {
+            let i = T::from(x).unwrap();
+            for sample in 0..settings.samples_per_pixel {
+                sampler.start_pixel_sample(x, y, sample);
+                let jitter = sampler.get_pixel_2d();
+                let u = (i + jitter.x) / width_scale;
+                let v = (j + jitter.y) / height_scale;
+                let ray: Ray3D<T> = camera.get_ray(u, v, sampler.as_mut());
+                film.add_sample(x - tile.x0, y - tile.y0, utilities_lib::ray_color(&ray, scene, &settings.background, settings.max_depth, sampler.as_mut()));
+            }
+        }
+    }
+    film
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use vector_lib::Vector3D;
+    use color_lib::RGBColor;
+    use sphere_lib::Sphere;
+    use planar_lib::Quad;
+    use material_lib::{DiffuseLight, Lambertian};
+
+    // Grey floor lit by an emitting sphere hanging in front of an emitting quad, both out of view of
+    // the camera so that the image only shows light reaching the floor. Without `sample_lights` the
+    // lights are only found by scattering.
+    fn two_lights_scene(sample_lights: bool) -> Scene<f64> {
+        let vector = |x: f64, y: f64, z: f64| Vector3D { x: x, y: y, z: z };
+        let light = Arc::new(DiffuseLight::new(RGBColor { R: 4.0, G: 4.0, B: 4.0 }));
+        let sphere = || Sphere::new(vector(0.0, 1.3, 0.0), 0.6, light.clone());
+        let quad = || Quad::new(vector(-1.0, 2.0, -1.0), vector(2.0, 0.0, 0.0), vector(0.0, 0.0, 2.0), light.clone());
+        let mut scene = Scene::default();
+        scene.add(Box::new(Quad::new(vector(-3.0, 0.0, -3.0), vector(0.0, 0.0, 6.0), vector(6.0, 0.0, 0.0),
+                                     Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 })))));
+        scene.add(Box::new(sphere()));
+        scene.add(Box::new(quad()));
+        if sample_lights {
+            scene.add_light(Arc::new(sphere()));
+            scene.add_light(Arc::new(quad()));
+        }
+        scene
+    }
+
+    fn render_scene(scene: &Scene<f64>, settings: &RenderSettings) -> Film<f64> {
+        let camera = Camera::look_at(Vector3D { x: 0.0, y: 0.6, z: 4.0 }, Vector3D { x: 0.0, y: 0.0, z: 1.0 },
+                                     Vector3D { x: 0.0, y: 1.0, z: 0.0 }, 20.0, settings.width as f64 / settings.height as f64);
+        render(scene, &camera, settings, &indicatif::ProgressBar::hidden())
+    }
+
+    fn mean(film: &Film<f64>) -> f64 {
+        let pixels = film.resolve();
+        pixels.iter().map(|pixel| pixel.R + pixel.G + pixel.B).sum::<f64>() / (3 * pixels.len()) as f64
+    }
+
+    // Sampling the lights directly must not change the image on average, also where one light
+    // hides part of another
+    #[test]
+    fn light_sampling_matches_scattering_only() {
+        let settings = RenderSettings { width: 16, height: 16, samples_per_pixel: 256, max_depth: 4,
+                                        background: Background::Solid([0.0, 0.0, 0.0]), ..RenderSettings::default() };
+        let with_lights = mean(&render_scene(&two_lights_scene(true), &settings));
+        let scattering_only = mean(&render_scene(&two_lights_scene(false), &settings));
+        assert!((with_lights - scattering_only).abs() < 0.03 * scattering_only,
+                "light sampling gives {}, scattering only {}", with_lights, scattering_only);
+    }
+
+    // A fixed seed gives the same film whatever the number of threads or the tiling of the image
+    #[test]
+    fn fixed_seed_is_reproducible_across_threads() {
+        let scene = two_lights_scene(true);
+        for sampler in [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton,
+                        SamplerKind::Sobol, SamplerKind::CorrelatedMultiJitter] {
+            let settings = RenderSettings { width: 12, height: 10, samples_per_pixel: 16, max_depth: 4, seed: 7,
+                                            sampler: sampler, ..RenderSettings::default() };
+            let single = render_scene(&scene, &RenderSettings { threads: 1, tile_size: 32, ..settings });
+            let parallel = render_scene(&scene, &RenderSettings { threads: 4, tile_size: 3, ..settings });
+            for y in 0..settings.height {
+                for x in 0..settings.width {
+                    assert!(single.get_sum(x, y) == parallel.get_sum(x, y), "{:?}: pixel ({}, {}) differs", sampler, x, y);
+                    assert_eq!(single.get_count(x, y), parallel.get_count(x, y));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,748 @@
+use vector_lib::{OrthonormalBasis, Vector2D, Vector3D, VectorOperations};
+use vector_lib::DataTypeTraits;
+
+/*
+    A sampler hands out the random numbers in [0, 1[ of one pixel sample at a time. Every number
+    drawn after `start_pixel_sample` belongs to the next "dimension" of that sample: the first two
+    place the sample in its pixel, the following ones pick lens positions, times, bounce directions,
+    lights, ... in the order the integrator asks for them. Low-discrepancy samplers spread each
+    dimension (and each pair of dimensions drawn with `get_2d`) evenly over the samples of a pixel,
+    which converges faster than independent random numbers.
+
+    All samplers are deterministic: the numbers are a function of the seed, the pixel, the sample
+    index and the dimension only, so a pixel gets the same samples whichever thread renders it.
+    Different pixels are decorrelated by hashing the pixel into the scrambling of the sequences.
+ */
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// SAMPLER TRAIT ////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub trait Sampler<T: DataTypeTraits> {
+    // Number of samples per pixel the sampler distributes its points over
+    fn samples_per_pixel(&self) -> u32;
+
+    // Starts sample `sample_index` of pixel (x, y), going back to its first dimension
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32);
+
+    // Next dimension of the current sample
+    fn get_1d(&mut self) -> T;
+
+    // Next two dimensions of the current sample, stratified together
+    fn get_2d(&mut self) -> Vector2D<T>;
+
+    // Position of the sample inside its pixel. Taken before anything else, so it gets the best
+    // distributed dimensions.
+    fn get_pixel_2d(&mut self) -> Vector2D<T> {
+        self.get_2d()
+    }
+
+    // Uniformly distributed direction
+    fn sample_sphere(&mut self) -> Vector3D<T> {
+        uniform_sphere(&self.get_2d())
+    }
+
+    // Uniformly distributed direction with z >= 0
+    fn sample_hemisphere(&mut self) -> Vector3D<T> {
+        uniform_hemisphere(&self.get_2d())
+    }
+
+    // Direction with z >= 0 and density cos(theta) / pi
+    fn sample_cosine_hemisphere(&mut self) -> Vector3D<T> {
+        cosine_hemisphere(&self.get_2d())
+    }
+
+    // Uniformly distributed point of the unit disk
+    fn sample_disk(&mut self) -> Vector2D<T> {
+        concentric_disk(&self.get_2d())
+    }
+}
+
+// Available samplers, for choosing one in settings
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum SamplerKind {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    CorrelatedMultiJitter,
+}
+
+impl SamplerKind {
+    // New sampler of this kind for `samples_per_pixel` samples per pixel
+    pub fn build<T: DataTypeTraits>(self, samples_per_pixel: u32, seed: u64) -> Box<dyn Sampler<T>> {
+        match self {
+            SamplerKind::Independent => Box::new(IndependentSampler::new(samples_per_pixel, seed)),
+            SamplerKind::Stratified => Box::new(StratifiedSampler::new(samples_per_pixel, seed)),
+            SamplerKind::Halton => Box::new(HaltonSampler::new(samples_per_pixel, seed)),
+            SamplerKind::Sobol => Box::new(SobolSampler::new(samples_per_pixel, seed)),
+            SamplerKind::CorrelatedMultiJitter => Box::new(CorrelatedMultiJitterSampler::new(samples_per_pixel, seed)),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////// PIXEL SAMPLE ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Where a sampler is in the sample sequence: the pixel, the sample and the next dimension
+#[derive(Clone, Copy, Debug, Default)]
+struct PixelSample
+{
+    seed: u64,
+    x: u64,
+    y: u64,
+    index: u32,
+    dimension: u32,
+}
+
+impl PixelSample {
+    fn new(seed: u64) -> Self {
+        PixelSample { seed: seed, ..PixelSample::default() }
+    }
+
+    fn start(&mut self, x: usize, y: usize, index: u32) {
+        self.x = x as u64;
+        self.y = y as u64;
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    // Claims the next `count` dimensions, returning the first
+    #[inline(always)]
+    fn take_dimensions(&mut self, count: u32) -> u32 {
+        let dimension = self.dimension;
+        self.dimension += count;
+        dimension
+    }
+
+    // Hash of the pixel and `dimension`, the same for all samples of the pixel
+    #[inline(always)]
+    fn pattern_hash(&self, dimension: u32) -> u64 {
+        hash(&[self.seed, self.x, self.y, dimension as u64])
+    }
+
+    // Hash of the pixel, the sample and `dimension`
+    #[inline(always)]
+    fn sample_hash(&self, dimension: u32) -> u64 {
+        hash(&[self.seed, self.x, self.y, dimension as u64, self.index as u64])
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////// INDEPENDENT ////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Uniform random numbers without any stratification, from one generator per pixel sample seeded
+// with a hash of the seed, the pixel and the sample index
+#[derive(Clone, Debug)]
+pub struct IndependentSampler
+{
+    samples_per_pixel: u32,
+    seed: u64,
+    rng: fastrand::Rng,
+}
+
+// Implementing IndependentSampler initialization through <T>::new()
+impl IndependentSampler
+{
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        IndependentSampler { samples_per_pixel: samples_per_pixel, seed: seed, rng: fastrand::Rng::with_seed(seed) }
+    }
+}
+
+impl<T: DataTypeTraits> Sampler<T> for IndependentSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.rng = fastrand::Rng::with_seed(hash(&[self.seed, x as u64, y as u64, sample_index as u64]));
+    }
+
+    fn get_1d(&mut self) -> T {
+        to_unit(self.rng.f64())
+    }
+
+    fn get_2d(&mut self) -> Vector2D<T> {
+        Vector2D { x: to_unit(self.rng.f64()), y: to_unit(self.rng.f64()) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////// STRATIFIED /////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Jittered stratification: every dimension (and pair of dimensions) is split into one stratum per
+// sample, each sample taking a random point of its own stratum. The strata are assigned to the
+// samples by a random permutation per pixel and dimension, which keeps the dimensions independent.
+// Pairs use a grid of x_strata x y_strata cells, as square as the number of samples allows.
+#[derive(Clone, Debug)]
+pub struct StratifiedSampler
+{
+    samples_per_pixel: u32,
+    x_strata: u32,
+    y_strata: u32,
+    state: PixelSample,
+}
+
+// Implementing StratifiedSampler initialization through <T>::new()
+impl StratifiedSampler
+{
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        // Largest divisor not above the square root
+        let x_strata = (1..=samples_per_pixel).take_while(|d| d * d <= samples_per_pixel)
+                                              .filter(|d| samples_per_pixel % d == 0)
+                                              .last().unwrap_or(1);
+        StratifiedSampler { samples_per_pixel: samples_per_pixel, x_strata: x_strata,
+                            y_strata: samples_per_pixel / x_strata, state: PixelSample::new(seed) }
+    }
+}
+
+impl<T: DataTypeTraits> Sampler<T> for StratifiedSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.state.start(x, y, sample_index);
+    }
+
+    fn get_1d(&mut self) -> T {
+        let dimension = self.state.take_dimensions(1);
+        let pattern = self.state.pattern_hash(dimension) as u32;
+        let stratum = permute(self.state.index % self.samples_per_pixel, self.samples_per_pixel, pattern);
+        let jitter = random_float(self.state.index, pattern.wrapping_mul(0x967a889b));
+        to_unit((stratum as f64 + jitter) / self.samples_per_pixel as f64)
+    }
+
+    fn get_2d(&mut self) -> Vector2D<T> {
+        let dimension = self.state.take_dimensions(2);
+        let pattern = self.state.pattern_hash(dimension) as u32;
+        let stratum = permute(self.state.index % self.samples_per_pixel, self.samples_per_pixel, pattern);
+        let (column, row) = (stratum % self.x_strata, stratum / self.x_strata);
+        let jitter_x = random_float(self.state.index, pattern.wrapping_mul(0xa399d265));
+        let jitter_y = random_float(self.state.index, pattern.wrapping_mul(0x711ad6a5));
+        Vector2D { x: to_unit((column as f64 + jitter_x) / self.x_strata as f64),
+                   y: to_unit((row as f64 + jitter_y) / self.y_strata as f64) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////// HALTON //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Bases of the Halton sequence, one per dimension
+const PRIMES: [u64; 64] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97,
+    101, 103, 107, 109, 113, 127, 131, 137, 139, 149, 151, 157, 163, 167, 173, 179, 181, 191, 193,
+    197, 199, 211, 223, 227, 229, 233, 239, 241, 251, 257, 263, 269, 271, 277, 281, 283, 293, 307, 311,
+];
+
+// Halton sequence (radical inverses of the sample index in the prime bases), Owen-scrambled per
+// pixel. Dimensions beyond the table of bases get independent random numbers.
+#[derive(Clone, Debug)]
+pub struct HaltonSampler
+{
+    samples_per_pixel: u32,
+    state: PixelSample,
+}
+
+// Implementing HaltonSampler initialization through <T>::new()
+impl HaltonSampler
+{
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        HaltonSampler { samples_per_pixel: samples_per_pixel, state: PixelSample::new(seed) }
+    }
+}
+
+impl HaltonSampler
+{
+    fn sample_dimension(&self, dimension: u32) -> f64 {
+        match PRIMES.get(dimension as usize) {
+            Some(base) => owen_scrambled_radical_inverse(*base, self.state.index as u64, self.state.pattern_hash(dimension)),
+            None => unit_from_hash(self.state.sample_hash(dimension)),
+        }
+    }
+}
+
+impl<T: DataTypeTraits> Sampler<T> for HaltonSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.state.start(x, y, sample_index);
+    }
+
+    fn get_1d(&mut self) -> T {
+        let dimension = self.state.take_dimensions(1);
+        to_unit(self.sample_dimension(dimension))
+    }
+
+    fn get_2d(&mut self) -> Vector2D<T> {
+        let dimension = self.state.take_dimensions(2);
+        Vector2D { x: to_unit(self.sample_dimension(dimension)), y: to_unit(self.sample_dimension(dimension + 1)) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////////// SOBOL ///////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// The first two dimensions of the Sobol sequence, a (0, 2)-sequence, padded to any number of
+// dimensions: every draw takes them with its own Owen scrambling and its own shuffling of the
+// sample order (Burley, "Practical Hash-based Owen Scrambling", 2020). Best with a power of two
+// samples per pixel.
+#[derive(Clone, Debug)]
+pub struct SobolSampler
+{
+    samples_per_pixel: u32,
+    state: PixelSample,
+}
+
+// Implementing SobolSampler initialization through <T>::new()
+impl SobolSampler
+{
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        SobolSampler { samples_per_pixel: samples_per_pixel, state: PixelSample::new(seed) }
+    }
+}
+
+impl<T: DataTypeTraits> Sampler<T> for SobolSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.state.start(x, y, sample_index);
+    }
+
+    fn get_1d(&mut self) -> T {
+        let dimension = self.state.take_dimensions(1);
+        let pattern = self.state.pattern_hash(dimension);
+        let index = nested_uniform_scramble(self.state.index, pattern as u32);
+        to_unit(unit_from_bits(nested_uniform_scramble(sobol(index, 0), (pattern >> 32) as u32)))
+    }
+
+    fn get_2d(&mut self) -> Vector2D<T> {
+        let dimension = self.state.take_dimensions(2);
+        let pattern = self.state.pattern_hash(dimension);
+        let index = nested_uniform_scramble(self.state.index, pattern as u32);
+        let scramble_y = mix_bits(pattern) as u32;
+        Vector2D { x: to_unit(unit_from_bits(nested_uniform_scramble(sobol(index, 0), (pattern >> 32) as u32))),
+                   y: to_unit(unit_from_bits(nested_uniform_scramble(sobol(index, 1), scramble_y))) }
+    }
+}
+
+// Dimension 0 or 1 of the Sobol sequence, as the bits of a fraction
+#[inline(always)]
+fn sobol(index: u32, dimension: u32) -> u32 {
+    if dimension == 0 {
+        return index.reverse_bits();
+    }
+    // Columns of the second generator matrix: v_k = v_(k-1) ^ (v_(k-1) >> 1), from the top bit
+    let (mut result, mut column, mut index) = (0u32, 1u32 << 31, index);
+    while index != 0 {
+        if index & 1 == 1 {
+            result ^= column;
+        }
+        index >>= 1;
+        column ^= column >> 1;
+    }
+    result
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////// CORRELATED MULTI-JITTERED /////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Correlated multi-jittered sampling (Kensler, "Correlated Multi-Jittered Sampling", 2013): pairs
+// of dimensions are jittered both over an m x n grid and over each of its rows and columns, with a
+// different pattern per pixel and dimension. Works well for any number of samples per pixel, though
+// perfect squares fill the grid completely.
+#[derive(Clone, Debug)]
+pub struct CorrelatedMultiJitterSampler
+{
+    samples_per_pixel: u32,
+    columns: u32,
+    rows: u32,
+    state: PixelSample,
+}
+
+// Implementing CorrelatedMultiJitterSampler initialization through <T>::new()
+impl CorrelatedMultiJitterSampler
+{
+    pub fn new(samples_per_pixel: u32, seed: u64) -> Self {
+        let samples_per_pixel = samples_per_pixel.max(1);
+        let columns = (samples_per_pixel as f64).sqrt().ceil() as u32;
+        CorrelatedMultiJitterSampler { samples_per_pixel: samples_per_pixel, columns: columns,
+                                       rows: samples_per_pixel.div_ceil(columns), state: PixelSample::new(seed) }
+    }
+}
+
+impl<T: DataTypeTraits> Sampler<T> for CorrelatedMultiJitterSampler {
+    fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    fn start_pixel_sample(&mut self, x: usize, y: usize, sample_index: u32) {
+        self.state.start(x, y, sample_index);
+    }
+
+    fn get_1d(&mut self) -> T {
+        let dimension = self.state.take_dimensions(1);
+        let pattern = self.state.pattern_hash(dimension) as u32;
+        let stratum = permute(self.state.index % self.samples_per_pixel, self.samples_per_pixel, pattern.wrapping_mul(0x68bc21eb));
+        let jitter = random_float(stratum, pattern.wrapping_mul(0x967a889b));
+        to_unit((stratum as f64 + jitter) / self.samples_per_pixel as f64)
+    }
+
+    fn get_2d(&mut self) -> Vector2D<T> {
+        let dimension = self.state.take_dimensions(2);
+        let pattern = self.state.pattern_hash(dimension) as u32;
+        let (m, n) = (self.columns, self.rows);
+        // The grid can have more cells than there are samples: each pixel uses a random subset
+        let cells = m * n;
+        let sample = permute(self.state.index % cells, cells, pattern.wrapping_mul(0x51633e2d));
+        let shuffled_x = permute(sample % m, m, pattern.wrapping_mul(0xa511e9b3));
+        let shuffled_y = permute(sample / m, n, pattern.wrapping_mul(0x63d83595));
+        let jitter_x = random_float(sample, pattern.wrapping_mul(0xa399d265));
+        let jitter_y = random_float(sample, pattern.wrapping_mul(0x711ad6a5));
+        Vector2D { x: to_unit(((sample % m) as f64 + (shuffled_y as f64 + jitter_x) / n as f64) / m as f64),
+                   y: to_unit(((sample / m) as f64 + (shuffled_x as f64 + jitter_y) / m as f64) / n as f64) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// WARPING FUNCTIONS ////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Maps a uniform sample `u` of the unit square onto the unit sphere, uniformly
+#[inline(always)]
+pub fn uniform_sphere<T: DataTypeTraits>(u: &Vector2D<T>) -> Vector3D<T> {
+    let z = T::one() - T::from(2.0).unwrap() * u.x;
+    let radius = (T::one() - z * z).max(T::zero()).sqrt();
+    let phi = T::TAU() * u.y;
+    Vector3D { x: radius * phi.cos(), y: radius * phi.sin(), z: z }
+}
+
+#[inline(always)]
+pub fn uniform_sphere_pdf<T: DataTypeTraits>() -> T {
+    T::one() / (T::from(2.0).unwrap() * T::TAU())
+}
+
+// Maps a uniform sample `u` of the unit square onto the hemisphere z >= 0, uniformly
+#[inline(always)]
+pub fn uniform_hemisphere<T: DataTypeTraits>(u: &Vector2D<T>) -> Vector3D<T> {
+    let z = u.x;
+    let radius = (T::one() - z * z).max(T::zero()).sqrt();
+    let phi = T::TAU() * u.y;
+    Vector3D { x: radius * phi.cos(), y: radius * phi.sin(), z: z }
+}
+
+#[inline(always)]
+pub fn uniform_hemisphere_pdf<T: DataTypeTraits>() -> T {
+    T::one() / T::TAU()
+}
+
+// Maps a uniform sample `u` of the unit square onto the hemisphere z >= 0 with density
+// cos(theta) / pi, by lifting a uniform point of the disk onto it (Malley's method)
+#[inline(always)]
+pub fn cosine_hemisphere<T: DataTypeTraits>(u: &Vector2D<T>) -> Vector3D<T> {
+    let disk = concentric_disk(u);
+    let z = (T::one() - disk.x * disk.x - disk.y * disk.y).max(T::zero()).sqrt();
+    Vector3D { x: disk.x, y: disk.y, z: z }
+}
+
+#[inline(always)]
+pub fn cosine_hemisphere_pdf<T: DataTypeTraits>(cos_theta: T) -> T {
+    cos_theta.max(T::zero()) / T::PI()
+}
+
+// Maps a uniform sample `u` of the unit square onto the unit disk, uniformly. Concentric squares go
+// to concentric circles (Shirley and Chiu), so stratification of `u` carries over to the disk.
+#[inline(always)]
+pub fn concentric_disk<T: DataTypeTraits>(u: &Vector2D<T>) -> Vector2D<T> {
+    let two = T::from(2.0).unwrap();
+    let (a, b) = (two * u.x - T::one(), two * u.y - T::one());
+    if a == T::zero() && b == T::zero() {
+        return Vector2D { x: T::zero(), y: T::zero() };
+    }
+    let quarter_pi = T::FRAC_PI_4();
+    let (radius, theta) = if a.abs() > b.abs() {
+        (a, quarter_pi * (b / a))
+    } else {
+        (b, T::FRAC_PI_2() - quarter_pi * (a / b))
+    };
+    Vector2D { x: radius * theta.cos(), y: radius * theta.sin() }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////////////////////// DIRECTION DENSITIES ///////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Cosine-weighted distribution of directions around a unit normal: density cos(theta) / pi, zero
+// below the surface. Importance sampling a Lambertian BRDF with it cancels the cosine and the 1/pi
+// of the estimator, leaving the albedo as the path weight.
+#[derive(Clone, Copy, Debug)]
+pub struct CosineHemisphere<T: DataTypeTraits>
+{
+    basis: OrthonormalBasis<T>,
+}
+
+// Implementing CosineHemisphere<T> initialization through <T>::new()
+impl<T: DataTypeTraits> CosineHemisphere<T>
+{
+    pub fn new(normal: &Vector3D<T>) -> Self {
+        CosineHemisphere { basis: OrthonormalBasis::from_w(normal) }
+    }
+}
+
+impl<T: DataTypeTraits> CosineHemisphere<T>
+{
+    // Unit direction drawn with `sampler`
+    #[inline(always)]
+    pub fn sample(&self, sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        self.basis.to_world(&sampler.sample_cosine_hemisphere())
+    }
+
+    // Density, over solid angle, of `sample` returning the (not necessarily unit) `direction`
+    #[inline(always)]
+    pub fn pdf(&self, direction: &Vector3D<T>) -> T {
+        cosine_hemisphere_pdf(direction.inner_product(&self.basis.w) / direction.norm())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// HELPERS //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Converts a number in [0, 1[ to T, keeping it below one after rounding
+#[inline(always)]
+fn to_unit<T: DataTypeTraits>(value: f64) -> T {
+    T::from(value).unwrap().min(T::one() - T::epsilon())
+}
+
+// Fraction whose bits, from the top, are `bits`
+#[inline(always)]
+fn unit_from_bits(bits: u32) -> f64 {
+    bits as f64 / 4294967296.0
+}
+
+// Number in [0, 1[ from the top 53 bits of a hash
+#[inline(always)]
+fn unit_from_hash(hash: u64) -> f64 {
+    (hash >> 11) as f64 / 9007199254740992.0
+}
+
+// SplitMix64 finalizer: scrambles all bits of `value`
+#[inline(always)]
+fn mix_bits(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+// Hash of a list of values
+#[inline(always)]
+fn hash(values: &[u64]) -> u64 {
+    values.iter().fold(0x2545_F491_4F6C_DD1D, |hash, value| mix_bits(hash ^ value.wrapping_add(0x9E37_79B9_7F4A_7C15)))
+}
+
+// Element `index` of a random permutation of [0, length[ chosen by `pattern` (Kensler, 2013)
+fn permute(index: u32, length: u32, pattern: u32) -> u32 {
+    if length <= 1 {
+        return 0;
+    }
+    let mut w = length - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    let mut i = index;
+    loop {
+        i ^= pattern;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= pattern >> 16;
+        i ^= (i & w) >> 4;
+        i ^= pattern >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= pattern >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | pattern >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        // Cycle walking: values outside of [0, length[ are permuted again
+        if i < length {
+            break;
+        }
+    }
+    (i.wrapping_add(pattern)) % length
+}
+
+// Random number in [0, 1[ from `index` and `pattern` (Kensler, 2013)
+#[inline(always)]
+fn random_float(index: u32, pattern: u32) -> f64 {
+    let mut i = index;
+    i ^= pattern;
+    i ^= i >> 17;
+    i ^= i >> 10;
+    i = i.wrapping_mul(0xb36534e5);
+    i ^= i >> 12;
+    i ^= i >> 21;
+    i = i.wrapping_mul(0x93fc4795);
+    i ^= 0xdf6e307f;
+    i ^= i >> 17;
+    i = i.wrapping_mul(1 | pattern >> 18);
+    unit_from_bits(i)
+}
+
+// Owen scrambling of the bits of a fraction: every bit is flipped depending on the bits above it
+// (Burley, 2020, after Laine and Karras)
+#[inline(always)]
+fn nested_uniform_scramble(bits: u32, seed: u32) -> u32 {
+    let mut x = bits.reverse_bits();
+    x = x.wrapping_add(seed);
+    x ^= x.wrapping_mul(0x6c50b47c);
+    x ^= x.wrapping_mul(0xb82f1e52);
+    x ^= x.wrapping_mul(0xc7afe638);
+    x ^= x.wrapping_mul(0x8d22f6e6);
+    x.reverse_bits()
+}
+
+// Radical inverse of `index` in `base` with Owen scrambling: every digit is permuted by a
+// permutation depending on the digits before it
+fn owen_scrambled_radical_inverse(base: u64, index: u64, hash: u64) -> f64 {
+    let inverse_base = 1.0 / base as f64;
+    let (mut index, mut reversed_digits, mut inverse_base_power) = (index, 0u64, 1.0);
+    // Also scrambles the leading zeros, down to the precision of f64 or until the digits no longer
+    // fit in 64 bits, which the large bases reach first
+    while 1.0 - (base - 1) as f64 * inverse_base_power < 1.0 {
+        let digit = index % base;
+        index /= base;
+        let digit_hash = mix_bits(hash ^ reversed_digits);
+        let digit = permute(digit as u32, base as u32, digit_hash as u32) as u64;
+        match reversed_digits.checked_mul(base).and_then(|shifted| shifted.checked_add(digit)) {
+            Some(digits) => reversed_digits = digits,
+            None => break,
+        }
+        inverse_base_power *= inverse_base;
+    }
+    (reversed_digits as f64 * inverse_base_power).min(1.0 - f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [SamplerKind; 5] = [SamplerKind::Independent, SamplerKind::Stratified, SamplerKind::Halton,
+                                     SamplerKind::Sobol, SamplerKind::CorrelatedMultiJitter];
+
+    // Every sample of pixel (x, y), drawn as get_pixel_2d, get_1d, get_2d, get_1d
+    fn pixel_samples(sampler: &mut dyn Sampler<f64>, x: usize, y: usize) -> Vec<[f64; 6]> {
+        (0..sampler.samples_per_pixel()).map(|index| {
+            sampler.start_pixel_sample(x, y, index);
+            let (pixel, first, pair, second) = (sampler.get_pixel_2d(), sampler.get_1d(), sampler.get_2d(), sampler.get_1d());
+            [pixel.x, pixel.y, first, pair.x, pair.y, second]
+        }).collect()
+    }
+
+    // Checks that the points put exactly one point in each of the columns x rows cells of [0, 1[²
+    fn assert_one_per_stratum(points: &[(f64, f64)], columns: usize, rows: usize) {
+        let mut counts = vec![0; columns * rows];
+        for (x, y) in points {
+            counts[(y * rows as f64) as usize * columns + (x * columns as f64) as usize] += 1;
+        }
+        assert!(counts.iter().all(|count| *count == 1), "{} x {} strata filled as {:?}", columns, rows, counts);
+    }
+
+    fn pairs(samples: &[[f64; 6]], first: usize, second: usize) -> Vec<(f64, f64)> {
+        samples.iter().map(|sample| (sample[first], sample[second])).collect()
+    }
+
+    fn singles(samples: &[[f64; 6]], dimension: usize) -> Vec<(f64, f64)> {
+        samples.iter().map(|sample| (sample[dimension], 0.0)).collect()
+    }
+
+    #[test]
+    fn samples_lie_in_the_unit_interval() {
+        for kind in KINDS {
+            for samples_per_pixel in [1, 7, 16] {
+                let mut sampler = kind.build::<f32>(samples_per_pixel, 3);
+                for (x, y) in [(0, 0), (17, 4), (1023, 767)] {
+                    for index in 0..samples_per_pixel {
+                        sampler.start_pixel_sample(x, y, index);
+                        // Past the 64 Halton bases, into the hashed dimensions
+                        for _ in 0..40 {
+                            let (value, pair) = (sampler.get_1d(), sampler.get_2d());
+                            for value in [value, pair.x, pair.y] {
+                                assert!((0.0..1.0).contains(&value), "{:?} gave {}", kind, value);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn stratified_samplers_fill_every_stratum_once() {
+        for (x, y) in [(0, 0), (5, 9), (640, 480)] {
+            for kind in [SamplerKind::Stratified, SamplerKind::CorrelatedMultiJitter, SamplerKind::Sobol] {
+                let samples = pixel_samples(kind.build::<f64>(16, 11).as_mut(), x, y);
+                for (first, second) in [(0, 1), (3, 4)] {
+                    assert_one_per_stratum(&pairs(&samples, first, second), 4, 4);
+                }
+                for dimension in [2, 5] {
+                    assert_one_per_stratum(&singles(&samples, dimension), 16, 1);
+                }
+            }
+            // Multi-jittering and the (0, 2)-sequence also stratify each dimension of a pair alone
+            for kind in [SamplerKind::CorrelatedMultiJitter, SamplerKind::Sobol] {
+                let samples = pixel_samples(kind.build::<f64>(16, 11).as_mut(), x, y);
+                for dimension in [0, 1, 3, 4] {
+                    assert_one_per_stratum(&singles(&samples, dimension), 16, 1);
+                }
+            }
+            let samples = pixel_samples(SamplerKind::Sobol.build::<f64>(16, 11).as_mut(), x, y);
+            assert_one_per_stratum(&pairs(&samples, 0, 1), 2, 8);
+            assert_one_per_stratum(&pairs(&samples, 0, 1), 8, 2);
+            // The first 2^3 3^2 Halton points fill an 8 x 9 grid of the bases 2 and 3
+            let samples = pixel_samples(SamplerKind::Halton.build::<f64>(72, 11).as_mut(), x, y);
+            assert_one_per_stratum(&pairs(&samples, 0, 1), 8, 9);
+        }
+    }
+
+    #[test]
+    fn pixel_samples_are_deterministic() {
+        for kind in KINDS {
+            let draw = |sampler: &mut dyn Sampler<f64>, x: usize, y: usize, index: u32| {
+                sampler.start_pixel_sample(x, y, index);
+                (0..70).map(|_| sampler.get_1d()).collect::<Vec<f64>>()
+            };
+            let mut sampler = kind.build::<f64>(16, 7);
+            let first = draw(sampler.as_mut(), 12, 34, 5);
+            // Whatever was drawn in between, and whichever sampler draws it
+            draw(sampler.as_mut(), 3, 4, 9);
+            sampler.get_2d();
+            assert!(first == draw(sampler.as_mut(), 12, 34, 5), "{:?}", kind);
+            assert!(first == draw(kind.build::<f64>(16, 7).as_mut(), 12, 34, 5), "{:?}", kind);
+            // While other pixels, samples and seeds get other numbers
+            assert!(first != draw(sampler.as_mut(), 12, 35, 5), "{:?}", kind);
+            assert!(first != draw(sampler.as_mut(), 12, 34, 6), "{:?}", kind);
+            assert!(first != draw(kind.build::<f64>(16, 8).as_mut(), 12, 34, 5), "{:?}", kind);
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::sync::Arc;
 
 use vector_lib::DataTypeTraits;
 
@@ -5,13 +6,18 @@ use ray_lib::Ray3D;
 
 use hittable_material_traits::{HitRecord, Hittable};
 
+use aabb_lib::AABB;
+
+use bvh_lib::BVH;
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 /////////////////////////////////////// STRUCT DEFINITION //////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 
 pub struct Scene<T>
 {
-    pub objects: Vec<Box<dyn Hittable<T> + Send + Sync>>
+    pub objects: Vec<Box<dyn Hittable<T> + Send + Sync>>,
+    pub lights: Vec<Arc<dyn Hittable<T> + Send + Sync>>, // Emitting objects the integrator samples directly
 }
 
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -21,12 +27,13 @@ pub struct Scene<T>
 // Implementing Scene<T> initialization through <T>::new()
 impl<T: DataTypeTraits> core::default::Default for Scene<T> {
     fn default() -> Self {
-        Scene {objects: Vec::<Box<dyn Hittable<T> + Send + Sync>>::default() }
+        Scene {objects: Vec::<Box<dyn Hittable<T> + Send + Sync>>::default(),
+               lights: Vec::<Arc<dyn Hittable<T> + Send + Sync>>::default() }
     }
 }
 
 impl<T: DataTypeTraits> Scene<T> {
-    fn is_empty(&self) -> bool {
+    pub fn is_empty(&self) -> bool {
         self.objects.is_empty()
     }
 }
@@ -37,13 +44,30 @@ impl<T: DataTypeTraits> Scene<T> {
     }
 }
 
+impl<T: DataTypeTraits> Scene<T> {
+    // Registers an object, which must also be added to the scene, as a light to sample directly.
+    // It has to implement `pdf_value` and `random_direction`.
+    pub fn add_light(& mut self, light: Arc<dyn Hittable<T> + Send + Sync>) {
+        self.lights.push(light);
+    }
+}
+
+impl<T: DataTypeTraits> Scene<T> {
+    // Replaces the flat list of objects by a single bounding volume hierarchy holding all of them.
+    // Should be called once all objects have been added.
+    pub fn build_bvh(&mut self) {
+        let objects = std::mem::take(&mut self.objects);
+        self.objects.push(Box::new(BVH::new(objects)));
+    }
+}
+
 impl<T: DataTypeTraits> Hittable<T> for Scene<T> {
-    fn hit(&mut self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
     {
         let mut hit_record_temp = HitRecord::default();
         let mut hit_anything = false;
         let mut closest_so_far = t_max;
-        for obj in self.objects.iter_mut() {
+        for obj in self.objects.iter() {
             if obj.hit(ray, t_min, closest_so_far, & mut hit_record_temp){
                 hit_anything = true;
                 closest_so_far = hit_record_temp.get_t();
@@ -53,4 +77,12 @@ impl<T: DataTypeTraits> Hittable<T> for Scene<T> {
         hit_anything
 
     }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let mut bbox = AABB::empty();
+        for obj in self.objects.iter() {
+            bbox = bbox.surrounding_box(&obj.bounding_box()?);
+        }
+        if bbox.is_empty() { None } else { Some(bbox) }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,1321 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::de::{DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, VariantAccess, Visitor};
+use serde::{Deserialize, Deserializer};
+use toml::Spanned;
+
+use vector_lib::{Matrix4, Vector2D, Vector3D, VectorOperations};
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+use camera_lib::Camera;
+
+use sphere_lib::Sphere;
+
+use triangle_lib::Triangle;
+
+use planar_lib::{AxisAlignedBox, Disk, Plane, Quad, Rect, RectPlane};
+
+use quadric_lib::{Capsule, Cone, Cylinder, Torus};
+
+use instance_lib::Instance;
+
+use obj_lib::{load_obj, ObjError};
+
+use ply_lib::load_ply;
+
+use gltf_lib::{load_gltf, GltfError};
+
+use texture_lib::{Checker, ImageTexture, NoisePattern, NoiseTexture, SolidColor, Texture, VertexColor, WrapMode};
+
+use scene_lib::Scene;
+
+use material_lib::{BumpMapped, Dielectric, DiffuseLight, Lambertian, Metal, NormalMapped};
+
+use renderer_lib::RenderSettings;
+
+use utilities_lib::Background;
+
+use sampler_lib::SamplerKind;
+
+use hittable_material_traits::{Hittable, Material};
+
+/*
+    Scene files are TOML documents of the form
+
+        background = [0.0, 0.0, 0.0]   # optional, seen by rays leaving the scene, default a sky
+
+        [camera]
+        look_from = [-2.0, 2.0, 1.0]
+        look_at = [0.0, 0.0, -1.0]
+        view_up = [0.0, 1.0, 0.0]      # optional, default [0, 1, 0]
+        vertical_fov = 20.0            # optional, degrees, default 90
+        aperture = 0.1                 # optional, default 0 (pinhole)
+        focus_distance = 3.4           # optional, default |look_from - look_at|
+        shutter_open = 0.0             # optional, default 0
+        shutter_close = 1.0            # optional, default 0 (no motion blur)
+
+        [render]                       # optional, every key has a default
+        width = 600
+        height = 337
+        samples_per_pixel = 300
+        max_depth = 50
+        sampler = "sobol"              # "independent" (default), "stratified", "halton", "sobol"
+                                       # or "cmj" (correlated multi-jitter)
+
+        [materials.glass]              # one table per named material
+        type = "dielectric"
+        index_of_refraction = 1.5
+
+        [[objects]]                    # one entry per object
+        type = "sphere"
+        center = [-1.0, 0.0, -1.0]
+        radius = 0.5
+        material = "glass"
+
+    Material types: lambertian (albedo), metal (albedo, fuzz), dielectric (index_of_refraction) and
+    diffuse_light (emit, optional two_sided = true). An albedo or emit is either a color or the
+    name of a texture, defined in a table of its own:
+
+        [textures.floor]
+        type = "checker"
+        scale = 0.5                    # side of the cubes of the (3D) checkerboard
+        even = [0.2, 0.3, 0.1]         # color or texture name
+        odd = "marble"
+
+    Texture types: checker (scale, even, odd), image (path of a PNG, JPEG or Radiance HDR file,
+    relative to the scene file, optional wrap = "repeat", "clamp" or "mirror" and linear = false,
+    true for images holding data such as normal maps) and noise (optional pattern = "smooth",
+    "turbulence" or "marble", scale = 1, color = [1, 1, 1] and seed = 0). Image textures are mapped
+    through the surface coordinates (u, v) of the objects.
+
+    Lambertian, metal and dielectric materials take optional surface detail, bending their shading
+    normal without changing the geometry:
+
+        normal_map = { texture = "bricks_normal", strength = 1.0 }   # tangent space, OpenGL style
+        bump_map = { texture = "bricks_height", scale = 0.01 }       # height = scale * texture
+
+    Objects with a diffuse_light material are lights; with a black background they are the only
+    ones. Spheres, disks, rects and quads without a transform are also sampled directly by the
+    renderer, which gives far less noise than hitting them by chance.
+
+    Object types: sphere (center, radius), triangle (vertices, optional normals and uvs), mesh
+    (path of a Wavefront OBJ, PLY or glTF file, relative to the scene file). The `material` of a
+    mesh is optional; it is used for the faces that do not select an MTL material and defaults to a
+    grey diffuse material showing the vertex colors of the mesh, if any. It also stands in for MTL
+    materials that cannot be found, which is reported as a warning. glTF files bring their
+    own materials and every mesh of their default scene is added.
+
+    Planar object types: plane (point, normal), disk (center, normal, radius), rect (plane = "xy",
+    "xz" or "yz", min and max corners in that plane, offset along the remaining axis), quad
+    (corner, edge_u, edge_v) and box (min, max).
+
+    Quadric object types: cylinder (base, top, radius, optional caps = true), cone (base, top,
+    base_radius, optional top_radius = 0 and caps = true), torus (center, axis, major_radius,
+    minor_radius) and capsule (start, end, radius).
+
+    Any object can be placed through an optional transform, e.g.
+
+        transform = { scale = [2.0, 2.0, 2.0], rotate = { axis = [0.0, 1.0, 0.0], degrees = 30.0 },
+                      translate = [1.0, 0.0, -2.0] }
+
+    which is applied as scale, then rotate, then translate. An affine `matrix` (4 rows of 4) can be
+    given too and is applied first. A mesh file is only loaded once; every object using it with the
+    same material shares it, so placing many transformed copies of a model is cheap.
+
+    Objects move from time 0 to time 1, and are blurred by the part of that motion seen while the
+    camera shutter is open: a sphere moves from `center` to an optional `center_end`, and any object
+    moves from its `transform` (or from where it is defined, without one) to an optional
+    `transform_end`.
+
+    glTF files (.gltf, .glb) can also be rendered directly, through their first perspective camera.
+
+    Unknown keys and invalid values are reported together with their line and column.
+ */
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// ERROR TYPE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum SceneFileError {
+    Io(std::io::Error),
+    Parse { line: usize, column: usize, message: String },
+    Gltf(GltfError),
+}
+
+impl std::fmt::Display for SceneFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SceneFileError::Io(e) => write!(f, "could not read scene file: {}", e),
+            SceneFileError::Parse { line, column, message } =>
+                write!(f, "line {}, column {}: {}", line, column, message),
+            SceneFileError::Gltf(e) => write!(f, "could not import glTF file: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SceneFileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SceneFileError::Io(e) => Some(e),
+            SceneFileError::Gltf(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SceneFileError {
+    fn from(e: std::io::Error) -> Self {
+        SceneFileError::Io(e)
+    }
+}
+
+impl From<GltfError> for SceneFileError {
+    fn from(e: GltfError) -> Self {
+        SceneFileError::Gltf(e)
+    }
+}
+
+impl SceneFileError {
+    // Error located at byte `offset` of `source`
+    fn at(source: &str, offset: usize, message: String) -> Self {
+        let (line, column) = line_and_column(source, offset);
+        SceneFileError::Parse { line: line, column: column, message: message }
+    }
+}
+
+// 1-based line and column of byte `offset` in `source`
+fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.len() - before.rfind('\n').map_or(0, |newline| newline + 1) + 1;
+    (line, column)
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// VALIDATED VALUES /////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Deserializes a value and rejects it with `message` unless `is_valid` holds. The error is raised
+// from within the deserializer, so it gets the location of the offending value.
+fn validated<'de, D, V, F>(deserializer: D, is_valid: F, message: &str) -> Result<V, D::Error>
+    where D: Deserializer<'de>, V: Deserialize<'de>, F: Fn(&V) -> bool
+{
+    let value = V::deserialize(deserializer)?;
+    if !is_valid(&value) {
+        return Err(serde::de::Error::custom(message));
+    }
+    Ok(value)
+}
+
+fn positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    validated(deserializer, |value: &f64| *value > 0.0, "value must be positive")
+}
+
+fn non_negative<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    validated(deserializer, |value: &f64| *value >= 0.0, "value must not be negative")
+}
+
+fn non_zero<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    validated(deserializer, |value: &f64| *value != 0.0, "value must not be zero")
+}
+
+fn unit_interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    validated(deserializer, |value: &f64| (0.0..=1.0).contains(value), "value must be in [0, 1]")
+}
+
+fn field_of_view<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    validated(deserializer, |value: &f64| *value > 0.0 && *value < 180.0,
+              "field of view must be in ]0, 180[ degrees")
+}
+
+fn positive_integer<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    validated(deserializer, |value: &u32| *value > 0, "value must be a positive integer")
+}
+
+fn color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f64; 3], D::Error> {
+    validated(deserializer, |value: &[f64; 3]| value.iter().all(|channel| *channel >= 0.0),
+              "color channels must not be negative")
+}
+
+fn optional_color<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[f64; 3]>, D::Error> {
+    color(deserializer).map(Some)
+}
+
+fn texture_reference<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TextureReference, D::Error> {
+    validated(deserializer, |value: &TextureReference| match value {
+        TextureReference::Color(color) => color.iter().all(|channel| *channel >= 0.0),
+        TextureReference::Name(_) => true,
+    }, "color channels must not be negative")
+}
+
+fn direction<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[f64; 3], D::Error> {
+    validated(deserializer, |value: &[f64; 3]| value.iter().any(|component| *component != 0.0),
+              "direction must not be the zero vector")
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// TAGGED TABLES ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Textures, materials and objects are tables whose `type` key says what they describe. Serde's
+// internally tagged enums buffer such a table before looking at its tag, which loses the locations
+// of its keys and values. Instead the table is read with the location of every key and value and
+// the variant named by `type` is built from the other keys, as for an externally tagged enum.
+type TaggedTable = BTreeMap<Spanned<String>, Spanned<toml::Value>>;
+
+// Error in a tagged table, at the key or value it is about (None -> the table as a whole)
+#[derive(Debug)]
+struct LocatedError {
+    span: Option<Range<usize>>,
+    message: String,
+}
+
+impl LocatedError {
+    fn at(span: Range<usize>, message: String) -> Self {
+        LocatedError { span: Some(span), message: message }
+    }
+}
+
+impl std::fmt::Display for LocatedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for LocatedError {}
+
+impl serde::de::Error for LocatedError {
+    fn custom<M: std::fmt::Display>(message: M) -> Self {
+        LocatedError { span: None, message: message.to_string() }
+    }
+}
+
+// Builds the description of type `D` (an enum with one variant per `type`) from `table`
+fn from_tagged_table<D: DeserializeOwned>(table: Spanned<TaggedTable>, source: &str) -> Result<Spanned<D>, SceneFileError> {
+    let span = table.span();
+    D::deserialize(TaggedTableDeserializer { table: table.into_inner() })
+        .map(|description| Spanned::new(span.clone(), description))
+        .map_err(|e| SceneFileError::at(source, e.span.unwrap_or(span).start, e.message))
+}
+
+struct TaggedTableDeserializer {
+    table: TaggedTable,
+}
+
+impl<'de> Deserializer<'de> for TaggedTableDeserializer {
+    type Error = LocatedError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, LocatedError> {
+        visitor.visit_enum(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option
+        unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de> EnumAccess<'de> for TaggedTableDeserializer {
+    type Error = LocatedError;
+    type Variant = TaggedFields;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(mut self, seed: V) -> Result<(V::Value, TaggedFields), LocatedError> {
+        let tag = self.table.remove("type")
+            .ok_or_else(|| <LocatedError as serde::de::Error>::missing_field("type"))?;
+        let span = tag.span();
+        let variant = seed.deserialize(tag.into_inner())
+            .map_err(|e| LocatedError::at(span, e.message().to_string()))?;
+        // Fields in the order of the file, so the first mistake in it is the one reported
+        let mut fields: Vec<(Spanned<String>, Spanned<toml::Value>)> = self.table.into_iter().collect();
+        fields.sort_by_key(|(key, _)| key.span().start);
+        Ok((variant, TaggedFields { fields: fields.into_iter(), value: None }))
+    }
+}
+
+// The keys of a tagged table other than `type`, handed out one by one
+struct TaggedFields {
+    fields: std::vec::IntoIter<(Spanned<String>, Spanned<toml::Value>)>,
+    value: Option<Spanned<toml::Value>>,
+}
+
+impl<'de> VariantAccess<'de> for TaggedFields {
+    type Error = LocatedError;
+
+    fn unit_variant(self) -> Result<(), LocatedError> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(self, _seed: S) -> Result<S::Value, LocatedError> {
+        Err(serde::de::Error::custom("expected a table"))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _length: usize, _visitor: V) -> Result<V::Value, LocatedError> {
+        Err(serde::de::Error::custom("expected a table"))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value, LocatedError> {
+        visitor.visit_map(self)
+    }
+}
+
+impl<'de> MapAccess<'de> for TaggedFields {
+    type Error = LocatedError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, LocatedError> {
+        let Some((key, value)) = self.fields.next() else {
+            return Ok(None);
+        };
+        self.value = Some(value);
+        let span = key.span();
+        seed.deserialize(key.into_inner().into_deserializer())
+            .map(Some)
+            .map_err(|e: LocatedError| LocatedError::at(span, e.message))
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, LocatedError> {
+        let value = self.value.take().ok_or_else(|| serde::de::Error::custom("value without a key"))?;
+        let span = value.span();
+        seed.deserialize(value.into_inner())
+            .map_err(|e| LocatedError::at(span, e.message().to_string()))
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// FILE STRUCTURE ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    #[serde(default, deserialize_with = "optional_color")]
+    background: Option<[f64; 3]>,
+    camera: Spanned<CameraDescription>,
+    #[serde(default)]
+    render: RenderDescription,
+    #[serde(default)]
+    textures: BTreeMap<String, Spanned<TaggedTable>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<TaggedTable>>,
+    #[serde(default)]
+    objects: Vec<Spanned<TaggedTable>>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    look_from: [f64; 3],
+    look_at: [f64; 3],
+    #[serde(default = "default_view_up", deserialize_with = "direction")]
+    view_up: [f64; 3],
+    #[serde(default = "default_vertical_fov", deserialize_with = "field_of_view")]
+    vertical_fov: f64,
+    #[serde(default, deserialize_with = "non_negative")]
+    aperture: f64,
+    #[serde(default, deserialize_with = "optional_positive")]
+    focus_distance: Option<f64>,
+    #[serde(default)]
+    shutter_open: f64,
+    #[serde(default)]
+    shutter_close: f64,
+}
+
+fn default_view_up() -> [f64; 3] {
+    [0.0, 1.0, 0.0]
+}
+
+fn default_vertical_fov() -> f64 {
+    90.0
+}
+
+fn optional_positive<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    positive(deserializer).map(Some)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RenderDescription {
+    #[serde(default = "default_width", deserialize_with = "positive_integer")]
+    width: u32,
+    #[serde(default = "default_height", deserialize_with = "positive_integer")]
+    height: u32,
+    #[serde(default = "default_samples_per_pixel", deserialize_with = "positive_integer")]
+    samples_per_pixel: u32,
+    #[serde(default = "default_max_depth", deserialize_with = "positive_integer")]
+    max_depth: u32,
+    #[serde(default)]
+    sampler: SamplerDescription,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum SamplerDescription {
+    #[default]
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    Cmj,
+}
+
+impl From<SamplerDescription> for SamplerKind {
+    fn from(sampler: SamplerDescription) -> Self {
+        match sampler {
+            SamplerDescription::Independent => SamplerKind::Independent,
+            SamplerDescription::Stratified => SamplerKind::Stratified,
+            SamplerDescription::Halton => SamplerKind::Halton,
+            SamplerDescription::Sobol => SamplerKind::Sobol,
+            SamplerDescription::Cmj => SamplerKind::CorrelatedMultiJitter,
+        }
+    }
+}
+
+fn default_width() -> u32 {
+    RenderSettings::default().width as u32
+}
+
+fn default_height() -> u32 {
+    RenderSettings::default().height as u32
+}
+
+fn default_samples_per_pixel() -> u32 {
+    RenderSettings::default().samples_per_pixel
+}
+
+fn default_max_depth() -> u32 {
+    RenderSettings::default().max_depth as u32
+}
+
+impl Default for RenderDescription {
+    fn default() -> Self {
+        RenderDescription { width: default_width(),
+                            height: default_height(),
+                            samples_per_pixel: default_samples_per_pixel(),
+                            max_depth: default_max_depth(),
+                            sampler: SamplerDescription::default() }
+    }
+}
+
+// A constant color, or the name of a texture
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a color [r, g, b] or the name of a texture")]
+enum TextureReference {
+    Color([f64; 3]),
+    Name(String),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum TextureDescription {
+    Checker {
+        #[serde(deserialize_with = "positive")]
+        scale: f64,
+        #[serde(deserialize_with = "texture_reference")]
+        even: TextureReference,
+        #[serde(deserialize_with = "texture_reference")]
+        odd: TextureReference,
+    },
+    Image {
+        path: String,
+        #[serde(default)]
+        wrap: WrapDescription,
+        #[serde(default)]
+        linear: bool,
+    },
+    Noise {
+        #[serde(default)]
+        pattern: PatternDescription,
+        #[serde(default = "default_noise_scale", deserialize_with = "positive")]
+        scale: f64,
+        #[serde(default = "default_noise_color", deserialize_with = "color")]
+        color: [f64; 3],
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum WrapDescription {
+    #[default]
+    Repeat,
+    Clamp,
+    Mirror,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+enum PatternDescription {
+    #[default]
+    Smooth,
+    Turbulence,
+    Marble,
+}
+
+fn default_noise_scale() -> f64 {
+    1.0
+}
+
+fn default_noise_color() -> [f64; 3] {
+    [1.0, 1.0, 1.0]
+}
+
+impl TextureDescription {
+    // Textures this one is built from
+    fn references(&self) -> Vec<&TextureReference> {
+        match self {
+            TextureDescription::Checker { even, odd, .. } => vec![even, odd],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum MaterialDescription {
+    Lambertian {
+        #[serde(deserialize_with = "texture_reference")]
+        albedo: TextureReference,
+        normal_map: Option<NormalMapDescription>,
+        bump_map: Option<BumpMapDescription>,
+    },
+    Metal {
+        #[serde(deserialize_with = "texture_reference")]
+        albedo: TextureReference,
+        #[serde(default, deserialize_with = "unit_interval")]
+        fuzz: f64,
+        normal_map: Option<NormalMapDescription>,
+        bump_map: Option<BumpMapDescription>,
+    },
+    Dielectric {
+        #[serde(deserialize_with = "positive")]
+        index_of_refraction: f64,
+        normal_map: Option<NormalMapDescription>,
+        bump_map: Option<BumpMapDescription>,
+    },
+    DiffuseLight {
+        #[serde(deserialize_with = "texture_reference")]
+        emit: TextureReference,
+        #[serde(default = "default_two_sided")]
+        two_sided: bool,
+    },
+}
+
+fn default_two_sided() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NormalMapDescription {
+    texture: String,
+    #[serde(default = "default_strength", deserialize_with = "non_negative")]
+    strength: f64,
+}
+
+fn default_strength() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BumpMapDescription {
+    texture: String,
+    scale: f64,
+}
+
+impl MaterialDescription {
+    // Normal and bump maps bending the shading normal of the material
+    fn surface_detail(&self) -> (Option<&NormalMapDescription>, Option<&BumpMapDescription>) {
+        match self {
+            MaterialDescription::Lambertian { normal_map, bump_map, .. } |
+            MaterialDescription::Metal { normal_map, bump_map, .. } |
+            MaterialDescription::Dielectric { normal_map, bump_map, .. } => (normal_map.as_ref(), bump_map.as_ref()),
+            MaterialDescription::DiffuseLight { .. } => (None, None),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+enum ObjectDescription {
+    Sphere {
+        center: [f64; 3],
+        center_end: Option<[f64; 3]>,
+        #[serde(deserialize_with = "non_zero")]
+        radius: f64,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Triangle {
+        vertices: [[f64; 3]; 3],
+        normals: Option<[[f64; 3]; 3]>,
+        uvs: Option<[[f64; 2]; 3]>,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Mesh {
+        path: String,
+        material: Option<String>,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Plane {
+        point: [f64; 3],
+        #[serde(deserialize_with = "direction")]
+        normal: [f64; 3],
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Disk {
+        center: [f64; 3],
+        #[serde(deserialize_with = "direction")]
+        normal: [f64; 3],
+        #[serde(deserialize_with = "positive")]
+        radius: f64,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Rect {
+        plane: RectPlaneDescription,
+        min: [f64; 2],
+        max: [f64; 2],
+        offset: f64,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Quad {
+        corner: [f64; 3],
+        edge_u: [f64; 3],
+        edge_v: [f64; 3],
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Box {
+        min: [f64; 3],
+        max: [f64; 3],
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Cylinder {
+        base: [f64; 3],
+        top: [f64; 3],
+        #[serde(deserialize_with = "positive")]
+        radius: f64,
+        #[serde(default = "default_caps")]
+        caps: bool,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Cone {
+        base: [f64; 3],
+        top: [f64; 3],
+        #[serde(deserialize_with = "non_negative")]
+        base_radius: f64,
+        #[serde(default, deserialize_with = "non_negative")]
+        top_radius: f64,
+        #[serde(default = "default_caps")]
+        caps: bool,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Torus {
+        center: [f64; 3],
+        #[serde(deserialize_with = "direction")]
+        axis: [f64; 3],
+        #[serde(deserialize_with = "positive")]
+        major_radius: f64,
+        #[serde(deserialize_with = "positive")]
+        minor_radius: f64,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+    Capsule {
+        start: [f64; 3],
+        end: [f64; 3],
+        #[serde(deserialize_with = "positive")]
+        radius: f64,
+        material: String,
+        transform: Option<TransformDescription>,
+        transform_end: Option<TransformDescription>,
+    },
+}
+
+fn default_caps() -> bool {
+    true
+}
+
+// Object to world transform, applied as scale, then rotate, then translate, all optional. A
+// general affine `matrix` (row by row) can be given as well; it is applied before the others.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TransformDescription {
+    matrix: Option<[[f64; 4]; 4]>,
+    #[serde(default, deserialize_with = "scale_factors")]
+    scale: Option<[f64; 3]>,
+    rotate: Option<RotationDescription>,
+    translate: Option<[f64; 3]>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RotationDescription {
+    #[serde(deserialize_with = "direction")]
+    axis: [f64; 3],
+    degrees: f64,
+}
+
+fn scale_factors<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<[f64; 3]>, D::Error> {
+    validated(deserializer, |value: &[f64; 3]| value.iter().all(|factor| *factor != 0.0),
+              "scale factors must not be zero").map(Some)
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum RectPlaneDescription {
+    Xy,
+    Xz,
+    Yz,
+}
+
+impl ObjectDescription {
+    fn material(&self) -> Option<&str> {
+        match self {
+            ObjectDescription::Sphere { material, .. }
+            | ObjectDescription::Triangle { material, .. }
+            | ObjectDescription::Plane { material, .. }
+            | ObjectDescription::Disk { material, .. }
+            | ObjectDescription::Rect { material, .. }
+            | ObjectDescription::Quad { material, .. }
+            | ObjectDescription::Box { material, .. }
+            | ObjectDescription::Cylinder { material, .. }
+            | ObjectDescription::Cone { material, .. }
+            | ObjectDescription::Torus { material, .. }
+            | ObjectDescription::Capsule { material, .. } => Some(material),
+            ObjectDescription::Mesh { material, .. } => material.as_deref(),
+        }
+    }
+
+    // Shapes implementing the queries needed to sample them as lights
+    fn can_be_sampled(&self) -> bool {
+        matches!(self, ObjectDescription::Sphere { .. } | ObjectDescription::Disk { .. }
+                       | ObjectDescription::Rect { .. } | ObjectDescription::Quad { .. })
+    }
+
+    fn transforms(&self) -> (Option<&TransformDescription>, Option<&TransformDescription>) {
+        match self {
+            ObjectDescription::Sphere { transform, transform_end, .. }
+            | ObjectDescription::Triangle { transform, transform_end, .. }
+            | ObjectDescription::Mesh { transform, transform_end, .. }
+            | ObjectDescription::Plane { transform, transform_end, .. }
+            | ObjectDescription::Disk { transform, transform_end, .. }
+            | ObjectDescription::Rect { transform, transform_end, .. }
+            | ObjectDescription::Quad { transform, transform_end, .. }
+            | ObjectDescription::Box { transform, transform_end, .. }
+            | ObjectDescription::Cylinder { transform, transform_end, .. }
+            | ObjectDescription::Cone { transform, transform_end, .. }
+            | ObjectDescription::Torus { transform, transform_end, .. }
+            | ObjectDescription::Capsule { transform, transform_end, .. } => (transform.as_ref(), transform_end.as_ref()),
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// LOADING //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Everything needed to render a scene file
+pub struct SceneFile<T: DataTypeTraits>
+{
+    pub scene: Scene<T>,
+    pub camera: Camera<T>,
+    pub settings: RenderSettings,
+    // Problems the scene was loaded in spite of, such as mesh materials that could not be found
+    pub warnings: Vec<String>,
+    camera_description: CameraDescription,
+}
+
+impl<T: DataTypeTraits> SceneFile<T> {
+    // Changes the resolution of the image, rebuilding the camera for the new aspect ratio
+    pub fn set_resolution(&mut self, width: usize, height: usize) {
+        self.settings.width = width;
+        self.settings.height = height;
+        // The description was already validated when the scene file was loaded
+        self.camera = build_camera(&self.camera_description, &self.settings).unwrap();
+    }
+}
+
+/// Reads and builds the scene file at `path`. See `parse_scene` for details, and `load_gltf_file`
+/// for .gltf and .glb files.
+pub fn load_scene_file<T: DataTypeTraits>(path: &Path) -> Result<SceneFile<T>, SceneFileError> {
+    if matches!(lowercase_extension(path).as_deref(), Some("gltf") | Some("glb")) {
+        return load_gltf_file(path);
+    }
+    let source = std::fs::read_to_string(path)?;
+    parse_scene(&source, path.parent().unwrap_or(Path::new(".")))
+}
+
+/// Builds the scene, camera and render settings described by the TOML document `source`. Mesh
+/// files are looked up relative to `directory`. The objects of the scene are put in a bounding
+/// volume hierarchy.
+pub fn parse_scene<T: DataTypeTraits>(source: &str, directory: &Path) -> Result<SceneFile<T>, SceneFileError> {
+    let description: SceneDescription = toml::from_str(source).map_err(|e| {
+        let offset = e.span().map_or(0, |span| span.start);
+        SceneFileError::at(source, offset, e.message().to_string())
+    })?;
+
+    let settings = RenderSettings { width: description.render.width as usize,
+                                    height: description.render.height as usize,
+                                    samples_per_pixel: description.render.samples_per_pixel,
+                                    max_depth: description.render.max_depth as i32,
+                                    sampler: description.render.sampler.into(),
+                                    background: description.background.map_or(Background::Sky, Background::Solid),
+                                    ..RenderSettings::default() };
+    let camera = build_camera(description.camera.get_ref(), &settings)
+        .map_err(|message| SceneFileError::at(source, description.camera.span().start, message))?;
+    let texture_descriptions: BTreeMap<String, Spanned<TextureDescription>> = description.textures.into_iter()
+        .map(|(name, table)| Ok((name, from_tagged_table(table, source)?)))
+        .collect::<Result<_, SceneFileError>>()?;
+    let material_descriptions: BTreeMap<String, Spanned<MaterialDescription>> = description.materials.into_iter()
+        .map(|(name, table)| Ok((name, from_tagged_table(table, source)?)))
+        .collect::<Result<_, SceneFileError>>()?;
+    let object_descriptions: Vec<Spanned<ObjectDescription>> = description.objects.into_iter()
+        .map(|table| from_tagged_table(table, source))
+        .collect::<Result<_, _>>()?;
+
+    // Textures can be built from other textures, so each one waits for those it uses
+    let mut textures: BTreeMap<&str, Arc<dyn Texture<T> + Send + Sync>> = BTreeMap::new();
+    let mut pending: Vec<(&String, &Spanned<TextureDescription>)> = texture_descriptions.iter().collect();
+    while !pending.is_empty() {
+        let pending_count = pending.len();
+        let mut waiting = Vec::new();
+        for (name, texture) in pending {
+            let mut is_ready = true;
+            for reference in texture.get_ref().references() {
+                if let TextureReference::Name(used) = reference {
+                    if !texture_descriptions.contains_key(used) {
+                        return Err(SceneFileError::at(source, texture.span().start, format!("unknown texture `{}`", used)));
+                    }
+                    is_ready &= textures.contains_key(used.as_str());
+                }
+            }
+            if !is_ready {
+                waiting.push((name, texture));
+                continue;
+            }
+            let built = build_texture(texture.get_ref(), &textures, directory)
+                .map_err(|message| SceneFileError::at(source, texture.span().start, message))?;
+            textures.insert(name.as_str(), built);
+        }
+        if waiting.len() == pending_count {
+            let (name, texture) = waiting[0];
+            return Err(SceneFileError::at(source, texture.span().start,
+                                          format!("texture `{}` is built from itself", name)));
+        }
+        pending = waiting;
+    }
+
+    let mut materials: BTreeMap<&str, Arc<dyn Material<T> + Send + Sync>> = BTreeMap::new();
+    for (name, material) in material_descriptions.iter() {
+        let built = build_material(material.get_ref(), &textures)
+            .map_err(|message| SceneFileError::at(source, material.span().start, message))?;
+        materials.insert(name.as_str(), built);
+    }
+    let is_light = |name: Option<&str>| name.and_then(|name| material_descriptions.get(name))
+        .is_some_and(|material| matches!(material.get_ref(), MaterialDescription::DiffuseLight { .. }));
+
+    // Every mesh file is loaded once, then shared by all the objects using it
+    let mut meshes: BTreeMap<(PathBuf, Option<String>), Vec<Arc<dyn Hittable<T> + Send + Sync>>> = BTreeMap::new();
+    let mut warnings: Vec<String> = Vec::new();
+    let mut scene = Scene::default();
+    for object in object_descriptions.iter() {
+        let (transform, transform_end) = object.get_ref().transforms();
+        let build = |transform: Option<&TransformDescription>| transform.map(build_transform::<T>).transpose()
+            .map_err(|message| SceneFileError::at(source, object.span().start, message));
+        let (transform, transform_end) = (build(transform)?, build(transform_end)?);
+        let material = match object.get_ref().material() {
+            Some(name) => materials.get(name).ok_or_else(|| {
+                SceneFileError::at(source, object.span().start, format!("unknown material `{}`", name))
+            })?.clone(),
+            None => default_material(),
+        };
+        let mut hittables: Vec<Box<dyn Hittable<T> + Send + Sync>> = Vec::new();
+        match object.get_ref() {
+            ObjectDescription::Sphere { center, center_end, radius, .. } => {
+                let sphere = match center_end {
+                    Some(center_end) => Sphere::moving(to_vector(center), to_vector(center_end), T::zero(), T::one(),
+                                                       T::from(*radius).unwrap(), material),
+                    None => Sphere::new(to_vector(center), T::from(*radius).unwrap(), material),
+                };
+                hittables.push(Box::new(sphere));
+            }
+            ObjectDescription::Triangle { vertices, normals, uvs, .. } => {
+                let normals = normals.map(|normals| normals.map(|normal| to_vector::<T>(&normal)));
+                let uvs = uvs.map(|uvs| uvs.map(|uv| Vector2D { x: T::from(uv[0]).unwrap(), y: T::from(uv[1]).unwrap() }));
+                hittables.push(Box::new(Triangle::with_attributes(vertices.map(|vertex| to_vector(&vertex)),
+                                                             normals, uvs, material)));
+            }
+            ObjectDescription::Mesh { path, material: material_name, .. } => {
+                let path = directory.join(path);
+                let key = (path.clone(), material_name.clone());
+                if !meshes.contains_key(&key) {
+                    let loaded: Vec<Arc<dyn Hittable<T> + Send + Sync>> = match lowercase_extension(&path).as_deref() {
+                        Some("obj") => load_obj(&path, material).map(|model| {
+                            warnings.extend(model.warnings.iter().map(|warning| warning.to_string()));
+                            vec![Arc::new(model.mesh) as _]
+                        }).map_err(|e| match e {
+                            ObjError::Parse { path, line, message } => format!("{}:{}: {}", path.display(), line, message),
+                            ObjError::Io { path, error } => format!("cannot read `{}`: {}", path.display(), error),
+                        }),
+                        Some("ply") => load_ply(&path, material).map(|mesh| vec![Arc::new(mesh) as _]).map_err(|e| format!("{}: {}", path.display(), e)),
+                        Some("gltf") | Some("glb") => load_gltf(&path)
+                            .map(|imported| imported.meshes.into_iter().map(|mesh| Arc::new(mesh) as _).collect())
+                            .map_err(|e| format!("{}: {}", path.display(), e)),
+                        _ => Err(format!("unsupported mesh file `{}` (expected .obj, .ply, .gltf or .glb)", path.display())),
+                    }.map_err(|message| SceneFileError::at(source, object.span().start, message))?;
+                    meshes.insert(key.clone(), loaded);
+                }
+                for mesh in meshes[&key].iter() {
+                    hittables.push(Box::new(mesh.clone()));
+                }
+            }
+            ObjectDescription::Plane { point, normal, .. } => {
+                hittables.push(Box::new(Plane::new(to_vector(point), to_vector(normal), material)));
+            }
+            ObjectDescription::Disk { center, normal, radius, .. } => {
+                hittables.push(Box::new(Disk::new(to_vector(center), to_vector(normal), T::from(*radius).unwrap(), material)));
+            }
+            ObjectDescription::Rect { plane, min, max, offset, .. } => {
+                if min[0] >= max[0] || min[1] >= max[1] {
+                    return Err(SceneFileError::at(source, object.span().start, "rect `min` must be below `max` on both axes".to_string()));
+                }
+                let plane = match plane {
+                    RectPlaneDescription::Xy => RectPlane::XY,
+                    RectPlaneDescription::Xz => RectPlane::XZ,
+                    RectPlaneDescription::Yz => RectPlane::YZ,
+                };
+                let value = |value: f64| T::from(value).unwrap();
+                hittables.push(Box::new(Rect::new(plane, value(min[0]), value(max[0]), value(min[1]), value(max[1]), value(*offset), material)));
+            }
+            ObjectDescription::Quad { corner, edge_u, edge_v, .. } => {
+                let (edge_u, edge_v): (Vector3D<T>, Vector3D<T>) = (to_vector(edge_u), to_vector(edge_v));
+                if edge_u.cross_product(&edge_v).norm() == T::zero() {
+                    return Err(SceneFileError::at(source, object.span().start, "quad edges must not be parallel".to_string()));
+                }
+                hittables.push(Box::new(Quad::new(to_vector(corner), edge_u, edge_v, material)));
+            }
+            ObjectDescription::Box { min, max, .. } => {
+                if (0..3).any(|axis| min[axis] >= max[axis]) {
+                    return Err(SceneFileError::at(source, object.span().start, "box `min` must be below `max` on every axis".to_string()));
+                }
+                hittables.push(Box::new(AxisAlignedBox::new(to_vector(min), to_vector(max), material)));
+            }
+            ObjectDescription::Cylinder { base, top, radius, caps, .. } => {
+                if base == top {
+                    return Err(SceneFileError::at(source, object.span().start, "cylinder `base` and `top` must differ".to_string()));
+                }
+                hittables.push(Box::new(Cylinder::new(to_vector(base), to_vector(top), T::from(*radius).unwrap(), *caps, material)));
+            }
+            ObjectDescription::Cone { base, top, base_radius, top_radius, caps, .. } => {
+                if base == top {
+                    return Err(SceneFileError::at(source, object.span().start, "cone `base` and `top` must differ".to_string()));
+                }
+                if *base_radius == 0.0 && *top_radius == 0.0 {
+                    return Err(SceneFileError::at(source, object.span().start, "cone radii must not both be zero".to_string()));
+                }
+                hittables.push(Box::new(Cone::new(to_vector(base), to_vector(top), T::from(*base_radius).unwrap(),
+                                             T::from(*top_radius).unwrap(), *caps, material)));
+            }
+            ObjectDescription::Torus { center, axis, major_radius, minor_radius, .. } => {
+                hittables.push(Box::new(Torus::new(to_vector(center), to_vector(axis), T::from(*major_radius).unwrap(),
+                                              T::from(*minor_radius).unwrap(), material)));
+            }
+            ObjectDescription::Capsule { start, end, radius, .. } => {
+                hittables.push(Box::new(Capsule::new(to_vector(start), to_vector(end), T::from(*radius).unwrap(), material)));
+            }
+        }
+        match (transform, transform_end) {
+            // Lit shapes that can be sampled are also handed to the integrator as lights
+            (None, None) if object.get_ref().can_be_sampled() && is_light(object.get_ref().material()) => for hittable in hittables {
+                let light: Arc<dyn Hittable<T> + Send + Sync> = Arc::from(hittable);
+                scene.add(Box::new(light.clone()));
+                scene.add_light(light);
+            },
+            (None, None) => hittables.into_iter().for_each(|hittable| scene.add(hittable)),
+            (Some(transform), None) => for hittable in hittables {
+                scene.add(Box::new(Instance::new(Arc::from(hittable), transform)));
+            },
+            (transform, Some(transform_end)) => for hittable in hittables {
+                scene.add(Box::new(Instance::moving(Arc::from(hittable), transform.unwrap_or_default(), transform_end,
+                                                    T::zero(), T::one())));
+            },
+        }
+    }
+    scene.build_bvh();
+
+    Ok(SceneFile { scene: scene, camera: camera, settings: settings, warnings: warnings,
+                   camera_description: description.camera.into_inner() })
+}
+
+/// Builds a scene from every mesh of the glTF file at `path`, seen through its first perspective
+/// camera. The image is as wide as by default, with the aspect ratio of the camera if it has one.
+pub fn load_gltf_file<T: DataTypeTraits>(path: &Path) -> Result<SceneFile<T>, SceneFileError> {
+    let imported = load_gltf::<T>(path)?;
+    let camera = imported.cameras.first()
+        .ok_or_else(|| GltfError::Invalid("file contains no perspective camera".to_string()))?;
+
+    let mut settings = RenderSettings::default();
+    if let Some(aspect_ratio) = camera.aspect_ratio {
+        settings.height = (settings.width as f64 / aspect_ratio.to_f64().unwrap()).round().max(1.0) as usize;
+    }
+    let to_array = |vector: &Vector3D<T>| [vector.x.to_f64().unwrap(), vector.y.to_f64().unwrap(), vector.z.to_f64().unwrap()];
+    let camera_description = CameraDescription { look_from: to_array(&camera.look_from),
+                                                 look_at: to_array(&camera.look_at),
+                                                 view_up: to_array(&camera.view_up),
+                                                 vertical_fov: camera.vertical_fov.to_f64().unwrap(),
+                                                 aperture: 0.0,
+                                                 focus_distance: None,
+                                                 shutter_open: 0.0,
+                                                 shutter_close: 0.0 };
+    let camera = build_camera(&camera_description, &settings).map_err(GltfError::Invalid)?;
+
+    let mut scene = Scene::default();
+    for mesh in imported.meshes {
+        scene.add(Box::new(mesh));
+    }
+    scene.build_bvh();
+    Ok(SceneFile { scene: scene, camera: camera, settings: settings, warnings: Vec::new(), camera_description: camera_description })
+}
+
+fn lowercase_extension(path: &Path) -> Option<String> {
+    path.extension().and_then(|extension| extension.to_str()).map(|extension| extension.to_ascii_lowercase())
+}
+
+fn build_camera<T: DataTypeTraits>(description: &CameraDescription, settings: &RenderSettings) -> Result<Camera<T>, String> {
+    let look_from: Vector3D<T> = to_vector(&description.look_from);
+    let look_at: Vector3D<T> = to_vector(&description.look_at);
+    let view_up: Vector3D<T> = to_vector(&description.view_up);
+    let viewing_direction = look_at - look_from;
+    if viewing_direction.norm() == T::zero() {
+        return Err("camera `look_from` and `look_at` must be different points".to_string());
+    }
+    if viewing_direction.cross_product(&view_up).norm() == T::zero() {
+        return Err("camera `view_up` must not be parallel to the viewing direction".to_string());
+    }
+    let focus_distance = match description.focus_distance {
+        Some(distance) => T::from(distance).unwrap(),
+        None => viewing_direction.norm(),
+    };
+    if description.shutter_close < description.shutter_open {
+        return Err("camera `shutter_close` must not be before `shutter_open`".to_string());
+    }
+    let aspect_ratio = T::from(settings.width as f64 / settings.height as f64).unwrap();
+    Ok(Camera::thin_lens(look_from, look_at, view_up, T::from(description.vertical_fov).unwrap(),
+                         aspect_ratio, T::from(description.aperture).unwrap(), focus_distance)
+        .with_shutter(T::from(description.shutter_open).unwrap(), T::from(description.shutter_close).unwrap()))
+}
+
+fn build_transform<T: DataTypeTraits>(description: &TransformDescription) -> Result<Matrix4<T>, String> {
+    let value = |value: f64| T::from(value).unwrap();
+    let mut transform = Matrix4::identity();
+    if let Some(rows) = description.matrix {
+        if rows[3] != [0.0, 0.0, 0.0, 1.0] {
+            return Err("transform `matrix` must be affine (last row 0, 0, 0, 1)".to_string());
+        }
+        transform = Matrix4::from_rows(rows.map(|row| row.map(value)));
+        if transform.inverse().is_none() {
+            return Err("transform `matrix` must be invertible".to_string());
+        }
+    }
+    if let Some(scale) = description.scale {
+        transform = Matrix4::scaling(&to_vector(&scale)) * transform;
+    }
+    if let Some(rotate) = &description.rotate {
+        transform = Matrix4::rotation(&to_vector(&rotate.axis), value(rotate.degrees.to_radians())) * transform;
+    }
+    if let Some(translate) = description.translate {
+        transform = Matrix4::translation(&to_vector(&translate)) * transform;
+    }
+    Ok(transform)
+}
+
+type TextureMap<'a, T> = BTreeMap<&'a str, Arc<dyn Texture<T> + Send + Sync>>;
+
+// Texture a color or texture name stands for. Named textures must already be built.
+fn resolve_texture<T: DataTypeTraits>(reference: &TextureReference, textures: &TextureMap<T>) -> Result<Arc<dyn Texture<T> + Send + Sync>, String> {
+    match reference {
+        TextureReference::Color(color) => Ok(Arc::new(SolidColor::new(to_color(color)))),
+        TextureReference::Name(name) => textures.get(name.as_str()).cloned()
+            .ok_or_else(|| format!("unknown texture `{}`", name)),
+    }
+}
+
+fn build_texture<T: DataTypeTraits>(description: &TextureDescription, textures: &TextureMap<T>, directory: &Path) -> Result<Arc<dyn Texture<T> + Send + Sync>, String> {
+    match description {
+        TextureDescription::Checker { scale, even, odd } => {
+            Ok(Arc::new(Checker::new(T::from(*scale).unwrap(), resolve_texture(even, textures)?, resolve_texture(odd, textures)?)))
+        }
+        TextureDescription::Image { path, wrap, linear } => {
+            let wrap_mode = match wrap {
+                WrapDescription::Repeat => WrapMode::Repeat,
+                WrapDescription::Clamp => WrapMode::Clamp,
+                WrapDescription::Mirror => WrapMode::Mirror,
+            };
+            let path_in_directory = directory.join(path);
+            let texture = if *linear { ImageTexture::load_linear(&path_in_directory, wrap_mode) } else { ImageTexture::load(&path_in_directory, wrap_mode) }
+                .map_err(|e| format!("could not load image `{}`: {}", path, e))?;
+            Ok(Arc::new(texture))
+        }
+        TextureDescription::Noise { pattern, scale, color, seed } => {
+            let pattern = match pattern {
+                PatternDescription::Smooth => NoisePattern::Smooth,
+                PatternDescription::Turbulence => NoisePattern::Turbulence,
+                PatternDescription::Marble => NoisePattern::Marble,
+            };
+            Ok(Arc::new(NoiseTexture::new(to_color(color), T::from(*scale).unwrap(), pattern, *seed)))
+        }
+    }
+}
+
+fn build_material<T: DataTypeTraits>(description: &MaterialDescription, textures: &TextureMap<T>) -> Result<Arc<dyn Material<T> + Send + Sync>, String> {
+    let mut material: Arc<dyn Material<T> + Send + Sync> = match description {
+        MaterialDescription::Lambertian { albedo, .. } => Arc::new(Lambertian::textured(resolve_texture(albedo, textures)?)),
+        MaterialDescription::Metal { albedo, fuzz, .. } => Arc::new(Metal::textured(resolve_texture(albedo, textures)?, T::from(*fuzz).unwrap())),
+        MaterialDescription::Dielectric { index_of_refraction, .. } => Arc::new(Dielectric::new(T::from(*index_of_refraction).unwrap())),
+        MaterialDescription::DiffuseLight { emit, two_sided } => {
+            Arc::new(DiffuseLight::textured(resolve_texture(emit, textures)?, *two_sided))
+        }
+    };
+    let named_texture = |name: &String| resolve_texture(&TextureReference::Name(name.clone()), textures);
+    let (normal_map, bump_map) = description.surface_detail();
+    if let Some(normal_map) = normal_map {
+        material = Arc::new(NormalMapped::new(material, named_texture(&normal_map.texture)?, T::from(normal_map.strength).unwrap()));
+    }
+    if let Some(bump_map) = bump_map {
+        material = Arc::new(BumpMapped::new(material, named_texture(&bump_map.texture)?, T::from(bump_map.scale).unwrap()));
+    }
+    Ok(material)
+}
+
+// Material of mesh faces that neither the mesh file nor the scene file give one
+fn default_material<T: DataTypeTraits>() -> Arc<dyn Material<T> + Send + Sync> {
+    let grey = RGBColor { R: T::from(0.5).unwrap(), G: T::from(0.5).unwrap(), B: T::from(0.5).unwrap() };
+    Arc::new(Lambertian::textured(Arc::new(VertexColor::new(grey))))
+}
+
+#[inline(always)]
+fn to_vector<T: DataTypeTraits>(values: &[f64; 3]) -> Vector3D<T> {
+    Vector3D { x: T::from(values[0]).unwrap(), y: T::from(values[1]).unwrap(), z: T::from(values[2]).unwrap() }
+}
+
+#[inline(always)]
+fn to_color<T: DataTypeTraits>(values: &[f64; 3]) -> RGBColor<T> {
+    RGBColor { R: T::from(values[0]).unwrap(), G: T::from(values[1]).unwrap(), B: T::from(values[2]).unwrap() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAMERA: &str = "[camera]\nlook_from = [0.0, 0.0, 1.0]\nlook_at = [0.0, 0.0, 0.0]\n";
+
+    // Line and message of the error the scene (after the camera table) is rejected with
+    fn error_in(scene: &str) -> (usize, String) {
+        match parse_scene::<f64>(&format!("{}{}", CAMERA, scene), Path::new(".")) {
+            Err(SceneFileError::Parse { line, message, .. }) => (line - 3, message),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("scene was accepted"),
+        }
+    }
+
+    #[test]
+    fn valid_scene_is_accepted() {
+        let scene = "[materials.grey]\ntype = \"lambertian\"\nalbedo = [0.5, 0.5, 0.5]\n\n\
+                     [[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nmaterial = \"grey\"\n";
+        assert!(parse_scene::<f64>(&format!("{}{}", CAMERA, scene), Path::new(".")).is_ok());
+    }
+
+    #[test]
+    fn invalid_scene_is_rejected() {
+        let (line, message) = error_in("[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nmaterial = \"missing\"\n");
+        assert_eq!(line, 1);
+        assert!(message.contains("missing"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_object_key_is_located() {
+        let (line, message) = error_in("[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -1.0]\nradius = 0.5\nbogus = 3\n");
+        assert_eq!(line, 5);
+        assert!(message.contains("bogus"), "{}", message);
+    }
+
+    #[test]
+    fn invalid_object_value_is_located() {
+        let (line, _) = error_in("[[objects]]\ntype = \"sphere\"\ncenter = [0.0, 0.0, -1.0]\nradius = 0.0\n");
+        assert_eq!(line, 4);
+    }
+
+    #[test]
+    fn unknown_material_key_is_located() {
+        let (line, message) = error_in("[materials.m]\ntype = \"lambertian\"\nalbedo = [0.5, 0.5, 0.5]\nbogus = 3\n");
+        assert_eq!(line, 4);
+        assert!(message.contains("bogus"), "{}", message);
+    }
+
+    #[test]
+    fn invalid_material_value_is_located() {
+        let (line, _) = error_in("[materials.m]\ntype = \"lambertian\"\nalbedo = [0.5, -0.5, 0.5]\n");
+        assert_eq!(line, 3);
+    }
+
+    #[test]
+    fn unknown_texture_key_is_located() {
+        let (line, message) = error_in("[textures.t]\ntype = \"checker\"\nscale = 1.0\neven = [1.0, 1.0, 1.0]\nodd = [0.0, 0.0, 0.0]\nbogus = 3\n");
+        assert_eq!(line, 6);
+        assert!(message.contains("bogus"), "{}", message);
+    }
+
+    #[test]
+    fn invalid_texture_value_is_located() {
+        let (line, _) = error_in("[textures.t]\ntype = \"checker\"\nscale = -1.0\neven = [1.0, 1.0, 1.0]\nodd = [0.0, 0.0, 0.0]\n");
+        assert_eq!(line, 3);
+    }
+
+    #[test]
+    fn missing_mesh_materials_are_warned_about() {
+        let directory = std::env::temp_dir().join(format!("scene_loader_lib_warnings_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("triangle.obj"), "mtllib missing.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl red\nf 1 2 3\n").unwrap();
+        let scene = format!("{}[[objects]]\ntype = \"mesh\"\npath = \"triangle.obj\"\n", CAMERA);
+        let result = parse_scene::<f64>(&scene, &directory);
+        std::fs::remove_dir_all(&directory).unwrap();
+        let warnings = match result {
+            Ok(scene_file) => scene_file.warnings,
+            Err(e) => panic!("unexpected error: {}", e),
+        };
+        assert_eq!(warnings.len(), 2, "{:?}", warnings);
+        assert!(warnings[1].contains("unknown material `red`"), "{}", warnings[1]);
+    }
+
+    #[test]
+    fn unknown_type_is_located() {
+        let (line, message) = error_in("[[objects]]\ntype = \"teapot\"\n");
+        assert_eq!(line, 2);
+        assert!(message.contains("teapot"), "{}", message);
+    }
+}
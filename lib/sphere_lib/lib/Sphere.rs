@@ -1,35 +1,80 @@
+use std::sync::Arc;
+
 use hittable_material_traits::{HitRecord, Hittable, Material};
 
-use vector_lib::Vector3D;
+use vector_lib::{OrthonormalBasis, Vector2D, Vector3D};
 use vector_lib::VectorOperations;
 use vector_lib::DataTypeTraits;
 
 use ray_lib::Ray3D;
 
+use aabb_lib::AABB;
+
+use sampler_lib::Sampler;
+
 #[derive(Clone)]
 pub struct Sphere<T: DataTypeTraits>
 {
-    pub center: Vector3D<T>,
+    pub center: Vector3D<T>, // Center at `time_start`, and at all times for a sphere at rest
+    pub center_end: Vector3D<T>, // Center at `time_end`
+    pub time_start: T,
+    pub time_end: T,
     pub radius: T,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
 }
 
 
 // Implementing Sphere<T> initialization through <T>::new()
 impl<T: DataTypeTraits> Sphere<T>
 {
-    pub fn new(center: Vector3D<T>, radius: T) -> Self {
+    pub fn new(center: Vector3D<T>, radius: T, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
         Sphere {
             center: center,
+            center_end: center,
+            time_start: T::zero(),
+            time_end: T::zero(),
+            radius: radius,
+            material: material,
+        }
+    }
+}
+
+// Implementing moving Sphere<T> initialization through <T>::moving(). The center moves in a
+// straight line from `center_start` at `time_start` to `center_end` at `time_end`, and stays put
+// before and after.
+impl<T: DataTypeTraits> Sphere<T>
+{
+    pub fn moving(center_start: Vector3D<T>, center_end: Vector3D<T>, time_start: T, time_end: T, radius: T,
+                  material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Sphere {
+            center: center_start,
+            center_end: center_end,
+            time_start: time_start,
+            time_end: time_end,
             radius: radius,
+            material: material,
+        }
+    }
+}
+
+impl<T: DataTypeTraits> Sphere<T>
+{
+    #[inline(always)]
+    pub fn center_at(&self, time: T) -> Vector3D<T> {
+        if self.time_end <= self.time_start {
+            return self.center;
         }
+        let fraction = ((time - self.time_start) / (self.time_end - self.time_start)).max(T::zero()).min(T::one());
+        self.center + (self.center_end - self.center) * fraction
     }
 }
 
 
 impl<T: DataTypeTraits> Hittable<T> for Sphere<T> {
-    fn hit(&mut self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
     {
-        let oc: Vector3D<T> = ray.origin - self.center;
+        let center = self.center_at(ray.time);
+        let oc: Vector3D<T> = ray.origin - center;
         let a: T = ray.direction.inner_product(&ray.direction);
         let b_half: T = oc.inner_product(&ray.direction);
         let c = oc.inner_product(&oc) - self.radius*self.radius;
@@ -51,11 +96,61 @@ impl<T: DataTypeTraits> Hittable<T> for Sphere<T> {
         (*hit_record).set_t(root);
         let ray_at = ray.at(root);
         (*hit_record).set_point(ray_at);
-        let outwards_normal = (ray_at - &self.center) / self.radius;
+        let outwards_normal = (ray_at - &center) / self.radius;
         (*hit_record).set_normal_vector(outwards_normal);
         (*hit_record).set_face_normal(&ray, &outwards_normal);
+        let direction = (ray_at - &center) / self.radius.abs();
+        (*hit_record).set_uv(sphere_uv(&direction));
+        // Derivatives of the point along u (around the y axis) and v (from pole to pole), up to scale
+        let radial_squared = direction.x * direction.x + direction.z * direction.z;
+        (*hit_record).set_tangents(&Vector3D { x: direction.z, y: T::zero(), z: -direction.x },
+                                   &Vector3D { x: -direction.y * direction.x, y: radial_squared, z: -direction.y * direction.z });
+        (*hit_record).set_material(self.material.clone());
         return true;
     }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let radius = self.radius.abs();
+        let extent = Vector3D { x: radius, y: radius, z: radius };
+        // Covers the whole motion of the sphere
+        Some(AABB::from_points(&[self.center - extent, self.center + extent, self.center_end - extent, self.center_end + extent]))
+    }
+
+    fn pdf_value(&self, origin: &Vector3D<T>, direction: &Vector3D<T>, time: T) -> T {
+        let mut hit_record = HitRecord::default();
+        let ray = Ray3D { origin: *origin, direction: *direction, time: time };
+        if !self.hit(&ray, T::from(0.0001).unwrap(), T::infinity(), &mut hit_record) {
+            return T::zero();
+        }
+        let to_center = self.center_at(time) - origin;
+        let distance_squared = to_center.inner_product(&to_center);
+        let radius_squared = self.radius * self.radius;
+        // From inside, directions are sampled over the whole sphere of directions
+        if distance_squared <= radius_squared {
+            return T::one() / (T::from(2.0).unwrap() * T::TAU());
+        }
+        // Uniform over the cone of directions subtended by the sphere
+        let cos_theta_max = (T::one() - radius_squared / distance_squared).sqrt();
+        T::one() / (T::TAU() * (T::one() - cos_theta_max))
+    }
+
+    fn random_direction(&self, origin: &Vector3D<T>, time: T, sampler: &mut dyn Sampler<T>) -> Vector3D<T> {
+        let to_center = self.center_at(time) - origin;
+        let distance_squared = to_center.inner_product(&to_center);
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return sampler.sample_sphere();
+        }
+        let cos_theta_max = (T::one() - radius_squared / distance_squared).sqrt();
+        let u = sampler.get_2d();
+        let phi = T::TAU() * u.x;
+        let z = T::one() + u.y * (cos_theta_max - T::one());
+        let sin_theta = (T::one() - z * z).max(T::zero()).sqrt();
+
+        // The cone is around the direction to the center
+        let basis = OrthonormalBasis::from_w(&to_center.unit_vector());
+        basis.to_world(&Vector3D { x: phi.cos() * sin_theta, y: phi.sin() * sin_theta, z: z })
+    }
 }
 
 
@@ -69,3 +164,12 @@ impl<T: DataTypeTraits> Hittable<T> for Sphere<T> {
 
 
 
+
+// Surface coordinates of the point `direction` (unit length, from the center) of a sphere: u is the
+// longitude measured around the y axis starting from -x, v the latitude from the south pole (-y).
+#[inline(always)]
+fn sphere_uv<T: DataTypeTraits>(direction: &Vector3D<T>) -> Vector2D<T> {
+    let theta = (-direction.y).max(-T::one()).min(T::one()).acos();
+    let phi = (-direction.z).atan2(direction.x) + T::PI();
+    Vector2D { x: phi / T::TAU(), y: theta / T::PI() }
+}
@@ -0,0 +1,425 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use hittable_material_traits::HitRecord;
+
+use vector_lib::{Vector3D, VectorOperations};
+use vector_lib::DataTypeTraits;
+
+use color_lib::RGBColor;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// ERROR TYPE //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug)]
+pub enum TextureError {
+    Image(image::ImageError),
+    DimensionMismatch { width: usize, height: usize, pixels: usize },
+}
+
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TextureError::Image(e) => write!(f, "could not read image: {}", e),
+            TextureError::DimensionMismatch { width, height, pixels } =>
+                write!(f, "texture of {}x{} pixels cannot be built from {} pixel values", width, height, pixels),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TextureError::Image(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        TextureError::Image(e)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////// TEXTURE TRAIT ////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+pub trait Texture<T: DataTypeTraits> {
+    // Color at surface coordinates (u, v) and point `point`
+    fn value(&self, u: T, v: T, point: &Vector3D<T>) -> RGBColor<T>;
+
+    // Color at a ray hit. Textures that depend on more than (u, v, point) override this.
+    fn value_at(&self, rec: &HitRecord<T>) -> RGBColor<T> {
+        let uv = rec.get_uv();
+        self.value(uv.x, uv.y, &rec.get_point())
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// SOLID COLOR /////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Same color everywhere
+#[derive(Clone, Copy)]
+pub struct SolidColor<T: DataTypeTraits>
+{
+    pub color: RGBColor<T>,
+}
+
+// Implementing SolidColor<T> initialization through <T>::new()
+impl<T: DataTypeTraits> SolidColor<T>
+{
+    pub fn new(color: RGBColor<T>) -> Self {
+        SolidColor { color: color }
+    }
+}
+
+impl<T: DataTypeTraits> Texture<T> for SolidColor<T> {
+    fn value(&self, _u: T, _v: T, _point: &Vector3D<T>) -> RGBColor<T> {
+        self.color
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// VERTEX COLOR ////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Color interpolated from the vertex colors of a mesh, or `fallback` on surfaces without them
+#[derive(Clone, Copy)]
+pub struct VertexColor<T: DataTypeTraits>
+{
+    pub fallback: RGBColor<T>,
+}
+
+// Implementing VertexColor<T> initialization through <T>::new()
+impl<T: DataTypeTraits> VertexColor<T>
+{
+    pub fn new(fallback: RGBColor<T>) -> Self {
+        VertexColor { fallback: fallback }
+    }
+}
+
+impl<T: DataTypeTraits> Texture<T> for VertexColor<T> {
+    fn value(&self, _u: T, _v: T, _point: &Vector3D<T>) -> RGBColor<T> {
+        self.fallback
+    }
+
+    fn value_at(&self, rec: &HitRecord<T>) -> RGBColor<T> {
+        rec.get_vertex_color().unwrap_or(self.fallback)
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+///////////////////////////////////////////// CHECKER //////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Solid 3D checkerboard of cubes with sides `scale`, alternating between two textures. Being defined
+// in space rather than in (u, v), it needs no surface coordinates.
+#[derive(Clone)]
+pub struct Checker<T: DataTypeTraits>
+{
+    pub scale: T,
+    pub even: Arc<dyn Texture<T> + Send + Sync>,
+    pub odd: Arc<dyn Texture<T> + Send + Sync>,
+}
+
+// Implementing Checker<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Checker<T>
+{
+    pub fn new(scale: T, even: Arc<dyn Texture<T> + Send + Sync>, odd: Arc<dyn Texture<T> + Send + Sync>) -> Self {
+        Checker { scale: scale, even: even, odd: odd }
+    }
+}
+
+// Implementing Checker<T> initialization with two colors through <T>::colored()
+impl<T: DataTypeTraits> Checker<T>
+{
+    pub fn colored(scale: T, even: RGBColor<T>, odd: RGBColor<T>) -> Self {
+        Checker::new(scale, Arc::new(SolidColor::new(even)), Arc::new(SolidColor::new(odd)))
+    }
+}
+
+impl<T: DataTypeTraits> Checker<T>
+{
+    #[inline(always)]
+    fn is_even(&self, point: &Vector3D<T>) -> bool {
+        let cell = |coordinate: T| (coordinate / self.scale).floor().to_i64().unwrap_or(0);
+        (cell(point.x) + cell(point.y) + cell(point.z)).rem_euclid(2) == 0
+    }
+}
+
+impl<T: DataTypeTraits> Texture<T> for Checker<T> {
+    fn value(&self, u: T, v: T, point: &Vector3D<T>) -> RGBColor<T> {
+        if self.is_even(point) { self.even.value(u, v, point) } else { self.odd.value(u, v, point) }
+    }
+
+    fn value_at(&self, rec: &HitRecord<T>) -> RGBColor<T> {
+        if self.is_even(&rec.get_point()) { self.even.value_at(rec) } else { self.odd.value_at(rec) }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// IMAGE TEXTURE ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// How texel coordinates outside of the image are mapped back into it
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum WrapMode {
+    #[default]
+    Repeat, // The image is tiled
+    Clamp,  // The border texels are extended
+    Mirror, // The image is tiled, flipping every other copy
+}
+
+impl WrapMode {
+    // Index of the texel standing in for `index` along a side of `size` texels
+    #[inline(always)]
+    fn wrap(self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::Clamp => index.clamp(0, size - 1),
+            WrapMode::Mirror => {
+                let folded = index.rem_euclid(2 * size);
+                if folded < size { folded } else { 2 * size - 1 - folded }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+// Image mapped onto the (u, v) square, with (0, 0) in the lower left corner, bilinearly filtered.
+// Texels hold linear colors: 8 and 16 bit images are taken to be sRGB encoded (unless loaded as
+// data), floating point ones (Radiance HDR) linear.
+#[derive(Clone)]
+pub struct ImageTexture<T: DataTypeTraits>
+{
+    pub wrap_mode: WrapMode,
+    width: usize,
+    height: usize,
+    texels: Vec<RGBColor<T>>, // Row by row, top row first
+}
+
+// Implementing ImageTexture<T> initialization through <T>::new()
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    pub fn new(width: usize, height: usize, texels: Vec<RGBColor<T>>, wrap_mode: WrapMode) -> Result<Self, TextureError> {
+        if width == 0 || height == 0 || texels.len() != width * height {
+            return Err(TextureError::DimensionMismatch { width: width, height: height, pixels: texels.len() });
+        }
+        Ok(ImageTexture { wrap_mode: wrap_mode, width: width, height: height, texels: texels })
+    }
+}
+
+// Implementing ImageTexture<T> initialization from an image file (PNG, JPEG or HDR) through <T>::load()
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    pub fn load(path: &Path, wrap_mode: WrapMode) -> Result<Self, TextureError> {
+        Self::read(path, wrap_mode, false)
+    }
+}
+
+// Implementing ImageTexture<T> initialization from an image file holding data rather than colors
+// (e.g. normal maps) through <T>::load_linear(). Its values are used as they are, without sRGB decoding.
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    pub fn load_linear(path: &Path, wrap_mode: WrapMode) -> Result<Self, TextureError> {
+        Self::read(path, wrap_mode, true)
+    }
+}
+
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    fn read(path: &Path, wrap_mode: WrapMode, is_data: bool) -> Result<Self, TextureError> {
+        let image = image::ImageReader::open(path).map_err(image::ImageError::IoError)?
+            .with_guessed_format().map_err(image::ImageError::IoError)?
+            .decode()?;
+        let is_linear = is_data || matches!(image.color(), image::ColorType::Rgb32F | image::ColorType::Rgba32F);
+        let decode = |channel: f32| {
+            let channel = channel as f64;
+            T::from(if is_linear { channel } else { srgb_to_linear(channel) }).unwrap()
+        };
+        let pixels = image.to_rgb32f();
+        let texels = pixels.pixels().map(|pixel| RGBColor { R: decode(pixel[0]), G: decode(pixel[1]), B: decode(pixel[2]) }).collect();
+        ImageTexture::new(pixels.width() as usize, pixels.height() as usize, texels, wrap_mode)
+    }
+}
+
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    #[inline(always)]
+    pub fn get_width(&self) -> usize {
+        self.width
+    }
+}
+
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    #[inline(always)]
+    pub fn get_height(&self) -> usize {
+        self.height
+    }
+}
+
+impl<T: DataTypeTraits> ImageTexture<T>
+{
+    #[inline(always)]
+    fn texel(&self, column: i64, row: i64) -> RGBColor<T> {
+        self.texels[self.wrap_mode.wrap(row, self.height) * self.width + self.wrap_mode.wrap(column, self.width)]
+    }
+}
+
+impl<T: DataTypeTraits> Texture<T> for ImageTexture<T> {
+    fn value(&self, u: T, v: T, _point: &Vector3D<T>) -> RGBColor<T> {
+        // Texel centers sit at half-integer coordinates; rows are counted from the top
+        let half = T::from(0.5).unwrap();
+        let x = u * T::from(self.width).unwrap() - half;
+        let y = (T::one() - v) * T::from(self.height).unwrap() - half;
+        if !x.is_finite() || !y.is_finite() {
+            return self.texel(0, 0);
+        }
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (column, row) = (x0.to_i64().unwrap(), y0.to_i64().unwrap());
+        let top = self.texel(column, row) * (T::one() - fx) + self.texel(column + 1, row) * fx;
+        let bottom = self.texel(column, row + 1) * (T::one() - fx) + self.texel(column + 1, row + 1) * fx;
+        top * (T::one() - fy) + bottom * fy
+    }
+}
+
+// Inverse of the sRGB transfer function
+#[inline(always)]
+fn srgb_to_linear(channel: f64) -> f64 {
+    if channel <= 0.04045 { channel / 12.92 } else { ((channel + 0.055) / 1.055).powf(2.4) }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////////// PERLIN NOISE ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+const PERLIN_POINTS: usize = 256;
+
+// Gradient noise over space: random unit gradients on the integer lattice, hashed through three
+// permutations and blended with a smooth (Hermite) interpolation. The same seed gives the same noise.
+#[derive(Clone)]
+pub struct Perlin<T: DataTypeTraits>
+{
+    gradients: Vec<Vector3D<T>>,
+    permutations: [Vec<usize>; 3],
+}
+
+// Implementing Perlin<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Perlin<T>
+{
+    pub fn new(seed: u64) -> Self {
+        let rng = fastrand::Rng::with_seed(seed);
+        let gradients = (0..PERLIN_POINTS).map(|_| {
+            // Uniformly distributed over the unit sphere
+            let z = 2.0 * rng.f64() - 1.0;
+            let phi = std::f64::consts::TAU * rng.f64();
+            let r = (1.0 - z * z).sqrt();
+            Vector3D { x: T::from(r * phi.cos()).unwrap(), y: T::from(r * phi.sin()).unwrap(), z: T::from(z).unwrap() }
+        }).collect();
+        let permutation = || {
+            let mut permutation: Vec<usize> = (0..PERLIN_POINTS).collect();
+            rng.shuffle(&mut permutation);
+            permutation
+        };
+        Perlin { gradients: gradients, permutations: [permutation(), permutation(), permutation()] }
+    }
+}
+
+impl<T: DataTypeTraits> Perlin<T>
+{
+    // Noise value at `point`, in [-1, 1] and zero on the lattice points
+    pub fn noise(&self, point: &Vector3D<T>) -> T {
+        let floor = [point.x.floor(), point.y.floor(), point.z.floor()];
+        let fraction = [point.x - floor[0], point.y - floor[1], point.z - floor[2]];
+        let lattice = floor.map(|coordinate| coordinate.to_i64().unwrap_or(0));
+        let smooth = fraction.map(|t| t * t * (T::from(3.0).unwrap() - T::from(2.0).unwrap() * t));
+        let mut accumulated = T::zero();
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let hash = (0..3).fold(0, |hash, axis| {
+                let index = (lattice[axis] + offset[axis] as i64).rem_euclid(PERLIN_POINTS as i64) as usize;
+                hash ^ self.permutations[axis][index]
+            });
+            let to_point = Vector3D { x: fraction[0] - T::from(offset[0]).unwrap(),
+                                      y: fraction[1] - T::from(offset[1]).unwrap(),
+                                      z: fraction[2] - T::from(offset[2]).unwrap() };
+            let weight = (0..3).fold(T::one(), |weight, axis| {
+                weight * if offset[axis] == 1 { smooth[axis] } else { T::one() - smooth[axis] }
+            });
+            accumulated = accumulated + weight * self.gradients[hash].inner_product(&to_point);
+        }
+        accumulated
+    }
+}
+
+impl<T: DataTypeTraits> Perlin<T>
+{
+    // Sum of `octaves` layers of absolute noise, each of twice the frequency and half the weight of
+    // the previous one
+    pub fn turbulence(&self, point: &Vector3D<T>, octaves: usize) -> T {
+        let mut accumulated = T::zero();
+        let mut sample_point = *point;
+        let mut weight = T::one();
+        for _ in 0..octaves {
+            accumulated = accumulated + weight * self.noise(&sample_point).abs();
+            weight = weight * T::from(0.5).unwrap();
+            sample_point = sample_point * T::from(2.0).unwrap();
+        }
+        accumulated
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// NOISE TEXTURE ///////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+const TURBULENCE_OCTAVES: usize = 7;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NoisePattern {
+    Smooth,     // Plain Perlin noise, mapped to [0, 1]
+    Turbulence, // Layered absolute noise, giving a cloudy look, capped at 1
+    Marble,     // Sine stripes along z, phase-shifted by turbulence
+}
+
+// Color modulated by Perlin noise of the hit point. `scale` is the frequency of the noise.
+#[derive(Clone)]
+pub struct NoiseTexture<T: DataTypeTraits>
+{
+    pub color: RGBColor<T>,
+    pub scale: T,
+    pub pattern: NoisePattern,
+    perlin: Perlin<T>,
+}
+
+// Implementing NoiseTexture<T> initialization through <T>::new()
+impl<T: DataTypeTraits> NoiseTexture<T>
+{
+    pub fn new(color: RGBColor<T>, scale: T, pattern: NoisePattern, seed: u64) -> Self {
+        NoiseTexture { color: color, scale: scale, pattern: pattern, perlin: Perlin::new(seed) }
+    }
+}
+
+impl<T: DataTypeTraits> Texture<T> for NoiseTexture<T> {
+    fn value(&self, _u: T, _v: T, point: &Vector3D<T>) -> RGBColor<T> {
+        let half = T::from(0.5).unwrap();
+        let scaled = point * self.scale;
+        let intensity = match self.pattern {
+            NoisePattern::Smooth => half * (T::one() + self.perlin.noise(&scaled)),
+            // The octaves can add up to more than one, which would brighten the color
+            NoisePattern::Turbulence => self.perlin.turbulence(&scaled, TURBULENCE_OCTAVES).max(T::zero()).min(T::one()),
+            NoisePattern::Marble => {
+                let phase = scaled.z + T::from(10.0).unwrap() * self.perlin.turbulence(point, TURBULENCE_OCTAVES);
+                half * (T::one() + phase.sin())
+            }
+        };
+        self.color * intensity
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use hittable_material_traits::{HitRecord, Hittable, Material};
+
+use vector_lib::{Vector2D, Vector3D};
+use vector_lib::VectorOperations;
+use vector_lib::DataTypeTraits;
+
+use ray_lib::Ray3D;
+
+use aabb_lib::AABB;
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+/////////////////////////////////////// STRUCT DEFINITION //////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Triangle with optional per-vertex normals (for smooth shading) and texture coordinates. The
+// geometric normal follows the right-hand rule for the vertex order v0 -> v1 -> v2.
+#[derive(Clone)]
+pub struct Triangle<T: DataTypeTraits>
+{
+    pub vertices: [Vector3D<T>; 3],
+    pub normals: Option<[Vector3D<T>; 3]>,
+    pub uvs: Option<[Vector2D<T>; 3]>,
+    pub material: Arc<dyn Material<T> + Send + Sync>,
+}
+
+// Implementing Triangle<T> initialization through <T>::new()
+impl<T: DataTypeTraits> Triangle<T>
+{
+    pub fn new(vertices: [Vector3D<T>; 3], material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Triangle { vertices: vertices, normals: None, uvs: None, material: material }
+    }
+}
+
+// Implementing Triangle<T> initialization with vertex attributes through <T>::with_attributes()
+impl<T: DataTypeTraits> Triangle<T>
+{
+    pub fn with_attributes(vertices: [Vector3D<T>; 3], normals: Option<[Vector3D<T>; 3]>,
+                           uvs: Option<[Vector2D<T>; 3]>, material: Arc<dyn Material<T> + Send + Sync>) -> Self {
+        Triangle { vertices: vertices, normals: normals, uvs: uvs, material: material }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////////////////////////
+////////////////////////////////////////// INTERSECTION ////////////////////////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Result of a ray-triangle intersection: distance along the ray and barycentric coordinates
+// (b1, b2) of the hit point p = (1 - b1 - b2) * v0 + b1 * v1 + b2 * v2.
+#[derive(Clone, Copy, Debug)]
+pub struct TriangleIntersection<T: DataTypeTraits>
+{
+    pub t: T,
+    pub b1: T,
+    pub b2: T,
+}
+
+/// Watertight ray-triangle intersection.
+///
+/// Like Möller–Trumbore, the hit point is found in barycentric coordinates, but these come from
+/// the signed volumes spanned by the ray and each edge of the triangle. The volume of an edge only
+/// depends on its two vertices, in order, so two triangles sharing an edge compute exactly opposite
+/// volumes for it, rounding errors included. A ray through the shared edge itself (a zero volume)
+/// goes to the triangle whose edge runs in the lexicographically positive direction, mirrored for
+/// triangles facing the ray, so it hits exactly one of them: no cracks and no duplicate hits.
+#[inline(always)]
+pub fn intersect_triangle<T: DataTypeTraits>(ray: &Ray3D<T>, v0: &Vector3D<T>, v1: &Vector3D<T>, v2: &Vector3D<T>,
+                                             t_min: T, t_max: T) -> Option<TriangleIntersection<T>> {
+    // Vertices relative to the ray origin
+    let (a, b, c) = (*v0 - ray.origin, *v1 - ray.origin, *v2 - ray.origin);
+    // Volume of edge v1 -> v2 (opposite v0) and so on, each proportional to a barycentric coordinate
+    let e0 = ray.direction.inner_product(&b.cross_product(&c));
+    let e1 = ray.direction.inner_product(&c.cross_product(&a));
+    let e2 = ray.direction.inner_product(&a.cross_product(&b));
+    let determinant = e0 + e1 + e2;
+    // Ray is parallel to the plane of the triangle (or the triangle is degenerate)
+    if determinant == T::zero() || !determinant.is_finite() {
+        return None;
+    }
+    // Inside, all volumes have the sign of the determinant, which is negative when the ray travels
+    // against the geometric normal
+    let front_facing = determinant < T::zero();
+    let inside = |volume: T, from: &Vector3D<T>, to: &Vector3D<T>| {
+        if volume == T::zero() { runs_positive(from, to) != front_facing } else { (volume < T::zero()) == front_facing }
+    };
+    if !inside(e0, v1, v2) || !inside(e1, v2, v0) || !inside(e2, v0, v1) {
+        return None;
+    }
+    let inverse_determinant = T::one() / determinant;
+    let normal = (v1 - v0).cross_product(&(v2 - v0));
+    let t = normal.inner_product(&a) / normal.inner_product(&ray.direction);
+    if t < t_min || t > t_max {
+        return None;
+    }
+    Some(TriangleIntersection { t: t, b1: e1 * inverse_determinant, b2: e2 * inverse_determinant })
+}
+
+// Whether the edge from `from` to `to` points in the lexicographically positive direction. Exactly
+// one of the two directions of an edge does, which breaks ties between the triangles sharing it.
+#[inline(always)]
+fn runs_positive<T: DataTypeTraits>(from: &Vector3D<T>, to: &Vector3D<T>) -> bool {
+    if to.x != from.x { to.x > from.x } else if to.y != from.y { to.y > from.y } else { to.z > from.z }
+}
+
+// Fills `hit_record` for an intersection with the triangle (v0, v1, v2). Shading normals and
+// texture coordinates are interpolated from the vertex attributes when given; otherwise the
+// geometric normal and the barycentric coordinates (b1, b2) are used.
+#[inline(always)]
+pub fn set_triangle_hit_record<T: DataTypeTraits>(hit_record: &mut HitRecord<T>, ray: &Ray3D<T>,
+                                                  intersection: &TriangleIntersection<T>, vertices: [&Vector3D<T>; 3],
+                                                  normals: Option<[&Vector3D<T>; 3]>, uvs: Option<[&Vector2D<T>; 3]>,
+                                                  material: &Arc<dyn Material<T> + Send + Sync>) {
+    let [v0, v1, v2] = vertices;
+    let (b1, b2) = (intersection.b1, intersection.b2);
+    let b0 = T::one() - b1 - b2;
+
+    (*hit_record).set_t(intersection.t);
+    // Interpolating the vertices is more accurate than evaluating the ray at t
+    (*hit_record).set_point(v0 * b0 + v1 * b1 + v2 * b2);
+    (*hit_record).set_barycentric(Vector3D { x: b0, y: b1, z: b2 });
+    let geometric_normal = (v1 - v0).cross_product(&(v2 - v0)).unit_vector();
+    let shading_normal = match normals {
+        Some([n0, n1, n2]) => {
+            let interpolated = n0 * b0 + n1 * b1 + n2 * b2;
+            let norm = interpolated.norm();
+            // Missing (zero) or opposing vertex normals can cancel out
+            if norm > T::zero() && norm.is_finite() { interpolated / norm } else { geometric_normal }
+        }
+        None => geometric_normal,
+    };
+    (*hit_record).set_normal_vector(shading_normal);
+    (*hit_record).set_face_normals(ray, &geometric_normal, &shading_normal);
+    let uv = match uvs {
+        Some([uv0, uv1, uv2]) => uv0 * b0 + uv1 * b1 + uv2 * b2,
+        None => Vector2D { x: b1, y: b2 },
+    };
+    (*hit_record).set_uv(uv);
+    let (edge_1, edge_2) = (v1 - v0, v2 - v0);
+    // Solving edge = dpdu * delta u + dpdv * delta v over both edges. Without texture coordinates
+    // (or with degenerate ones) u and v are the barycentric coordinates, running along the edges.
+    let (dpdu, dpdv) = match uvs {
+        Some([uv0, uv1, uv2]) => {
+            let (delta_1, delta_2) = (uv1 - uv0, uv2 - uv0);
+            let determinant = delta_1.x * delta_2.y - delta_1.y * delta_2.x;
+            if determinant != T::zero() && determinant.is_finite() {
+                ((edge_1 * delta_2.y - edge_2 * delta_1.y) / determinant,
+                 (edge_2 * delta_1.x - edge_1 * delta_2.x) / determinant)
+            } else {
+                (edge_1, edge_2)
+            }
+        }
+        None => (edge_1, edge_2),
+    };
+    (*hit_record).set_tangents(&dpdu, &dpdv);
+    (*hit_record).set_material(material.clone());
+}
+
+impl<T: DataTypeTraits> Hittable<T> for Triangle<T> {
+    fn hit(&self, ray: &Ray3D<T>, t_min: T, t_max: T, hit_record: &mut HitRecord<T>) -> bool
+    {
+        let [v0, v1, v2] = &self.vertices;
+        let intersection = match intersect_triangle(ray, v0, v1, v2, t_min, t_max) {
+            Some(intersection) => intersection,
+            None => return false,
+        };
+        // Intersection occurred - setting hit record of triangle.
+        set_triangle_hit_record(hit_record, ray, &intersection, [v0, v1, v2],
+                                self.normals.as_ref().map(|[n0, n1, n2]| [n0, n1, n2]),
+                                self.uvs.as_ref().map(|[uv0, uv1, uv2]| [uv0, uv1, uv2]),
+                                &self.material);
+        true
+    }
+
+    fn bounding_box(&self) -> Option<AABB<T>> {
+        let bbox = AABB::from_points(&self.vertices);
+        let extent = bbox.extent();
+        let largest_extent = extent.x.max(extent.y).max(extent.z);
+        Some(bbox.ensure_thickness(largest_extent * T::from(1e-6).unwrap() + T::min_positive_value()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_lib::RGBColor;
+    use material_lib::Lambertian;
+
+    fn vector(x: f64, y: f64, z: f64) -> Vector3D<f64> {
+        Vector3D { x: x, y: y, z: z }
+    }
+
+    fn ray(origin: Vector3D<f64>, direction: Vector3D<f64>) -> Ray3D<f64> {
+        Ray3D { origin: origin, direction: direction, time: 0.0 }
+    }
+
+    fn assert_close(actual: &Vector3D<f64>, expected: Vector3D<f64>) {
+        assert!((*actual - expected).norm() < 1e-9, "({}, {}, {}) != ({}, {}, {})",
+                actual.x, actual.y, actual.z, expected.x, expected.y, expected.z);
+    }
+
+    // Number of the triangles (given as vertex triples) hit by `ray`
+    fn hits(ray: &Ray3D<f64>, triangles: &[[Vector3D<f64>; 3]]) -> usize {
+        triangles.iter().filter(|[v0, v1, v2]| intersect_triangle(ray, v0, v1, v2, 0.0, f64::INFINITY).is_some()).count()
+    }
+
+    #[test]
+    fn shared_edge_is_hit_exactly_once() {
+        let rng = fastrand::Rng::with_seed(5);
+        let random = |scale: f64| vector(rng.f64() - 0.5, rng.f64() - 0.5, rng.f64() - 0.5) * scale;
+        for _ in 0..200 {
+            // A skewed quad split along its diagonal p0 - p2, with both windings of the second half.
+            // It is kept flat: a folded one could be hit twice by rays grazing one of its halves.
+            let (p0, p1, p2) = (random(4.0), random(4.0), random(4.0));
+            let p3 = p0 + (p2 - p1) * (0.5 + rng.f64());
+            for second in [[p0, p2, p3], [p3, p2, p0]] {
+                let triangles = [[p0, p1, p2], second];
+                for _ in 0..50 {
+                    // The rounded point is a hair off the edge, on either side of it
+                    let on_edge = p0 + (p2 - p0) * rng.f64();
+                    let origin = random(20.0);
+                    assert_eq!(hits(&ray(origin, on_edge - origin), &triangles), 1);
+                }
+            }
+        }
+        // Rays exactly through the edge, from both sides
+        let triangles = [[vector(0.0, 0.0, 0.0), vector(1.0, 0.0, 0.0), vector(1.0, 1.0, 0.0)],
+                         [vector(0.0, 0.0, 0.0), vector(1.0, 1.0, 0.0), vector(0.0, 1.0, 0.0)]];
+        for origin in [vector(0.5, 0.5, 1.0), vector(0.25, 0.25, -2.0), vector(3.0, -1.0, 2.0)] {
+            assert_eq!(hits(&ray(origin, vector(0.5, 0.5, 0.0) - origin), &triangles), 1);
+        }
+    }
+
+    #[test]
+    fn parallel_rays_miss() {
+        let triangle = [vector(0.0, 0.0, 1.0), vector(2.0, 0.0, 1.0), vector(0.0, 2.0, 1.0)];
+        // In the plane of the triangle, through its interior, and parallel to it above
+        assert_eq!(hits(&ray(vector(-1.0, 0.5, 1.0), vector(1.0, 0.0, 0.0)), &[triangle]), 0);
+        assert_eq!(hits(&ray(vector(-1.0, 0.5, 2.0), vector(1.0, 0.3, 0.0)), &[triangle]), 0);
+        // Degenerate triangles are never hit
+        let degenerate = [vector(0.0, 0.0, 1.0), vector(1.0, 1.0, 1.0), vector(2.0, 2.0, 1.0)];
+        assert_eq!(hits(&ray(vector(1.0, 1.0, 0.0), vector(0.0, 0.0, 1.0)), &[degenerate]), 0);
+    }
+
+    #[test]
+    fn barycentrics_locate_the_hit() {
+        let (v0, v1, v2) = (vector(1.0, 0.0, 0.0), vector(0.0, 2.0, 0.0), vector(0.0, 0.0, 3.0));
+        let point = v0 * 0.2 + v1 * 0.3 + v2 * 0.5;
+        let origin = vector(-1.0, -2.0, -1.0);
+        let direction = (point - origin) / 4.0;
+        let intersection = intersect_triangle(&ray(origin, direction), &v0, &v1, &v2, 0.0, f64::INFINITY).unwrap();
+        assert!((intersection.t - 4.0).abs() < 1e-12);
+        assert!((intersection.b1 - 0.3).abs() < 1e-12 && (intersection.b2 - 0.5).abs() < 1e-12);
+        // Out of the [t_min, t_max] range, and outside of the triangle
+        assert!(intersect_triangle(&ray(origin, direction), &v0, &v1, &v2, 0.0, 3.9).is_none());
+        assert!(intersect_triangle(&ray(origin, -direction), &v0, &v1, &v2, 0.0, f64::INFINITY).is_none());
+        let outside = v0 * 0.6 + v1 * 0.6 - v2 * 0.2;
+        assert!(intersect_triangle(&ray(origin, outside - origin), &v0, &v1, &v2, 0.0, f64::INFINITY).is_none());
+
+        // The hit record interpolates the vertex attributes with the same weights
+        let material = Arc::new(Lambertian::new(RGBColor { R: 0.5, G: 0.5, B: 0.5 }));
+        let normal = (v1 - v0).cross_product(&(v2 - v0)).unit_vector();
+        let normals = [normal, normal, vector(0.0, 1.0, 0.0)];
+        let uvs = [Vector2D { x: 0.0, y: 0.0 }, Vector2D { x: 1.0, y: 0.0 }, Vector2D { x: 0.0, y: 2.0 }];
+        let triangle = Triangle::with_attributes([v0, v1, v2], Some(normals), Some(uvs), material.clone());
+        let mut hit_record = HitRecord::default();
+        assert!(triangle.hit(&ray(origin, direction), 0.0, f64::INFINITY, &mut hit_record));
+        assert_close(&hit_record.get_point(), point);
+        assert_close(&hit_record.get_barycentric(), vector(0.2, 0.3, 0.5));
+        assert!((hit_record.get_uv().x - 0.3).abs() < 1e-12 && (hit_record.get_uv().y - 1.0).abs() < 1e-12);
+        assert_close(&hit_record.get_geometric_normal(), normal * -1.0);
+        assert_close(&hit_record.get_normal_vector(), (normal * 0.5 + vector(0.0, 0.5, 0.0)).unit_vector() * -1.0);
+        assert!(!hit_record.get_front_face());
+        // Without attributes, the UVs are the barycentrics and the normal is the geometric one
+        let triangle = Triangle::new([v0, v1, v2], material);
+        assert!(triangle.hit(&ray(origin, direction), 0.0, f64::INFINITY, &mut hit_record));
+        assert!((hit_record.get_uv().x - 0.3).abs() < 1e-12 && (hit_record.get_uv().y - 0.5).abs() < 1e-12);
+        assert_close(&hit_record.get_normal_vector(), normal * -1.0);
+    }
+}
@@ -1,9 +1,5 @@
 
-use std::io::Write;
-
-use rand_distr::{Normal, Distribution};
-
-use hittable_material_traits::{HitRecord, Hittable};
+use hittable_material_traits::{HitRecord, Hittable, ScatterRecord};
 
 use color_lib::RGBColor;
 
@@ -11,22 +7,58 @@ use vector_lib::Vector3D;
 use vector_lib::DataTypeTraits;
 use vector_lib::VectorOperations;
 
-use sphere_lib::Sphere;
-
 use ray_lib::Ray3D;
 
 use scene_lib::Scene;
 
+use sampler_lib::Sampler;
+
 // Constants
 const F32_INFINITY: f32 = f32::INFINITY;
 const F32_PI: f32 = std::f32::consts::PI;
+const T_MIN: f64 = 0.0001; // Closest hit accepted along rays leaving a surface
 
 
 
 
+// What rays that leave the scene see
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background
+{
+    Sky, // White at the horizon to light blue overhead
+    Solid([f64; 3]), // Uniform color; black for scenes lit only by their lights
+}
+
+impl Background {
+    #[inline(always)]
+    pub fn color<T: DataTypeTraits>(&self, ray: &Ray3D<T>) -> RGBColor<T> {
+        match self {
+            Background::Sky => {
+                let unit_direction = ray.direction.unit_vector();
+                let t = T::from(0.5).unwrap() * (unit_direction.y + T::one());
+                RGBColor{R: T::one(), G:  T::one(), B:  T::one()} * ( T::one() - t) +
+                    RGBColor{R: T::from(0.5).unwrap(), G: T::from(0.7).unwrap(), B:  T::one()} * t
+            }
+            Background::Solid(color) => RGBColor{R: T::from(color[0]).unwrap(), G: T::from(color[1]).unwrap(), B: T::from(color[2]).unwrap()},
+        }
+    }
+}
+
+
 #[inline(always)]
-// True Lambertian reflection
-pub fn ray_color<T: DataTypeTraits>(ray: &Ray3D<T>, scene: &mut Scene<T>, depth: i32) -> RGBColor<T> {
+// Recursively traces a ray through the scene, scattering off the materials of the objects it hits
+// and collecting the light emitted by them and by the background. At diffuse surfaces the lights
+// of the scene are also sampled directly, and both estimates of their light are combined with
+// multiple importance sampling. All random decisions along the path are drawn from `sampler`.
+pub fn ray_color<T: DataTypeTraits>(ray: &Ray3D<T>, scene: &Scene<T>, background: &Background, depth: i32,
+                                    sampler: &mut dyn Sampler<T>) -> RGBColor<T> {
+    trace(ray, scene, background, depth, None, sampler)
+}
+
+// `bsdf_pdf` is the density the previous bounce chose the direction of `ray` with, or None when
+// lights could not have been sampled instead (camera rays and specular bounces)
+fn trace<T: DataTypeTraits>(ray: &Ray3D<T>, scene: &Scene<T>, background: &Background, depth: i32,
+                            bsdf_pdf: Option<T>, sampler: &mut dyn Sampler<T>) -> RGBColor<T> {
     // If we've exceeded the ray bounce limit, no more light is gathered.
     if depth <= 0 {
         return RGBColor{R: T::zero(), G: T::zero(), B: T::zero()};
@@ -38,171 +70,166 @@ pub fn ray_color<T: DataTypeTraits>(ray: &Ray3D<T>, scene: &mut Scene<T>, depth:
     // but instead at t=−0.0000001 or t=0.00000001 or whatever floating point approximation
     // the intersector gives us. So we need to ignore hits very near zero.
     //  -> This gets rid of the shadow acne problem.
-    if scene.hit(ray, T::from(0.0001).unwrap(), T::from(F32_INFINITY).unwrap(), &mut hit_record) {
-        let p = hit_record.get_point();
-        //let method: &str = "Acceptance-Rejection";
-        //let method: &str = "Native";
-        let method: &str = "Inverse-CDF";
-        //let method: &str = "Gaussian-Sampling";
-        let target = &p + hit_record.get_normal_vector() + random_uniform_unit_sphere_point(method);
-        // Absorb half the energy on each bounce
-        let absorb_coefficient = T::from(0.5).unwrap();
-        return  ray_color(&Ray3D{origin: p, direction: target - p}, scene, depth-1) * absorb_coefficient;
+    if !scene.hit(ray, T::from(T_MIN).unwrap(), T::from(F32_INFINITY).unwrap(), &mut hit_record) {
+        return background.color(ray);
+    }
+    let material = hit_record.get_material();
+    let mut color = material.emitted(ray, &hit_record);
+    if let Some(bsdf_pdf) = bsdf_pdf {
+        if !is_black(&color) {
+            color = color * power_heuristic(bsdf_pdf, light_pdf(ray, scene, hit_record.get_t()));
+        }
     }
-    // Unnormalized normal vector of sphere at point of intersection w. ray
-    let unit_direction = ray.direction.unit_vector();
-    let t = T::from(0.5).unwrap() * (unit_direction.y + T::one());
-    return RGBColor{R: T::one(), G:  T::one(), B:  T::one()} * ( T::one() - t) +
-        RGBColor{R: T::from(0.5).unwrap(), G: T::from(0.7).unwrap(), B:  T::one()} * t
 
+    // Let the material of the hit object decide how (and if) the ray is scattered
+    let mut scatter_record = ScatterRecord::default();
+    if !material.scatter(ray, &hit_record, &mut scatter_record, sampler) {
+        // Ray was absorbed
+        return color;
+    }
+    if scatter_record.is_specular() {
+        return color + trace(&scatter_record.ray, scene, background, depth-1, None, sampler) * scatter_record.attenuation;
+    }
+    if !scene.lights.is_empty() {
+        color = color + sample_light(ray, &hit_record, scene, sampler);
+    }
+    color + trace(&scatter_record.ray, scene, background, depth-1, Some(scatter_record.pdf), sampler) * scatter_record.attenuation
+}
+
+// Light reaching the hit point straight from a light picked at random, weighted against finding it
+// by scattering
+fn sample_light<T: DataTypeTraits>(ray: &Ray3D<T>, hit_record: &HitRecord<T>, scene: &Scene<T>,
+                                   sampler: &mut dyn Sampler<T>) -> RGBColor<T> {
+    let lights = &scene.lights;
+    let index = (sampler.get_1d() * T::from(lights.len()).unwrap()).to_usize().unwrap_or(0).min(lights.len() - 1);
+    let point = hit_record.get_point();
+    let direction = lights[index].random_direction(&point, ray.time, sampler);
+    // Directions into the surface cannot reach a light without crossing it
+    if direction.inner_product(&hit_record.get_geometric_normal()) <= T::zero() {
+        return RGBColor::zeros();
+    }
+    let shadow_ray = Ray3D{origin: point, direction: direction, time: ray.time};
+    let mut light_record = HitRecord::default();
+    if !scene.hit(&shadow_ray, T::from(T_MIN).unwrap(), T::from(F32_INFINITY).unwrap(), &mut light_record) {
+        return RGBColor::zeros();
+    }
+    // The sample only counts if the picked light is visible; light from another one in front of it
+    // is found when that light is picked
+    if !hit_at(lights[index].as_ref(), &shadow_ray, light_record.get_t()) {
+        return RGBColor::zeros();
+    }
+    let emitted = light_record.get_material().emitted(&shadow_ray, &light_record);
+    let light_pdf = light_pdf(&shadow_ray, scene, light_record.get_t());
+    if is_black(&emitted) || light_pdf <= T::zero() {
+        return RGBColor::zeros();
+    }
+    let material = hit_record.get_material();
+    let brdf = material.brdf(ray, hit_record, &shadow_ray);
+    let scattering_pdf = material.scattering_pdf(ray, hit_record, &shadow_ray);
+    let cosine = direction.unit_vector().inner_product(&hit_record.get_normal_vector()).abs();
+    emitted * brdf * (cosine * power_heuristic(light_pdf, scattering_pdf) / light_pdf)
 }
 
+// Density of light sampling choosing the direction of `ray`: a light picked uniformly, then a
+// direction towards it. Only lights the ray reaches at distance `t` count, as sampling any other
+// light leads to a different point.
+fn light_pdf<T: DataTypeTraits>(ray: &Ray3D<T>, scene: &Scene<T>, t: T) -> T {
+    if scene.lights.is_empty() {
+        return T::zero();
+    }
+    let total = scene.lights.iter()
+        .filter(|light| hit_at(light.as_ref(), ray, t))
+        .fold(T::zero(), |total, light| total + light.pdf_value(&ray.origin, &ray.direction, ray.time));
+    total / T::from(scene.lights.len()).unwrap()
+}
+
+// True if `ray` hits `light` at distance `t`
+#[inline(always)]
+fn hit_at<T: DataTypeTraits>(light: &(dyn Hittable<T> + Send + Sync), ray: &Ray3D<T>, t: T) -> bool {
+    let tolerance = T::from(1e-4).unwrap() * t.max(T::one());
+    light.hit(ray, t - tolerance, t + tolerance, &mut HitRecord::default())
+}
+
+// Weight of a sample drawn with density `pdf` against one drawn with density `other_pdf`
+#[inline(always)]
+fn power_heuristic<T: DataTypeTraits>(pdf: T, other_pdf: T) -> T {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > T::zero() { a / (a + b) } else { T::zero() }
+}
 
-/// Generates a random number of type `T` within the given range. If no range is specified, the random number
-/// will be generated within the maximum value of type `T`. The function returns a random number of type `T`.
+#[inline(always)]
+fn is_black<T: DataTypeTraits>(color: &RGBColor<T>) -> bool {
+    color.R <= T::zero() && color.G <= T::zero() && color.B <= T::zero()
+}
+
+
+/// Generates a random number of type `T` in [min_value, max_value[, drawn from `rng`. Passing the
+/// generator explicitly keeps the numbers reproducible: the same seed gives the same sequence.
 ///
 /// # Arguments
 ///
-/// * `min_value`: An optional parameter of type `Option<T>`. If specified, represents the minimum value of the range.
-/// * `max_value`: An optional parameter of type `Option<T>`. If specified, represents the maximum value of the range.
-///
-/// # Panics
-///
-/// The function will panic if `T` is not of type `f32` or `f64`.
+/// * `min_value`: The lower bound of the range.
+/// * `max_value`: The upper bound of the range.
+/// * `rng`: The generator to draw from.
 ///
 /// # Examples
 ///
 /// Generating a random float between 0.0 and 1.0:
 ///
 /// ```
-/// let rand_num = generate_random_number::<f32>(Some(0.0), Some(1.0));
-/// ```
-///
-/// Generating a random float with no specified range (random float within maximum value of `f64`):
-///
-/// ```
-/// let rand_num = generate_random_number::<f64>(None, None);
+/// let mut rng = fastrand::Rng::with_seed(42);
+/// let rand_num = utilities_lib::generate_random_uniform::<f32>(0.0, 1.0, &mut rng);
 /// ```
 #[inline(always)]
-pub fn generate_random_uniform<T: DataTypeTraits>(min_value: T, max_value: T) -> T {
-    let rng = fastrand::Rng::new();
-    if std::mem::size_of::<T>() == std::mem::size_of::<f32>() {
-        let random_float = T::from(rng.f32()).unwrap();
-         return min_value + random_float * (max_value - min_value);
-        }
-    else {
-        let random_float = T::from(rng.f64()).unwrap();
-        return min_value + random_float * (max_value - min_value);
-    }
+pub fn generate_random_uniform<T: DataTypeTraits>(min_value: T, max_value: T, rng: &mut fastrand::Rng) -> T {
+    min_value + T::from(rng.f64()).unwrap() * (max_value - min_value)
 }
 
 
+// Normally distributed random number drawn from `rng` (Box-Muller transform)
 #[inline(always)]
-pub fn generate_random_gaussian<T: DataTypeTraits>(mean: T, std_dev: T) -> T {
-    if std::mem::size_of::<T>() == std::mem::size_of::<f32>() {
-        let normal_dist = Normal::new(mean.to_f32().unwrap(), std_dev.to_f32().unwrap()).unwrap();
-        return T::from(normal_dist.sample(&mut rand::thread_rng())).unwrap();
-    }
-    else {
-        let normal_dist = Normal::new(mean.to_f64().unwrap(), std_dev.to_f64().unwrap()).unwrap();
-        return T::from(normal_dist.sample(&mut rand::thread_rng())).unwrap();
-    }
+pub fn generate_random_gaussian<T: DataTypeTraits>(mean: T, std_dev: T, rng: &mut fastrand::Rng) -> T {
+    // 1 - u lies in ]0, 1], keeping the logarithm finite
+    let radius = (-2.0 * (1.0 - rng.f64()).ln()).sqrt();
+    let angle = std::f64::consts::TAU * rng.f64();
+    mean + std_dev * T::from(radius * angle.cos()).unwrap()
 }
 
 
 
 
-pub fn write_color<T: DataTypeTraits>(mut file: &std::fs::File, color_sum: RGBColor<T>, samples_per_pixel: u32) -> std::io::Result<()>
-{
-    let factor: f64 = 256.0;
-    // Divide the color by the number of samples and gamma-correct for gamma=2.0 by taking sqrt.
-    let scale: f64 = 1.0 / samples_per_pixel as f64;
-    let r = (scale * color_sum.R.to_f64().unwrap()).sqrt();
-    let g = (scale * color_sum.G.to_f64().unwrap()).sqrt();
-    let b = (scale * color_sum.B.to_f64().unwrap()).sqrt();
-    let integer_red   = (factor * clamp(r,0.0, 0.999)) as i32;
-    let integer_green = (factor * clamp(g,0.0, 0.999)) as i32;
-    let integer_blue  = (factor * clamp(b,0.0, 0.999)) as i32;
-    writeln!(file, "{} {} {}", integer_red, integer_green, integer_blue)?;
-    Ok(())
-}
-
 #[inline(always)]
 pub fn degrees_to_radians<T: DataTypeTraits>(degrees: T) -> T {
     degrees * T::from(F32_PI).unwrap() / T::from(180.0).unwrap()
 }
 
 
-pub fn convert_to_png(file_name: &str) -> Result<(), String> {
-    let output_file_name = format!("{}.png", &file_name[..file_name.len() - 4]);
-    let command = format!("pnmtopng {} > {}", file_name, output_file_name);
-    let output = std::process::Command::new("sh")
-        .arg("-c")
-        .arg(&command)
-        .output()
-        .map_err(|e| format!("failed to execute process 1: {}", e))?;
+// True if the vector is close to zero in all dimensions
+#[inline(always)]
+pub fn near_zero<T: DataTypeTraits>(vector: &Vector3D<T>) -> bool
+{
+    let epsilon = T::from(1e-8).unwrap();
+    vector.x.abs() < epsilon && vector.y.abs() < epsilon && vector.z.abs() < epsilon
+}
 
-    if !output.status.success() {
-        return Err(format!("process exited with code {}", output.status));
-    }
-    let command = format!("rm {}", file_name);
-    let output = std::process::Command::new("sh")
-        .arg("-c")
-        .arg(&command)
-        .output()
-        .map_err(|e| format!("failed to execute process 2: {}", e))?;
-
-    if !output.status.success() {
-        return Err(format!("process exited with code {}", output.status));
-    }
 
-    Ok(())
+// Mirror reflection of `vector` about the (unit) surface normal
+#[inline(always)]
+pub fn reflect<T: DataTypeTraits>(vector: &Vector3D<T>, normal: &Vector3D<T>) -> Vector3D<T>
+{
+    vector - normal * (T::from(2.0).unwrap() * vector.inner_product(normal))
 }
 
 
-
+// Refraction of the unit vector `unit_vector` through a surface with (unit) normal `normal`,
+// following Snell's law with `etai_over_etat` being the ratio of refractive indices.
 #[inline(always)]
-// To achieve true Lambertian reflection
-pub fn random_uniform_unit_sphere_point<T: DataTypeTraits>(method: &str) -> Vector3D<T>{
-    if method == "Acceptance-Rejection" {
-        'outer: loop {
-            let mut coordinates: Vec<T> = Vec::new();
-            let mut r_squared = T::zero();
-            for _coord in 0..3{
-                let x_i = generate_random_uniform(-T::one(), T::one());
-                r_squared  = r_squared + x_i * x_i;
-                coordinates.push(x_i);
-                if r_squared >= T::one() {
-                    continue 'outer;
-                }
-            }
-            return Vector3D::<T>{x: coordinates[0],y: coordinates[1],z: coordinates[2]}.unit_vector();
-        }
-        }
-    else if method == "Inverse-CDF"{
-        let theta = T::from(2.0).unwrap() * T::PI() * generate_random_uniform(T::zero(), T::one()) - T::PI();
-        let phi = (T::one() - T::from(2.0).unwrap() as T * generate_random_uniform(T::zero(), T::one())).acos();
-        return Vector3D::<T>{ x: phi.sin() * theta.cos(),
-                              y: phi.sin() * theta.sin(),
-                              z: phi.cos() }
-    }
-    else if method == "Gaussian-Sampling"{
-            let mean = T::zero();
-            let std_dev = T::from(1.0/3.0_f32.sqrt()).unwrap();
-            let x = generate_random_gaussian(mean,std_dev);
-            let y = generate_random_gaussian(mean,std_dev);
-            let z = generate_random_gaussian(mean,std_dev);
-            return Vector3D::<T>{ x: x,
-                                  y: y,
-                                  z: z }.unit_vector();
-        }
-    else if method == "Native" {
-        let v: [T; 3] = rand_distr::UnitSphere.sample(&mut rand::thread_rng());
-        return Vector3D::<T>{x: v[0],y: v[1],z: v[2]}
-    }
-    else {
-        panic!("Unsupported method.")
-    }
+pub fn refract<T: DataTypeTraits>(unit_vector: &Vector3D<T>, normal: &Vector3D<T>, etai_over_etat: T) -> Vector3D<T>
+{
+    let cos_theta = (-unit_vector).inner_product(normal).min(T::one());
+    let r_out_perp = (unit_vector + normal * cos_theta) * etai_over_etat;
+    let r_out_parallel = normal * (-(T::one() - r_out_perp.inner_product(&r_out_perp)).abs().sqrt());
+    r_out_perp + r_out_parallel
 }
 
 
@@ -1,5 +1,3 @@
-use rand::distributions::{Distribution, Uniform};
-
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 ///////////////////////////////////// DATA TRAIT DEFINITIONS ///////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -11,7 +9,7 @@ pub trait DataTypeTraits:
         num_traits::Float + std::fmt::Display + std::fmt::Debug +
         std::marker::Copy + std::default::Default + num_traits::Zero +
         num_traits::One + rand::distributions::uniform::SampleUniform +
-        std::clone::Clone + num_traits::float::FloatConst + std::marker::Send{
+        std::clone::Clone + num_traits::float::FloatConst + std::marker::Send + std::marker::Sync + 'static{
         // we'd usually add more functions in this block,
         // but in this case we don't need any more.
 }
@@ -21,7 +19,7 @@ impl<T> DataTypeTraits for T
     where T: num_traits::Float + std::fmt::Display + std::fmt::Debug +
     std::marker::Copy + std::default::Default + num_traits::Zero +
     num_traits::One + rand::distributions::uniform::SampleUniform +
-    std::clone::Clone + num_traits::float::FloatConst + std::marker::Send{
+    std::clone::Clone + num_traits::float::FloatConst + std::marker::Send + std::marker::Sync + 'static{
     // Nothing to implement, since T already supports the other traits.
     // It has the functions it needs already
 }
@@ -91,6 +89,22 @@ pub struct Vector3D<T: DataTypeTraits> {
     pub z: T
 }
 
+// Orthonormal basis (u, v, w), e.g. a tangent frame around a surface normal w. Local coordinates
+// (x, y, z) stand for the direction x * u + y * v + z * w.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct OrthonormalBasis<T: DataTypeTraits> {
+    pub u: Vector3D<T>,
+    pub v: Vector3D<T>,
+    pub w: Vector3D<T>
+}
+
+// 4x4 matrix of an affine transform, stored row by row and applied to column vectors (so in
+// `a * b` the transform `b` is applied first)
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct Matrix4<T: DataTypeTraits> {
+    pub rows: [[T; 4]; 4]
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////////
 //////////////////////////////////////// IMPL DEFINITIONS //////////////////////////////////////////
 ////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -172,37 +186,16 @@ impl<T: DataTypeTraits> Vector2D<T>
 }
 
 
-// Implementing Vector2D<T> with random uniform in [low,high[ initialization through <T>::random_uniform()
+// Implementing Vector2D<T> with random uniform in [low,high[ initialization through <T>::random_uniform(), drawn from `rng`
 impl<T: DataTypeTraits> Vector2D<T>
 {
-    pub fn random_uniform(lower_bound: T, upper_bound: T) -> Vector2D<T> {
+    pub fn random_uniform(lower_bound: T, upper_bound: T, rng: &mut fastrand::Rng) -> Vector2D<T> {
         if upper_bound <= lower_bound {
             panic!("Upper bound cannot be less than or equal to lower bound");
         }
-        let distribution = Uniform::try_from(lower_bound..upper_bound).unwrap();
-        let mut rng = rand::thread_rng();
-        Vector2D { x: distribution.sample(&mut rng),
-                   y: distribution.sample(&mut rng)}
-    }
-}
-
-
-// Implementing Vector2D<T> with random uniform in [low,high[ initialization through <T>::fast_random_uniform()
-impl<T: DataTypeTraits> Vector2D<T>
-{
-    pub fn fast_random_uniform(lower_bound: T, upper_bound: T) -> Vector2D<T> {
-        if upper_bound <= lower_bound {
-            panic!("Upper bound cannot be less than or equal to lower bound");
-        }
-        let rng = fastrand::Rng::new();
-        if std::mem::size_of::<T>() == std::mem::size_of::<f32>() {
-            Vector2D { x: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound),
-                       y: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound)}
-        }
-        else {
-            Vector2D { x: lower_bound + T::from(rng.f64()).unwrap() * (upper_bound - lower_bound),
-                       y: lower_bound + T::from(rng.f64()).unwrap() * (upper_bound - lower_bound)}
-        }
+        let draw = || lower_bound + T::from(rng.f64()).unwrap() * (upper_bound - lower_bound);
+        Vector2D { x: draw(),
+                   y: draw()}
     }
 }
 
@@ -457,44 +450,21 @@ impl<T: DataTypeTraits> Vector3D<T>
 }
 
 
-// Implementing Vector3D<T> with random uniform in [low,high[ initialization through <T>::random_uniform()
-impl<T: DataTypeTraits> Vector3D<T>
-{
-    pub fn random_uniform(lower_bound: T, upper_bound: T) -> Vector3D<T> {
-        if upper_bound <= lower_bound {
-            panic!("Upper bound cannot be less than or equal to lower bound");
-        }
-        let distribution = Uniform::try_from(lower_bound..upper_bound).unwrap();
-        let mut rng = rand::thread_rng();
-        Vector3D { x: distribution.sample(&mut rng),
-                   y: distribution.sample(&mut rng),
-                   z: distribution.sample(&mut rng)}
-    }
-}
-
-// Implementing Vector3D<T> with random uniform in [low,high[ initialization through <T>::fast_random_uniform()
+// Implementing Vector3D<T> with random uniform in [low,high[ initialization through <T>::random_uniform(), drawn from `rng`
 impl<T: DataTypeTraits> Vector3D<T>
 {
-    pub fn fast_random_uniform(lower_bound: T, upper_bound: T) -> Vector3D<T> {
+    pub fn random_uniform(lower_bound: T, upper_bound: T, rng: &mut fastrand::Rng) -> Vector3D<T> {
         if upper_bound <= lower_bound {
             panic!("Upper bound cannot be less than or equal to lower bound");
         }
-        let rng = fastrand::Rng::new();
-        if std::mem::size_of::<T>() == std::mem::size_of::<f32>() {
-            Vector3D { x: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound),
-                       y: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound),
-                       z: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound)}
-        }
-        else {
-            Vector3D { x: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound),
-                       y: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound),
-                       z: lower_bound + T::from(rng.f32()).unwrap() * (upper_bound - lower_bound)}
-        }
+        let draw = || lower_bound + T::from(rng.f64()).unwrap() * (upper_bound - lower_bound);
+        Vector3D { x: draw(),
+                   y: draw(),
+                   z: draw()}
     }
 }
 
 
-
 //++++++++++++++++++++++++++++++++++++++++ Addition ++++++++++++++++++++++++++++++++++++++++++++ //
 // Implementing Vector3D<T> + Vector3D<T> -> Vector3D<T> type addition
 impl<T: std::ops::Add<Output = T> + DataTypeTraits> std::ops::Add<Vector3D<T>> for Vector3D<T> {
@@ -684,6 +654,30 @@ impl<'a, T: std::ops::Div<Output = T> + DataTypeTraits> std::ops::Div<T> for &'a
     }
 }
 
+//~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~ Negation ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~//
+
+// Implementing -Vector3D<T> -> Vector3D<T> type negation
+impl<T: std::ops::Neg<Output = T> + DataTypeTraits> std::ops::Neg for Vector3D<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self::Output {
+        Self { x: -self.x,
+               y: -self.y,
+               z: -self.z}
+    }
+}
+
+// Implementing -&Vector3D<T> -> Vector3D<T> type negation
+impl<'a, T: std::ops::Neg<Output = T> + DataTypeTraits> std::ops::Neg for &'a Vector3D<T> {
+    type Output = Vector3D<T>;
+    #[inline(always)]
+    fn neg(self) -> Self::Output {
+        Vector3D { x: -self.x,
+                   y: -self.y,
+                   z: -self.z}
+    }
+}
+
 // ===================================  VectorOperations impl =================================== //
 
 impl<T: DataTypeTraits> VectorOperations<T> for Vector3D<T> {
@@ -699,9 +693,234 @@ impl<T: DataTypeTraits> VectorOperations<T> for Vector3D<T> {
 // Implementing Vector3D<T>.cross_product(Vector3D<T>)
 impl<T: std::ops::Mul<Output = T> + DataTypeTraits> Vector3D<T>{
     #[inline(always)]
-    fn cross_product(&self, other: &Self) -> Vector3D<T> {
+    pub fn cross_product(&self, other: &Self) -> Vector3D<T> {
         Self { x: self.y * other.z - self.z * other.y,
                y: self.z * other.x - self.x * other.z,
                z: self.x * other.y - self.y * other.x}
     }
 }
+
+
+
+/************************************** ORTHONORMAL BASIS *****************************************/
+
+
+// Implementing OrthonormalBasis<T> initialization through <T>::new(), from three orthonormal vectors
+impl<T: DataTypeTraits> OrthonormalBasis<T>
+{
+    pub fn new(u: Vector3D<T>, v: Vector3D<T>, w: Vector3D<T>) -> Self {
+        OrthonormalBasis { u: u, v: v, w: w }
+    }
+}
+
+// Implementing OrthonormalBasis<T> initialization around a unit vector through <T>::from_w(). The
+// basis is right-handed, with u and v a continuous choice (Duff et al., "Building an Orthonormal
+// Basis, Revisited").
+impl<T: DataTypeTraits> OrthonormalBasis<T>
+{
+    pub fn from_w(w: &Vector3D<T>) -> Self {
+        let sign = T::one().copysign(w.z);
+        let a = -T::one() / (sign + w.z);
+        let b = w.x * w.y * a;
+        OrthonormalBasis { u: Vector3D { x: T::one() + sign * w.x * w.x * a, y: sign * b, z: -sign * w.x },
+                           v: Vector3D { x: b, y: sign + w.y * w.y * a, z: -w.y },
+                           w: *w }
+    }
+}
+
+// Implementing OrthonormalBasis<T>.to_world() and .to_local(), converting directions between
+// world coordinates and coordinates in the basis
+impl<T: DataTypeTraits> OrthonormalBasis<T>
+{
+    #[inline(always)]
+    pub fn to_world(&self, local: &Vector3D<T>) -> Vector3D<T> {
+        self.u * local.x + self.v * local.y + self.w * local.z
+    }
+
+    #[inline(always)]
+    pub fn to_local(&self, world: &Vector3D<T>) -> Vector3D<T> {
+        Vector3D { x: world.inner_product(&self.u),
+                   y: world.inner_product(&self.v),
+                   z: world.inner_product(&self.w) }
+    }
+}
+
+
+/******************************************** 4x4 MATRIX ******************************************/
+
+
+// Implementing Matrix4<T> initialization through <T>::from_rows()
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    pub fn from_rows(rows: [[T; 4]; 4]) -> Self {
+        Matrix4 { rows: rows }
+    }
+}
+
+// Implementing Matrix4<T> identity initialization through <T>::identity()
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    pub fn identity() -> Self {
+        let mut rows = [[T::zero(); 4]; 4];
+        for (index, row) in rows.iter_mut().enumerate() {
+            row[index] = T::one();
+        }
+        Matrix4 { rows: rows }
+    }
+}
+
+// Implementing Matrix4<T> initialization through <T>::default(), giving the identity
+impl<T: DataTypeTraits> Default for Matrix4<T> {
+    fn default() -> Self {
+        Matrix4::identity()
+    }
+}
+
+// Implementing translation by `offset` through <T>::translation()
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    pub fn translation(offset: &Vector3D<T>) -> Self {
+        let mut matrix = Matrix4::identity();
+        matrix.rows[0][3] = offset.x;
+        matrix.rows[1][3] = offset.y;
+        matrix.rows[2][3] = offset.z;
+        matrix
+    }
+}
+
+// Implementing scaling by `factors` along the coordinate axes through <T>::scaling()
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    pub fn scaling(factors: &Vector3D<T>) -> Self {
+        let mut matrix = Matrix4::identity();
+        matrix.rows[0][0] = factors.x;
+        matrix.rows[1][1] = factors.y;
+        matrix.rows[2][2] = factors.z;
+        matrix
+    }
+}
+
+// Implementing counterclockwise rotation by `angle` radians around `axis` (Rodrigues' formula)
+// through <T>::rotation()
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    pub fn rotation(axis: &Vector3D<T>, angle: T) -> Self {
+        let axis = axis.unit_vector();
+        let (sin, cos) = angle.sin_cos();
+        let one_minus_cos = T::one() - cos;
+        let (x, y, z) = (axis.x, axis.y, axis.z);
+        Matrix4 { rows: [[cos + x * x * one_minus_cos, x * y * one_minus_cos - z * sin, x * z * one_minus_cos + y * sin, T::zero()],
+                         [y * x * one_minus_cos + z * sin, cos + y * y * one_minus_cos, y * z * one_minus_cos - x * sin, T::zero()],
+                         [z * x * one_minus_cos - y * sin, z * y * one_minus_cos + x * sin, cos + z * z * one_minus_cos, T::zero()],
+                         [T::zero(), T::zero(), T::zero(), T::one()]] }
+    }
+}
+
+//++++++++++++++++++++++++++++++++++++ Matrix Multiplication +++++++++++++++++++++++++++++++++++++ //
+// Implementing &Matrix4<T> * &Matrix4<T> -> Matrix4<T> type multiplication
+impl<'a, T: DataTypeTraits> std::ops::Mul<&'a Matrix4<T>> for &'a Matrix4<T> {
+    type Output = Matrix4<T>;
+    #[inline(always)]
+    fn mul(self, other: &'a Matrix4<T>) -> Self::Output {
+        let mut rows = [[T::zero(); 4]; 4];
+        for (row, result) in rows.iter_mut().enumerate() {
+            for (column, value) in result.iter_mut().enumerate() {
+                *value = (0..4).fold(T::zero(), |sum, k| sum + self.rows[row][k] * other.rows[k][column]);
+            }
+        }
+        Matrix4 { rows: rows }
+    }
+}
+
+// Implementing Matrix4<T> * Matrix4<T> -> Matrix4<T> type multiplication
+impl<T: DataTypeTraits> std::ops::Mul<Matrix4<T>> for Matrix4<T> {
+    type Output = Matrix4<T>;
+    #[inline(always)]
+    fn mul(self, other: Matrix4<T>) -> Self::Output {
+        &self * &other
+    }
+}
+
+// =================================== Special Matrix4 impls ==================================== //
+
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    pub fn transpose(&self) -> Matrix4<T> {
+        let mut rows = [[T::zero(); 4]; 4];
+        for (row, result) in rows.iter_mut().enumerate() {
+            for (column, value) in result.iter_mut().enumerate() {
+                *value = self.rows[column][row];
+            }
+        }
+        Matrix4 { rows: rows }
+    }
+}
+
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    // Inverse through Gauss-Jordan elimination with partial pivoting, None for singular matrices
+    pub fn inverse(&self) -> Option<Matrix4<T>> {
+        let mut matrix = self.rows;
+        let mut inverse = Matrix4::identity().rows;
+        for column in 0..4 {
+            let pivot = (column..4).max_by(|&a, &b| matrix[a][column].abs().partial_cmp(&matrix[b][column].abs())
+                                                                       .unwrap_or(std::cmp::Ordering::Equal))?;
+            if matrix[pivot][column] == T::zero() || !matrix[pivot][column].is_finite() {
+                return None;
+            }
+            matrix.swap(column, pivot);
+            inverse.swap(column, pivot);
+            let scale = T::one() / matrix[column][column];
+            for k in 0..4 {
+                matrix[column][k] = matrix[column][k] * scale;
+                inverse[column][k] = inverse[column][k] * scale;
+            }
+            for row in 0..4 {
+                let factor = matrix[row][column];
+                if row == column || factor == T::zero() {
+                    continue;
+                }
+                for k in 0..4 {
+                    matrix[row][k] = matrix[row][k] - factor * matrix[column][k];
+                    inverse[row][k] = inverse[row][k] - factor * inverse[column][k];
+                }
+            }
+        }
+        Some(Matrix4 { rows: inverse })
+    }
+}
+
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    // Determinant of the upper-left 3x3 block; negative for transforms that mirror space
+    pub fn linear_determinant(&self) -> T {
+        let m = &self.rows;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+}
+
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    // Applies the transform to a point (w = 1)
+    #[inline(always)]
+    pub fn transform_point(&self, point: &Vector3D<T>) -> Vector3D<T> {
+        let m = &self.rows;
+        Vector3D { x: m[0][0] * point.x + m[0][1] * point.y + m[0][2] * point.z + m[0][3],
+                   y: m[1][0] * point.x + m[1][1] * point.y + m[1][2] * point.z + m[1][3],
+                   z: m[2][0] * point.x + m[2][1] * point.y + m[2][2] * point.z + m[2][3]}
+    }
+}
+
+impl<T: DataTypeTraits> Matrix4<T>
+{
+    // Applies the transform to a direction (w = 0), which ignores the translation
+    #[inline(always)]
+    pub fn transform_vector(&self, vector: &Vector3D<T>) -> Vector3D<T> {
+        let m = &self.rows;
+        Vector3D { x: m[0][0] * vector.x + m[0][1] * vector.y + m[0][2] * vector.z,
+                   y: m[1][0] * vector.x + m[1][1] * vector.y + m[1][2] * vector.z,
+                   z: m[2][0] * vector.x + m[2][1] * vector.y + m[2][2] * vector.z}
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+
+use image_lib::ImageFormat;
+
+use renderer_lib::Tile;
+
+use sampler_lib::SamplerKind;
+
+// Command-line interface of the render binary. Values given here override the ones of the scene file.
+#[derive(Parser, Debug)]
+#[command(name = "main", version, about = "Renders a scene description file with a path tracer")]
+pub struct Cli
+{
+    /// Scene description file (TOML)
+    pub scene: PathBuf,
+
+    /// Image width in pixels (keeps the aspect ratio of the scene file if --height is not given)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub width: Option<u32>,
+
+    /// Image height in pixels (keeps the aspect ratio of the scene file if --width is not given)
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub height: Option<u32>,
+
+    /// Samples per pixel
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub spp: Option<u32>,
+
+    /// Maximum number of ray bounces
+    #[arg(long, value_parser = clap::value_parser!(i32).range(1..))]
+    pub max_depth: Option<i32>,
+
+    /// Number of render threads (0 uses all cores)
+    #[arg(long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// Seed of the random numbers. Renders with the same seed and settings are bit-identical,
+    /// whatever the number of threads.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Sample generator (defaults to the one of the scene file)
+    #[arg(long, value_enum)]
+    pub sampler: Option<SamplerChoice>,
+
+    /// Output image path (defaults to renders/<scene name>.png)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Output image format (defaults to the one given by the extension of the output path, where
+    /// .png always means 8-bit PNG: 16-bit PNGs need --format png16)
+    #[arg(long, value_enum)]
+    pub format: Option<OutputFormat>,
+
+    /// Only render (and write) the pixel window [x0, x1[ x [y0, y1[, given as x0,y0,x1,y1
+    #[arg(long, value_parser = parse_crop_window)]
+    pub crop: Option<Tile>,
+
+    /// Do not show the progress bar or timing information
+    #[arg(short, long)]
+    pub quiet: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum OutputFormat {
+    /// 8-bit PNG
+    Png,
+    /// 16-bit PNG (never picked from the .png extension)
+    Png16,
+    /// Binary PPM (P6)
+    Ppm,
+    /// Radiance HDR
+    Hdr,
+}
+
+impl From<OutputFormat> for ImageFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Png => ImageFormat::Png8,
+            OutputFormat::Png16 => ImageFormat::Png16,
+            OutputFormat::Ppm => ImageFormat::Ppm,
+            OutputFormat::Hdr => ImageFormat::Hdr,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum SamplerChoice {
+    /// Independent uniform random numbers
+    Independent,
+    /// Jittered strata
+    Stratified,
+    /// Owen-scrambled Halton sequence
+    Halton,
+    /// Owen-scrambled Sobol sequence
+    Sobol,
+    /// Correlated multi-jittered sampling
+    Cmj,
+}
+
+impl From<SamplerChoice> for SamplerKind {
+    fn from(sampler: SamplerChoice) -> Self {
+        match sampler {
+            SamplerChoice::Independent => SamplerKind::Independent,
+            SamplerChoice::Stratified => SamplerKind::Stratified,
+            SamplerChoice::Halton => SamplerKind::Halton,
+            SamplerChoice::Sobol => SamplerKind::Sobol,
+            SamplerChoice::Cmj => SamplerKind::CorrelatedMultiJitter,
+        }
+    }
+}
+
+fn parse_crop_window(value: &str) -> Result<Tile, String> {
+    let coordinates: Vec<usize> = value.split(',')
+        .map(|coordinate| coordinate.trim().parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("expected four non-negative integers x0,y0,x1,y1 ({})", e))?;
+    if coordinates.len() != 4 {
+        return Err(format!("expected four values x0,y0,x1,y1 but got {}", coordinates.len()));
+    }
+    let window = Tile { x0: coordinates[0], y0: coordinates[1], x1: coordinates[2], y1: coordinates[3] };
+    if window.x0 >= window.x1 || window.y0 >= window.y1 {
+        return Err("crop window must satisfy x0 < x1 and y0 < y1".to_string());
+    }
+    Ok(window)
+}
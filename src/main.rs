@@ -1,15 +1,15 @@
+mod cli;
+
 use std::time::Instant;
 
-use std::io::Write;
-use camera_lib::Camera;
+use std::path::PathBuf;
 
-use ray_lib::Ray3D;
+use clap::{CommandFactory, Parser};
 
-use vector_lib::Vector3D;
+use cli::Cli;
+use scene_loader_lib::SceneFile;
 
-use color_lib::RGBColor;
-use scene_lib::Scene;
-use sphere_lib::Sphere;
+use sampler_lib::SamplerKind;
 
 
 
@@ -18,68 +18,78 @@ use sphere_lib::Sphere;
 
 
 
-fn main() -> std::io::Result<()> {
+////////////////////////////////////////////////////////////////////////////////////////////////////
+//////////////////////// RENDERING THE SCENE FILE GIVEN ON THE COMMAND LINE ////////////////////////
+////////////////////////////////////////////////////////////////////////////////////////////////////
 
+fn main() {
+    let cli = Cli::parse();
 
+    // Loading scene, camera and render settings from the scene description file
+    let mut scene_file: SceneFile<f64> = match scene_loader_lib::load_scene_file(&cli.scene) {
+        Ok(scene_file) => scene_file,
+        Err(e) => {
+            eprintln!("Error in {}: {}", cli.scene.display(), e);
+            std::process::exit(1);
+        }
+    };
+    for warning in scene_file.warnings.iter() {
+        eprintln!("Warning in {}: {}", cli.scene.display(), warning);
+    }
 
-////////////////////////////////////////////////////////////////////////////////////////////////////
-//// ANTI-ALIASING GRADIENT W. GRADIENT BALL ON GROUND + DIFFUSION + TRUE LAMBERTIAN REFLECTION ////
-////////////////////////////////////////////////////////////////////////////////////////////////////
+    // Overriding the settings of the scene file with the ones given on the command line
+    let file_width = scene_file.settings.width;
+    let file_height = scene_file.settings.height;
+    let (width, height) = match (cli.width, cli.height) {
+        (Some(width), Some(height)) => (width as usize, height as usize),
+        (Some(width), None) => (width as usize, ((width as usize * file_height) as f64 / file_width as f64).round().max(1.0) as usize),
+        (None, Some(height)) => (((height as usize * file_width) as f64 / file_height as f64).round().max(1.0) as usize, height as usize),
+        (None, None) => (file_width, file_height),
+    };
+    if (width, height) != (file_width, file_height) {
+        scene_file.set_resolution(width, height);
+    }
+    let settings = &mut scene_file.settings;
+    settings.samples_per_pixel = cli.spp.unwrap_or(settings.samples_per_pixel);
+    settings.max_depth = cli.max_depth.unwrap_or(settings.max_depth);
+    settings.threads = cli.threads;
+    settings.seed = cli.seed;
+    settings.sampler = cli.sampler.map_or(settings.sampler, SamplerKind::from);
+    if let Some(window) = cli.crop {
+        if window.x1 > width || window.y1 > height {
+            Cli::command().error(clap::error::ErrorKind::ValueValidation,
+                                 format!("crop window {},{},{},{} is outside the {}x{} image",
+                                         window.x0, window.y0, window.x1, window.y1, width, height)).exit();
+        }
+        settings.crop = Some(window);
+    }
 
-    // Defining camera for scene
-    const ORIGIN_4: Vector3D<f64> = Vector3D{ x: 0.0_f64, y: 0.0_f64, z: 0.0_f64};
-    const VIEWPORT_HEIGHT_4: f64 = 2.0;
-    const FOCAL_LENGTH_4: f64 = 1.0;
-    const ASPECT_RATIO_4: f64 = 16.0 / 9.0;
-    const IMG_WIDTH_4: i32 = 600;
-    const IMG_HEIGHT_4: i32 = (IMG_WIDTH_4 as f64 / ASPECT_RATIO_4) as i32;
-    const SAMPLES_PER_PIXEL_4: i32 = 300;
-    const MAX_DEPTH_4: i32 = 50;
-
-    let camera: Camera<f64> = Camera::new(ASPECT_RATIO_4, VIEWPORT_HEIGHT_4,
-                                          FOCAL_LENGTH_4, ORIGIN_4);
-    // Creating balls
-    let center_1 = Vector3D{x:0.0_f64, y:0.0_f64, z: -1.0_f64};
-    let radius_1 = 0.5_f64;
-    let sphere_1: Sphere<f64> = Sphere::new(center_1, radius_1);
-
-    // Creating large ball to emulate ground
-    let ground: Sphere<f64> = Sphere::new(Vector3D{x:0.0_f64, y:-100.5_f64, z: -1.0_f64}, 100.0_f64);
-
-    // Creating scene
-    let mut scene = Scene::default();
-    scene.add(Box::new(sphere_1));
-    scene.add(Box::new(ground));
-
-
-    // Creating new file
-    let file_name = "renders/lambertian_diffusion_antialiasing_gradient_w_gradient_ball_on_ground.ppm";
-    let mut file = std::fs::OpenOptions::new().create(true).write(true).open(file_name)?;
-    // Header info for .ppm file
-    write!(file, "P3\n{} {}\n255\n", IMG_WIDTH_4, IMG_HEIGHT_4)?;
-    // Progress bar
-    let bar = indicatif::ProgressBar::new(IMG_HEIGHT_4 as u64);
+    // Progress bar (hidden in quiet mode to keep batch logs clean)
+    let bar = if cli.quiet { indicatif::ProgressBar::hidden() } else { indicatif::ProgressBar::new(0) };
     let start = Instant::now();
-    for j in (0..IMG_HEIGHT_4).rev() {
-        bar.inc(1);
-        for i in 0..IMG_WIDTH_4 {
-            let mut color_sum: RGBColor<f64> = RGBColor{R: 0.0_f64, G: 0.0_f64, B: 0.0_f64};
-            for _sample in 0..SAMPLES_PER_PIXEL_4
-            {
-                let u: f64 = (f64::from(i) + utilities_lib::generate_random_uniform::<f64>(0.0_f64,1.0_f64))/ f64::from(IMG_WIDTH_4-1);
-                let v: f64 = (f64::from(j) + utilities_lib::generate_random_uniform::<f64>(0.0_f64,1.0_f64))/ f64::from(IMG_HEIGHT_4-1);
-                let ray: Ray3D<f64> = camera.get_ray(u,v);
-                color_sum = color_sum + utilities_lib::ray_color(&ray, &mut scene, MAX_DEPTH_4);
-            }
-            utilities_lib::write_color(&file, color_sum, SAMPLES_PER_PIXEL_4 as u32)?;
-        }
+    // Rendering tiles of the image in parallel into an in-memory film
+    let mut film = renderer_lib::render(&scene_file.scene, &scene_file.camera, &scene_file.settings, &bar);
+    if !cli.quiet {
+        println!("Time elapsed is: {:?}", start.elapsed());
     }
-    let duration = start.elapsed();
-    println!("Time elapsed is: {:?}", duration);
-    match utilities_lib::convert_to_png(file_name) {
-        Ok(_) => println!("Conversion successful!"),
-        Err(e) => eprintln!("Error: {}", e),
+    if let Some(window) = cli.crop {
+        film = film.crop(window.x0, window.y0, window.x1, window.y1);
     }
 
-    Ok(())
-}
\ No newline at end of file
+    // Writing image (format is chosen from the file extension unless given explicitly)
+    let file_name = cli.output.unwrap_or_else(|| {
+        let scene_name = cli.scene.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+        PathBuf::from("renders").join(format!("{}.png", scene_name))
+    });
+    let result = match cli.format {
+        Some(format) => image_lib::write_image_as(&file_name, format.into(), film.width(), film.height(), &film.resolve()),
+        None => image_lib::write_film(&file_name, &film),
+    };
+    match result {
+        Ok(_) => if !cli.quiet { println!("Image written to {}", file_name.display()) },
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}